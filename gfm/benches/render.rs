@@ -0,0 +1,49 @@
+//! Rendering benchmarks, run with `cargo bench -p gfm`.
+
+#![feature(test)]
+
+extern crate test;
+
+use gfm::Parser;
+use test::Bencher;
+
+/// Repeats `section` until the document is at least `len` bytes long.
+fn document(section: &str, len: usize) -> String {
+	section.repeat(len / section.len() + 1)
+}
+
+const PROSE: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, \
+                     sed do eiusmod tempor incididunt ut labore et dolore \
+                     magna aliqua.\nUt enim ad minim veniam, quis nostrud \
+                     exercitation ullamco laboris nisi ut aliquip ex ea \
+                     commodo consequat.\n\n";
+
+const INLINE: &str = "Some *emphasis*, **strong** and ~~struck~~ text \
+	with `code`, a [link](https://example.com \"title\") and an \
+	![image](img.png) next to www.example.com and foo@bar.baz.\n\n";
+
+const BLOCKS: &str = "# Heading\n\n> quoted *text*\n> more\n\n- item one\n- \
+                      item two\n  - nested\n\n1. first\n2. second\n\n| a | b \
+                      |\n| --- | :-: |\n| `c` | d |\n\n```rust\nfn main() \
+                      {}\n```\n\n---\n\n";
+
+#[bench]
+fn prose(b: &mut Bencher) {
+	let source = document(PROSE, 64 * 1024);
+	b.bytes = source.len() as u64;
+	b.iter(|| Parser::render(&source));
+}
+
+#[bench]
+fn inline_markup(b: &mut Bencher) {
+	let source = document(INLINE, 64 * 1024);
+	b.bytes = source.len() as u64;
+	b.iter(|| Parser::render(&source));
+}
+
+#[bench]
+fn block_structure(b: &mut Bencher) {
+	let source = document(BLOCKS, 64 * 1024);
+	b.bytes = source.len() as u64;
+	b.iter(|| Parser::render(&source));
+}
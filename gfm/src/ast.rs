@@ -0,0 +1,284 @@
+//! Owned document tree, built from the blocks of the first parsing phase and
+//! the inline tokens of the second one.
+//!
+//! Unlike the intermediate structures of the parser, the tree does not
+//! borrow from the source, so it can be inspected and rewritten, eg. with a
+//! [VisitorMut](crate::VisitorMut), before it is rendered.
+
+use crate::{
+	block::{
+		self,
+		Alignment,
+		ListKind,
+		TaskBox,
+		Text,
+	},
+	diagnostic::Diagnostic,
+	token::Token,
+	Options,
+	Parser,
+};
+
+/// A parsed markdown document.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Document {
+	/// Top level blocks, in the order they appear in the source
+	pub blocks: Vec<Block>,
+}
+
+/// A block of the document tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+pub enum Block {
+	/// Vec<Inline>: Content of the paragraph
+	Paragraph(Vec<Inline>),
+	/// usize: Header level (1..=6). Vec<Inline>: Content. Option<String>:
+	/// html id given as `{#id}`
+	Header(usize, Vec<Inline>, Option<String>),
+	/// Corresponds to a <hr /> html tag
+	HorizontalRule,
+	/// First String: Code. Second String: Info string, whose first word is
+	/// the language
+	CodeBlock(String, String),
+	/// Vec<Block>: Blocks inside the quote
+	BlockQuote(Vec<Block>),
+	/// ListKind: Marker of the list. bool: Whether the list is tight, so its
+	/// paragraphs are rendered without <p> tags. Vec<ListItem>: Items
+	List(ListKind, bool, Vec<ListItem>),
+	/// Vec<Option<Alignment>>: Alignment of every column. Vec<Vec<Inline>>:
+	/// Header cells. Vec<Vec<Vec<Inline>>>: Rows of cells. Cells without a
+	/// column are not rendered
+	Table(
+		Vec<Option<Alignment>>,
+		Vec<Vec<Inline>>,
+		Vec<Vec<Vec<Inline>>>,
+	),
+	/// Vec<Inline>: Content of the summary. Vec<Block>: Blocks rendered in
+	/// the collapsable section
+	Detail(Vec<Inline>, Vec<Block>),
+	/// String: Reference id. Vec<Block>: Reference content, rendered at the
+	/// end of the document
+	Footnote(String, Vec<Block>),
+	/// Custom: Line of a [BlockSyntax](crate::BlockSyntax)
+	Custom(Custom),
+	/// String: Lines of raw html, kept as far as the
+	/// [Sanitizer](crate::Sanitizer) allows
+	Html(String),
+}
+
+/// An item of a [Block::List].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListItem {
+	/// Checkbox of a task list item
+	pub task:     Option<TaskBox>,
+	pub children: Vec<Block>,
+}
+
+/// Inline content of a paragraph, heading or table cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inline {
+	/// String: Unstructured text
+	Text(String),
+	/// Vec<Inline>: Content to be emphasized. eg. <em>content</em>
+	Emphasis(Vec<Inline>),
+	/// Vec<Inline>: Content to be strongly emphasized. eg.
+	/// <strong>content</strong>
+	Strong(Vec<Inline>),
+	/// String: Text to be struck through
+	Strikethrough(String),
+	/// String: Text to be placed within an inline code tag. eg.
+	/// <code>String</code>
+	Code(String),
+	/// String: Destination. Vec<Inline>: Content of the link.
+	/// Option<String>: Hover text. Destinations that are not allowed, eg.
+	/// `javascript:`, are not linked
+	Link(String, Vec<Inline>, Option<String>),
+	/// First String: Destination. Second String: Text of the link, the
+	/// destination as it is written, eg. without the `mailto:` of an email
+	/// address
+	Autolink(String, String),
+	/// Image: Source, alternate text, title and size of the image
+	Image(Image),
+	/// String: Id of the [Block::Footnote] referenced as `[^id]`
+	FootnoteReference(String),
+	/// Corresponds to a </br> html tag
+	LineBreak,
+	/// Line ending inside a paragraph, rendered as a newline
+	SoftBreak,
+	/// Custom: Markup of an [InlineSyntax](crate::InlineSyntax)
+	Custom(Custom),
+	/// String: Raw html tag, comment or declaration, kept as far as the
+	/// [Sanitizer](crate::Sanitizer) allows
+	Html(String),
+}
+
+/// An image, eg. `![alt](source "title"){width=300}`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Image {
+	/// Destination of the image as it is written
+	pub source: String,
+	/// Alternate text, shown if the image cannot be
+	pub alt:    Option<String>,
+	/// Title, shown on hover or as the caption of a figure
+	pub title:  Option<String>,
+	/// Width in pixels given as `{width=300}`
+	pub width:  Option<u32>,
+	/// Height in pixels given as `{height=200}`
+	pub height: Option<u32>,
+}
+
+/// A node produced by a syntax extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Custom {
+	/// Name of the syntax that produced the node
+	pub name:     String,
+	/// What the syntax parsed besides the content, eg. the page a wikilink
+	/// points to
+	pub value:    String,
+	/// Inline content, eg. the text of a highlight
+	pub children: Vec<Inline>,
+}
+
+/// Lexes the inline content of every block into a document. Markup that is
+/// kept as text is added to `diagnostics`.
+pub(crate) fn build(
+	blocks: Vec<block::Block>,
+	options: &Options,
+	diagnostics: &mut Vec<Diagnostic>,
+) -> Document {
+	Document {
+		blocks: build_blocks(blocks, options, diagnostics),
+	}
+}
+
+fn build_blocks(
+	blocks: Vec<block::Block>,
+	options: &Options,
+	diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<Block> {
+	blocks
+		.into_iter()
+		.filter_map(|b| build_block(b, options, diagnostics))
+		.collect()
+}
+
+fn build_block(
+	block: block::Block,
+	options: &Options,
+	diagnostics: &mut Vec<Diagnostic>,
+) -> Option<Block> {
+	Some(match block {
+		block::Block::Paragraph(t) => {
+			Block::Paragraph(inlines(&t, options, diagnostics))
+		}
+		block::Block::Header(l, t, lbl) => {
+			Block::Header(l, inlines(&t, options, diagnostics), lbl)
+		}
+		block::Block::HorizontalRule => Block::HorizontalRule,
+		block::Block::CodeBlock(code, info) => Block::CodeBlock(code, info),
+		block::Block::BlockQuote(inner) => {
+			Block::BlockQuote(build_blocks(inner, options, diagnostics))
+		}
+		block::Block::List(kind, tight, items, _) => Block::List(
+			kind,
+			tight,
+			items
+				.into_iter()
+				.filter_map(|item| match item {
+					block::Block::ListItem(task, inner) => Some(ListItem {
+						task,
+						children: build_blocks(inner, options, diagnostics),
+					}),
+					_ => None,
+				})
+				.collect(),
+		),
+		// Items are only ever found inside of lists
+		block::Block::ListItem(..) => return None,
+		block::Block::Table(alignments, headings, rows) => Block::Table(
+			alignments,
+			headings
+				.iter()
+				.map(|cell| inlines(cell, options, diagnostics))
+				.collect(),
+			rows.iter()
+				.map(|row| {
+					row.iter()
+						.map(|cell| inlines(cell, options, diagnostics))
+						.collect()
+				})
+				.collect(),
+		),
+		block::Block::Detail(summary, inner) => Block::Detail(
+			inlines(&summary, options, diagnostics),
+			build_blocks(inner, options, diagnostics),
+		),
+		block::Block::Footnote(ref_id, inner) => {
+			Block::Footnote(ref_id, build_blocks(inner, options, diagnostics))
+		}
+		block::Block::Html(source) => Block::Html(source),
+		block::Block::Custom(name, value, content) => Block::Custom(Custom {
+			name,
+			value,
+			children: inlines(&content, options, diagnostics),
+		}),
+	})
+}
+
+/// Lexes the inline content of a leaf block. Diagnostics are added with
+/// spans in the source of the whole document.
+fn inlines(
+	text: &Text,
+	options: &Options,
+	diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<Inline> {
+	let mut found = Vec::new();
+	let tokens = Parser::lex_inline(
+		&text.text,
+		options.max_nesting,
+		options.on_parse_error,
+		options.extensions.inline_syntaxes(),
+		options.autolinks,
+		&mut found,
+	);
+	diagnostics.extend(found.into_iter().map(|mut d| {
+		d.span =
+			text.source_offset(d.span.start)..text.source_offset(d.span.end);
+		d
+	}));
+	from_tokens(tokens)
+}
+
+fn from_tokens(tokens: Vec<Token<'_>>) -> Vec<Inline> {
+	tokens.into_iter().filter_map(from_token).collect()
+}
+
+fn from_token(token: Token<'_>) -> Option<Inline> {
+	Some(match token {
+		Token::Plaintext(t) if t.starts_with("[^") && t.ends_with(']') => {
+			Inline::FootnoteReference(t[2..t.len() - 1].to_string())
+		}
+		Token::Plaintext(t) => Inline::Text(t.into_owned()),
+		Token::Emphasis(inner) => Inline::Emphasis(from_tokens(inner)),
+		Token::Strong(inner) => Inline::Strong(from_tokens(inner)),
+		Token::Strikethrough(t) => Inline::Strikethrough(t.to_string()),
+		Token::Code(t) => Inline::Code(t.into_owned()),
+		Token::Link(l, Some(inner), ht) => Inline::Link(
+			l.destination(),
+			from_tokens(inner),
+			ht.map(str::to_string),
+		),
+		Token::Link(l, None, _) => Inline::Autolink(l.destination(), l.text()),
+		Token::Image(image) => Inline::Image(image),
+		Token::LineBreak => Inline::LineBreak,
+		Token::SoftBreak => Inline::SoftBreak,
+		Token::Custom(name, value, inner) => Inline::Custom(Custom {
+			name: name.to_string(),
+			value,
+			children: from_tokens(inner),
+		}),
+		Token::Html(t) => Inline::Html(t.to_string()),
+		// Resolved into emphasis or text before the tokens get here
+		Token::Delimiter(_) => return None,
+	})
+}
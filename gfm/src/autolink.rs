@@ -0,0 +1,325 @@
+//! Extended autolinks as described in
+//! <https://github.github.com/gfm/#autolinks-extension->.
+//!
+//! Every matcher receives the remaining source starting at the candidate
+//! position and returns the byte length of the autolink, or `None` when the
+//! text at that position does not form one.
+
+/// Schemes that are recognised without angle brackets.
+const URL_SCHEMES: [&str; 3] = ["http://", "https://", "ftp://"];
+
+/// Schemes that may prefix an email autolink.
+const EMAIL_SCHEMES: [&str; 2] = ["mailto:", "xmpp:"];
+
+/// Longest domain that is recognised, the limit of DNS names. Checking
+/// longer candidates at every position of a word would take quadratic time.
+const MAX_DOMAIN_LEN: usize = 253;
+
+/// Longest local part of an email address that is recognised, as set by
+/// RFC 5321, for the same reason.
+const MAX_LOCAL_PART_LEN: usize = 64;
+
+/// Kind of extended autolink found at a position.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum AutolinkKind {
+	/// `www.commonmark.org`, rendered with an implicit `http://`
+	Www,
+	/// `https://commonmark.org`
+	Url,
+	/// `foo@bar.baz`, rendered with an implicit `mailto:`
+	Email,
+	/// `mailto:foo@bar.baz` or `xmpp:foo@bar.baz/resource`
+	ProtocolEmail,
+}
+
+/// Checks whether an extended `www.` or url autolink may start after `prev`.
+///
+/// They are only recognised at the beginning of a line, after whitespace or
+/// after one of the delimiters `*`, `_`, `~` and `(`.
+pub(crate) fn is_boundary(prev: Option<char>) -> bool {
+	match prev {
+		None => true,
+		Some(c) => c.is_whitespace() || matches!(c, '*' | '_' | '~' | '('),
+	}
+}
+
+/// Checks whether an email autolink may start after `prev`.
+///
+/// The local part is the longest run of email characters in front of the
+/// `@`, so a match may not start in the middle of such a run. `_` is still
+/// accepted since it usually is an emphasis delimiter.
+fn is_email_boundary(prev: Option<char>) -> bool {
+	match prev {
+		None => true,
+		Some(c) => !(c.is_ascii_alphanumeric() || matches!(c, '.' | '+' | '-')),
+	}
+}
+
+/// Finds an extended autolink at the start of `source`.
+///
+/// `prev` is the character directly in front of `source`, if any.
+pub(crate) fn match_autolink(
+	source: &str,
+	prev: Option<char>,
+) -> Option<(AutolinkKind, usize)> {
+	if !source.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+		return None;
+	}
+	if is_boundary(prev) {
+		if let Some(len) = www_len(source) {
+			return Some((AutolinkKind::Www, len));
+		}
+		if let Some(len) = url_len(source) {
+			return Some((AutolinkKind::Url, len));
+		}
+	}
+	if is_email_boundary(prev) {
+		if let Some(len) = protocol_email_len(source) {
+			return Some((AutolinkKind::ProtocolEmail, len));
+		}
+		if let Some(len) = email_len(source, false) {
+			return Some((AutolinkKind::Email, len));
+		}
+	}
+	None
+}
+
+/// Matches `www.` followed by a valid domain and an optional path.
+pub(crate) fn www_len(source: &str) -> Option<usize> {
+	if !source.starts_with("www.") {
+		return None;
+	}
+	let domain = domain_len(source, false)?;
+	Some(trim_trailing(source, path_end(source, domain)))
+}
+
+/// Matches `http://`, `https://` or `ftp://` followed by a domain and an
+/// optional path.
+pub(crate) fn url_len(source: &str) -> Option<usize> {
+	let scheme = URL_SCHEMES
+		.iter()
+		.find(|s| starts_with_ignore_case(source, s))?
+		.len();
+	let domain = domain_len(&source[scheme..], true)?;
+	Some(trim_trailing(source, path_end(source, scheme + domain)))
+}
+
+/// Matches `mailto:` or `xmpp:` followed by an email address. `xmpp:`
+/// addresses may carry a single `/resource` suffix.
+pub(crate) fn protocol_email_len(source: &str) -> Option<usize> {
+	let scheme = EMAIL_SCHEMES
+		.iter()
+		.find(|s| starts_with_ignore_case(source, s))?;
+	let len = email_len(&source[scheme.len()..], scheme == &"xmpp:")?;
+	Some(scheme.len() + len)
+}
+
+/// Matches an email address made of a local part (alphanumerics, `.`, `-`,
+/// `_` and `+`), a single `@` and a domain containing at least one `.`.
+pub(crate) fn email_len(source: &str, allow_resource: bool) -> Option<usize> {
+	let bytes = source.as_bytes();
+	let local = bytes
+		.iter()
+		.take(MAX_LOCAL_PART_LEN + 1)
+		.take_while(|&&b| b.is_ascii_alphanumeric() || b"._+-".contains(&b))
+		.count();
+	if local == 0 ||
+		local > MAX_LOCAL_PART_LEN ||
+		bytes.get(local) != Some(&b'@')
+	{
+		return None;
+	}
+
+	let mut end = local + 1;
+	let mut periods = 0;
+	let mut in_resource = false;
+	while let Some(&b) = bytes.get(end) {
+		match b {
+			b if b.is_ascii_alphanumeric() => {}
+			b'-' | b'_' => {}
+			b'.' if bytes
+				.get(end + 1)
+				.is_some_and(u8::is_ascii_alphanumeric) =>
+			{
+				periods += usize::from(!in_resource);
+			}
+			b'@' if in_resource => {}
+			b'@' => return None,
+			b'/' if allow_resource && !in_resource && periods > 0 => {
+				in_resource = true;
+			}
+			_ => break,
+		}
+		end += 1;
+	}
+
+	let last = bytes[end - 1];
+	if end == local + 1 ||
+		periods == 0 ||
+		!(last.is_ascii_alphabetic() || last == b'.')
+	{
+		return None;
+	}
+	Some(trim_trailing(source, end))
+}
+
+/// Returns the length of a valid domain at the start of `source`.
+///
+/// A domain is made of segments of alphanumerics, `-` and `_` separated by
+/// `.`. Underscores are not allowed in the last two segments and, unless
+/// `allow_short` is set, at least one `.` is required.
+fn domain_len(source: &str, allow_short: bool) -> Option<usize> {
+	let mut periods = 0;
+	let mut underscores_last = 0;
+	let mut underscores_current = 0;
+	let mut end = 0;
+
+	for (i, c) in source.char_indices() {
+		if i > MAX_DOMAIN_LEN {
+			return None;
+		}
+		match c {
+			'_' => underscores_current += 1,
+			'.' => {
+				underscores_last = underscores_current;
+				underscores_current = 0;
+				periods += 1;
+			}
+			'-' => {}
+			c if c.is_whitespace() || c.is_ascii_punctuation() => break,
+			_ => {}
+		}
+		end = i + c.len_utf8();
+	}
+
+	// A trailing `.` belongs to the surrounding sentence, not the domain.
+	if source[..end].ends_with('.') {
+		end -= 1;
+		periods -= 1;
+		underscores_current = underscores_last;
+		underscores_last = 0;
+	}
+
+	if end == 0 ||
+		underscores_last > 0 ||
+		underscores_current > 0 ||
+		(!allow_short && periods == 0)
+	{
+		return None;
+	}
+	Some(end)
+}
+
+/// Extends a link from `start` up to the next whitespace or `<`.
+fn path_end(source: &str, start: usize) -> usize {
+	source[start..]
+		.find(|c: char| c.is_whitespace() || c == '<')
+		.map_or(source.len(), |i| start + i)
+}
+
+/// Applies the extended autolink path validation to `source[..end]`.
+///
+/// Trailing punctuation is not part of the link, unbalanced trailing `)` are
+/// removed one by one and a trailing entity-like `&name;` is excluded.
+fn trim_trailing(source: &str, mut end: usize) -> usize {
+	let bytes = source.as_bytes();
+	let opening = bytes[..end].iter().filter(|&&b| b == b'(').count();
+	let mut closing = bytes[..end].iter().filter(|&&b| b == b')').count();
+
+	while end > 0 {
+		match bytes[end - 1] {
+			b')' if closing > opening => {
+				closing -= 1;
+				end -= 1;
+			}
+			b'?' | b'!' | b'.' | b',' | b':' | b'*' | b'_' | b'~' | b'\'' |
+			b'"' => end -= 1,
+			b';' => {
+				let name = bytes[..end - 1]
+					.iter()
+					.rev()
+					.take_while(|b| b.is_ascii_alphanumeric())
+					.count();
+				let amp = end - 1 - name;
+				if name > 0 && amp > 0 && bytes[amp - 1] == b'&' {
+					end = amp - 1;
+				} else {
+					end -= 1;
+				}
+			}
+			_ => break,
+		}
+	}
+	end
+}
+
+fn starts_with_ignore_case(source: &str, prefix: &str) -> bool {
+	source
+		.get(..prefix.len())
+		.is_some_and(|s| s.eq_ignore_ascii_case(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn www_requires_a_period_in_the_domain() {
+		assert_eq!(Some(18), www_len("www.commonmark.org"));
+		assert_eq!(Some(18), www_len("www.commonmark.org."));
+		assert_eq!(None, www_len("www."));
+		assert_eq!(None, www_len("www.foo_bar.com"));
+	}
+
+	#[test]
+	fn trailing_punctuation_is_trimmed() {
+		assert_eq!(Some(22), www_len("www.commonmark.org/a.b."));
+		assert_eq!(Some(21), url_len("http://commonmark.org?!"));
+		assert_eq!(Some(21), www_len("www.commonmark.org/he<lp"));
+	}
+
+	#[test]
+	fn unbalanced_parentheses_are_trimmed() {
+		let link = "www.google.com/search?q=Markup+(business)";
+		assert_eq!(Some(link.len()), www_len(link));
+		assert_eq!(Some(link.len()), www_len(&format!("{link}))")));
+		assert_eq!(Some(38), www_len("www.google.com/search?q=(business))+ok"));
+	}
+
+	#[test]
+	fn entity_like_suffix_is_excluded() {
+		assert_eq!(Some(34), www_len("www.google.com/search?q=commonmark&hl;"));
+		assert_eq!(
+			Some(40),
+			www_len("www.google.com/search?q=commonmark&hl=en")
+		);
+	}
+
+	#[test]
+	fn emails() {
+		assert_eq!(Some(11), email_len("foo@bar.baz", false));
+		assert_eq!(Some(11), email_len("a.b-c_d@a.b.", false));
+		assert_eq!(None, email_len("a.b-c_d@a.b-", false));
+		assert_eq!(None, email_len("a.b-c_d@a.b_", false));
+		assert_eq!(None, email_len("hello@mail+xyz.example", false));
+	}
+
+	#[test]
+	fn protocol_emails() {
+		assert_eq!(Some(18), protocol_email_len("mailto:foo@bar.baz"));
+		assert_eq!(Some(20), protocol_email_len("xmpp:foo@bar.baz/txt/bin"));
+		assert_eq!(
+			Some(28),
+			protocol_email_len("xmpp:foo@bar.baz/txt@bin.com")
+		);
+		assert_eq!(None, protocol_email_len("mailto:foo/bar@baz.com"));
+	}
+
+	#[test]
+	fn boundaries() {
+		assert!(match_autolink("www.a.b", Some('(')).is_some());
+		assert!(match_autolink("www.a.b", Some('a')).is_none());
+		assert!(match_autolink("foo@a.b", Some('_')).is_some());
+		assert!(match_autolink("foo@a.b", Some('.')).is_none());
+	}
+}
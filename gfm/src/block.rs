@@ -0,0 +1,1354 @@
+//! Block structure of a document, the first of the two parsing phases
+//! described in <https://spec.commonmark.org/0.29/#appendix-a-parsing-strategy>.
+//!
+//! The source is consumed line by line. A line first has to match the open
+//! container blocks, may then start new blocks and whatever is left of it is
+//! added to the innermost open block. Inline content is kept as raw text and
+//! only lexed once the whole structure is known.
+
+use std::{
+	fmt,
+	sync::Arc,
+};
+
+use log::debug;
+
+use crate::{
+	diagnostic::{
+		Diagnostic,
+		DiagnosticKind,
+	},
+	html,
+	indent::{
+		Line,
+		TAB_STOP,
+	},
+	BlockSyntax,
+	Options,
+};
+
+/// Maximum number of cells added to table rows that are shorter than the
+/// header. Rows that would exceed it end the table, so a few bytes of input
+/// cannot produce an arbitrarily large table.
+const MAX_AUTOCOMPLETED_CELLS: usize = 1 << 19;
+
+/// Tags whose content is never markdown, so an html block started by them
+/// runs up to their closing tag.
+const RAW_TEXT_TAGS: [&str; 4] = ["script", "pre", "style", "textarea"];
+
+/// Tags that start an html block, which ends at the next blank line.
+///
+/// See <https://spec.commonmark.org/0.29/#html-blocks>.
+const BLOCK_TAGS: [&str; 62] = [
+	"address",
+	"article",
+	"aside",
+	"base",
+	"basefont",
+	"blockquote",
+	"body",
+	"caption",
+	"center",
+	"col",
+	"colgroup",
+	"dd",
+	"details",
+	"dialog",
+	"dir",
+	"div",
+	"dl",
+	"dt",
+	"fieldset",
+	"figcaption",
+	"figure",
+	"footer",
+	"form",
+	"frame",
+	"frameset",
+	"h1",
+	"h2",
+	"h3",
+	"h4",
+	"h5",
+	"h6",
+	"head",
+	"header",
+	"hr",
+	"html",
+	"iframe",
+	"legend",
+	"li",
+	"link",
+	"main",
+	"menu",
+	"menuitem",
+	"nav",
+	"noframes",
+	"ol",
+	"optgroup",
+	"option",
+	"p",
+	"param",
+	"section",
+	"source",
+	"summary",
+	"table",
+	"tbody",
+	"td",
+	"tfoot",
+	"th",
+	"thead",
+	"title",
+	"tr",
+	"track",
+	"ul",
+];
+
+/// A block of the document tree. Inline content is kept as raw text.
+#[derive(Debug, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+pub(crate) enum Block {
+	/// Text: Inline content of the paragraph
+	Paragraph(Text),
+	/// usize: Header level (1..=6). Text: Inline content. Option<String>:
+	/// html id given as `{#id}`
+	Header(usize, Text, Option<String>),
+	/// Corresponds to a <hr /> html tag
+	HorizontalRule,
+	/// First String: Code. Second String: Info string, whose first word is
+	/// the language
+	CodeBlock(String, String),
+	/// Vec<Block>: Blocks inside the quote
+	BlockQuote(Vec<Block>),
+	/// ListKind: Marker of the list. bool: Whether the list is tight, so its
+	/// paragraphs are rendered without <p> tags. Vec<Block>: List items.
+	/// usize: Byte offset of the first marker in the source
+	List(ListKind, bool, Vec<Block>, usize),
+	/// Option<TaskBox>: Checkbox of a task list item. Vec<Block>: Content of
+	/// the list item
+	ListItem(Option<TaskBox>, Vec<Block>),
+	/// Vec<Option<Alignment>>: Alignment of every column. Vec<Text>: Header
+	/// cells. Vec<Vec<Text>>: Rows, with exactly one cell per column
+	Table(Vec<Option<Alignment>>, Vec<Text>, Vec<Vec<Text>>),
+	/// Text: Inline content of the summary. Vec<Block>: Blocks rendered in
+	/// the collapsable section
+	Detail(Text, Vec<Block>),
+	/// String: Reference id. Vec<Block>: Reference content
+	Footnote(String, Vec<Block>),
+	/// First String: Name of the syntax extension. Second String: Value it
+	/// parsed. Text: Content
+	Custom(String, String, Text),
+	/// String: Lines of raw html
+	Html(String),
+}
+
+/// Inline content of a leaf block, which remembers where each of its lines
+/// starts in the source so diagnostics can point at it.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct Text {
+	pub(crate) text: String,
+	/// Byte offset of every line in `text` and of the same line in the source
+	lines:           Vec<(usize, usize)>,
+}
+
+impl Text {
+	fn new(text: &str, source_offset: usize) -> Self {
+		Text {
+			text:  text.to_string(),
+			lines: vec![(0, source_offset)],
+		}
+	}
+
+	fn push_line(&mut self, line: &str, source_offset: usize) {
+		self.lines.push((self.text.len(), source_offset));
+		self.text.push_str(line);
+		self.text.push('\n');
+	}
+
+	/// Removes the first `count` bytes of the text.
+	fn remove_start(&mut self, count: usize) {
+		let first = self.lines.partition_point(|&(t, _)| t <= count);
+		let first = first.saturating_sub(1);
+		self.lines.drain(..first);
+		for (i, (text, source)) in self.lines.iter_mut().enumerate() {
+			if i == 0 {
+				*source += count.saturating_sub(*text);
+			}
+			*text = text.saturating_sub(count);
+		}
+		self.text.drain(..count);
+	}
+
+	fn trim_end(&mut self) {
+		self.text.truncate(self.text.trim_end().len());
+	}
+
+	fn trim(&mut self) {
+		self.trim_end();
+		self.remove_start(self.text.len() - self.text.trim_start().len());
+	}
+
+	/// Maps a byte offset in the text to one in the source.
+	pub(crate) fn source_offset(&self, offset: usize) -> usize {
+		let line = self.lines.partition_point(|&(t, _)| t <= offset);
+		match line.checked_sub(1).map(|l| self.lines[l]) {
+			Some((text, source)) => source + offset - text,
+			None => offset,
+		}
+	}
+}
+
+/// Marker of a list. Items with a different marker start a new list.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ListKind {
+	/// char: `-`, `+` or `*`
+	Bullet(char),
+	/// usize: Number of the first item. char: `.` or `)`
+	Ordered(usize, char),
+}
+
+impl ListKind {
+	fn continues(&self, other: &ListKind) -> bool {
+		match (self, other) {
+			(ListKind::Bullet(a), ListKind::Bullet(b)) => a == b,
+			(ListKind::Ordered(_, a), ListKind::Ordered(_, b)) => a == b,
+			_ => false,
+		}
+	}
+}
+
+/// Holds the possible states of a taskbox in a task list
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TaskBox {
+	Checked,
+	Unchecked,
+}
+
+/// Holds the alignment states of a table column
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Alignment {
+	Left,
+	Right,
+	Center,
+}
+
+impl fmt::Display for Alignment {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Alignment::Left => write!(f, "left"),
+			Alignment::Right => write!(f, "right"),
+			Alignment::Center => write!(f, "center"),
+		}
+	}
+}
+
+/// Splits `source` into its blocks.
+///
+/// Blocks nested deeper than [Options::max_nesting] are not started, their
+/// markers are kept as text instead. The first line this happens on is
+/// reported along with the blocks.
+pub(crate) fn parse(
+	source: &str,
+	options: &Options,
+) -> (Vec<Block>, Option<Diagnostic>) {
+	let mut parser = BlockParser {
+		source,
+		stack: vec![OpenBlock::new(Open::Document, 0)],
+		line_number: 0,
+		line_start: 0,
+		max_nesting: options.max_nesting,
+		too_deep: None,
+		syntaxes: options.extensions.block_syntaxes(),
+	};
+	for line in source.lines() {
+		parser.line_start = parser.offset_of(line);
+		parser.process_line(line);
+	}
+	parser.close_to(0);
+	let document = parser.stack.pop().map(|d| d.children).unwrap_or_default();
+	debug!("Block phase completed, {} blocks", document.len());
+	(
+		document.into_iter().map(|c| c.block).collect(),
+		parser.too_deep,
+	)
+}
+
+/// Kind of a block that may still receive lines.
+#[derive(Debug)]
+enum Open {
+	Document,
+	BlockQuote,
+	/// Byte offset of the first marker in the source
+	List(ListKind, usize),
+	/// Content starts `marker_offset + padding` columns into the line
+	ListItem {
+		marker_offset: usize,
+		padding:       usize,
+	},
+	Paragraph,
+	IndentedCode,
+	FencedCode {
+		fence:  u8,
+		length: usize,
+		/// Indentation of the opening fence, removed from every line
+		offset: usize,
+		info:   String,
+	},
+	Table {
+		alignments:    Vec<Option<Alignment>>,
+		header:        Vec<Text>,
+		rows:          Vec<Vec<Text>>,
+		/// Number of cells added to rows shorter than the header
+		autocompleted: usize,
+	},
+	Detail(Option<Text>),
+	Footnote(String),
+	/// An html comment, which is not rendered
+	Comment,
+	/// Lines of raw html, up to the line where `HtmlEnd` is found
+	Html(HtmlEnd),
+}
+
+/// What ends a block of raw html.
+///
+/// See <https://spec.commonmark.org/0.29/#html-blocks>.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HtmlEnd {
+	/// A line containing the closing tag of a `<script>`, `<pre>`, `<style>`
+	/// or `<textarea>`, in any case
+	RawText,
+	/// A line containing the marker, eg. `?>`
+	Marker(&'static str),
+	/// A blank line, which is not part of the block
+	BlankLine,
+}
+
+impl HtmlEnd {
+	/// Checks whether the block ends on a line that was added to it.
+	fn ends_on(self, line: &str) -> bool {
+		match self {
+			HtmlEnd::RawText => {
+				let line = line.to_ascii_lowercase();
+				RAW_TEXT_TAGS
+					.iter()
+					.any(|tag| line.contains(&format!("</{}>", tag)))
+			}
+			HtmlEnd::Marker(marker) => line.contains(marker),
+			HtmlEnd::BlankLine => false,
+		}
+	}
+}
+
+impl Open {
+	fn can_contain(&self, child: &Open) -> bool {
+		match self {
+			Open::Document |
+			Open::BlockQuote |
+			Open::ListItem { .. } |
+			Open::Detail(_) |
+			Open::Footnote(_) => !matches!(child, Open::ListItem { .. }),
+			Open::List(..) => matches!(child, Open::ListItem { .. }),
+			_ => false,
+		}
+	}
+}
+
+#[derive(Debug)]
+struct OpenBlock {
+	kind:            Open,
+	children:        Vec<Child>,
+	/// Raw lines of a paragraph or code block
+	content:         Text,
+	/// Whether the last line added to the block was blank
+	last_line_blank: bool,
+	start_line:      usize,
+}
+
+impl OpenBlock {
+	fn new(kind: Open, start_line: usize) -> Self {
+		OpenBlock {
+			kind,
+			children: Vec::new(),
+			content: Text::default(),
+			last_line_blank: false,
+			start_line,
+		}
+	}
+}
+
+/// A closed block, along with what its list needs to know to decide whether
+/// it is tight.
+#[derive(Debug)]
+struct Child {
+	block:           Block,
+	/// Whether the block, or its last nested item, ends with a blank line
+	ends_with_blank: bool,
+	/// Whether blank lines separate the blocks of a list item
+	blank_inside:    bool,
+}
+
+/// What is left to do with a line after new blocks are opened.
+enum Started {
+	/// The remainder of the line goes to the block at this depth
+	Container(usize),
+	/// The line is completely consumed
+	Done,
+}
+
+struct BlockParser<'a> {
+	source:      &'a str,
+	/// Open blocks, each the last child of the one before it
+	stack:       Vec<OpenBlock>,
+	line_number: usize,
+	/// Byte offset of the current line in the source
+	line_start:  usize,
+	max_nesting: usize,
+	/// First line that would have nested blocks deeper than `max_nesting`
+	too_deep:    Option<Diagnostic>,
+	/// Syntax extensions, looked for before the blocks of GFM
+	syntaxes:    &'a [Arc<dyn BlockSyntax>],
+}
+
+impl<'a> BlockParser<'a> {
+	fn process_line(&mut self, text: &'a str) {
+		self.line_number += 1;
+		let mut line = Line::new(text);
+		let Some(last_matched) = self.match_containers(&mut line) else {
+			return;
+		};
+		match self.open_blocks(&mut line, last_matched) {
+			Started::Container(container) => {
+				self.add_text(&mut line, container, last_matched)
+			}
+			Started::Done => {}
+		}
+	}
+
+	/// Matches the line against the open blocks and returns the depth of the
+	/// innermost one that continues, or `None` if the line closed a block.
+	fn match_containers(&mut self, line: &mut Line) -> Option<usize> {
+		let mut matched = 0;
+		for depth in 1..self.stack.len() {
+			line.find_first_nonspace();
+			let has_children = !self.stack[depth].children.is_empty() ||
+				depth + 1 < self.stack.len();
+			let continues = match &self.stack[depth].kind {
+				Open::Document | Open::List(..) | Open::Comment => true,
+				Open::Html(end) => *end != HtmlEnd::BlankLine || !line.blank,
+				Open::BlockQuote => {
+					if line.indent > 3 ||
+						line.nonspace_rest().as_bytes().first() !=
+							Some(&b'>')
+					{
+						false
+					} else {
+						line.advance(line.indent + 1, true);
+						if matches!(line.peek(), Some(b' ' | b'\t')) {
+							line.advance(1, true);
+						}
+						true
+					}
+				}
+				Open::ListItem {
+					marker_offset,
+					padding,
+				} => {
+					if line.indent >= marker_offset + padding {
+						line.advance(marker_offset + padding, true);
+						true
+					} else if line.blank && has_children {
+						line.skip_indent();
+						true
+					} else {
+						false
+					}
+				}
+				Open::Paragraph | Open::Table { .. } => !line.blank,
+				Open::IndentedCode => {
+					if line.indent >= TAB_STOP {
+						line.advance(TAB_STOP, true);
+						true
+					} else if line.blank {
+						line.skip_indent();
+						true
+					} else {
+						false
+					}
+				}
+				Open::FencedCode {
+					fence,
+					length,
+					offset,
+					..
+				} => {
+					if line.indent <= 3 &&
+						is_closing_fence(
+							line.nonspace_rest(),
+							*fence,
+							*length,
+						) {
+						self.close_to(depth - 1);
+						return None;
+					}
+					let mut offset = *offset;
+					while offset > 0 &&
+						matches!(line.peek(), Some(b' ' | b'\t'))
+					{
+						line.advance(1, true);
+						offset -= 1;
+					}
+					true
+				}
+				Open::Detail(_) => {
+					let innermost = !self.stack[depth + 1..]
+						.iter()
+						.any(|b| matches!(b.kind, Open::Detail(_)));
+					if innermost &&
+						line.indent <= 3 && line.nonspace_rest().trim_end() ==
+						"</details>"
+					{
+						self.close_to(depth - 1);
+						return None;
+					}
+					true
+				}
+				Open::Footnote(_) => {
+					if line.indent >= TAB_STOP {
+						line.advance(TAB_STOP, true);
+						true
+					} else {
+						line.blank
+					}
+				}
+			};
+			if !continues {
+				break;
+			}
+			matched = depth;
+		}
+		Some(matched)
+	}
+
+	/// Opens the blocks started by the line inside the block at depth
+	/// `container`.
+	fn open_blocks(&mut self, line: &mut Line, container: usize) -> Started {
+		let all_matched = container + 1 == self.stack.len();
+		let mut maybe_lazy =
+			matches!(self.stack.last().map(|b| &b.kind), Some(Open::Paragraph));
+		let mut container = container;
+
+		loop {
+			line.find_first_nonspace();
+			let indented = line.indent >= TAB_STOP;
+			let rest = line.nonspace_rest();
+			let (in_paragraph, in_list) = match &self.stack[container].kind {
+				Open::IndentedCode |
+				Open::FencedCode { .. } |
+				Open::Table { .. } |
+				Open::Comment |
+				Open::Html(_) => break,
+				Open::Paragraph => (true, None),
+				Open::List(kind, _) => (false, Some(*kind)),
+				_ => (false, None),
+			};
+
+			if let Some(custom) =
+				(!indented).then(|| self.custom_block(rest)).flatten()
+			{
+				self.add_closed(container, custom);
+				return Started::Done;
+			} else if !indented &&
+				rest.starts_with('>') &&
+				self.can_nest(container + 1)
+			{
+				line.skip_indent();
+				line.advance(1, false);
+				if matches!(line.peek(), Some(b' ' | b'\t')) {
+					line.advance(1, true);
+				}
+				container = self.open(container, Open::BlockQuote);
+			} else if let Some((level, content, id)) =
+				(!indented).then(|| atx_heading(rest)).flatten()
+			{
+				let content = Text::new(content, self.offset_of(content));
+				self.add_closed(container, Block::Header(level, content, id));
+				return Started::Done;
+			} else if let Some((fence, length, info)) =
+				(!indented).then(|| opening_fence(rest)).flatten()
+			{
+				self.open(container, Open::FencedCode {
+					fence,
+					length,
+					offset: line.indent,
+					info: info.to_string(),
+				});
+				return Started::Done;
+			} else if !indented && rest.starts_with("<!--") {
+				let comment = self.open(container, Open::Comment);
+				if rest[4..].contains("-->") {
+					self.close_to(comment - 1);
+				}
+				return Started::Done;
+			} else if let Some(level) = (!indented && in_paragraph)
+				.then(|| setext_level(rest))
+				.flatten()
+			{
+				let mut content = self.pop().content;
+				content.trim();
+				self.add_closed(
+					container - 1,
+					Block::Header(level, content, None),
+				);
+				return Started::Done;
+			} else if !indented &&
+				!(in_paragraph && !all_matched) &&
+				is_thematic_break(rest)
+			{
+				self.add_closed(container, Block::HorizontalRule);
+				return Started::Done;
+			} else if let Some((kind, length, continues)) = (!indented)
+				.then(|| list_marker(rest, in_paragraph))
+				.flatten()
+				.map(|(kind, length)| {
+					let continues =
+						in_list.is_some_and(|list| list.continues(&kind));
+					(kind, length, continues)
+				})
+				.filter(|(_, _, continues)| {
+					self.can_nest(container + 2 - usize::from(*continues))
+				}) {
+				let marker_offset = line.indent;
+				line.skip_indent();
+				line.advance(length, false);
+				let padding = length + list_padding(line);
+				if !continues {
+					let offset = self.offset_of(rest);
+					container = self.open(container, Open::List(kind, offset));
+				}
+				container = self.open(container, Open::ListItem {
+					marker_offset,
+					padding,
+				});
+			} else if indented && !maybe_lazy && !line.blank {
+				line.advance(TAB_STOP, true);
+				container = self.open(container, Open::IndentedCode);
+			} else if let Some(table) = (!indented && in_paragraph)
+				.then(|| self.table_start(rest))
+				.flatten()
+			{
+				let mut paragraph = self.pop();
+				let content = paragraph.content.text.trim_end();
+				let header_start = content.rfind('\n').map_or(0, |i| i + 1);
+				paragraph.content.text.truncate(header_start);
+				self.close_block(paragraph);
+				self.open(container - 1, table);
+				return Started::Done;
+			} else if !indented &&
+				rest.trim_end() == "<details>" &&
+				self.can_nest(container + 1)
+			{
+				self.open(container, Open::Detail(None));
+				return Started::Done;
+			} else if let Some(summary) =
+				(!indented).then(|| self.summary(container, rest)).flatten()
+			{
+				self.stack[container].kind = Open::Detail(Some(summary));
+				return Started::Done;
+			} else if let Some(end) = (!indented)
+				.then(|| html_block_start(rest, in_paragraph))
+				.flatten()
+			{
+				container = self.open(container, Open::Html(end));
+				break;
+			} else if let Some((label, length)) = (!indented)
+				.then(|| footnote_label(rest))
+				.flatten()
+				.filter(|_| self.can_nest(container + 1))
+			{
+				line.skip_indent();
+				line.advance(length, false);
+				container = self.open(container, Open::Footnote(label));
+			} else {
+				break;
+			}
+
+			if matches!(self.stack[container].kind, Open::IndentedCode) {
+				break;
+			}
+			maybe_lazy = false;
+		}
+		Started::Container(container)
+	}
+
+	/// Adds what is left of the line to the block at depth `container`, or to
+	/// an open paragraph it lazily continues.
+	fn add_text(&mut self, line: &mut Line, container: usize, matched: usize) {
+		line.find_first_nonspace();
+		if line.blank {
+			if let Some(open) = self.stack.get_mut(container + 1) {
+				open.last_line_blank = true;
+			} else if let Some(child) =
+				self.stack[container].children.last_mut()
+			{
+				child.ends_with_blank = true;
+			}
+		}
+
+		let line_number = self.line_number;
+		let innermost = container + 1 == self.stack.len();
+		let block = &mut self.stack[container];
+		block.last_line_blank =
+			line.blank &&
+				!matches!(
+					block.kind,
+					Open::BlockQuote | Open::FencedCode { .. }
+				) && !(matches!(block.kind, Open::ListItem { .. }) &&
+				block.children.is_empty() &&
+				innermost && block.start_line == line_number);
+		for ancestor in self.stack[..container].iter_mut() {
+			ancestor.last_line_blank = false;
+		}
+
+		let tip = self.stack.len() - 1;
+		if tip != matched &&
+			container == matched &&
+			!line.blank &&
+			matches!(self.stack[tip].kind, Open::Paragraph)
+		{
+			line.skip_indent();
+			let offset = self.line_start + line.offset();
+			self.stack[tip].content.push_line(&line.rest(), offset);
+			return;
+		}
+		if container == matched {
+			self.close_to(container);
+		}
+
+		let row_start = self.offset_of(line.nonspace_rest());
+		match &mut self.stack[container].kind {
+			Open::IndentedCode | Open::FencedCode { .. } => {
+				let offset = self.line_start + line.offset();
+				self.stack[container]
+					.content
+					.push_line(&line.rest(), offset)
+			}
+			Open::Comment => {
+				if line.rest().contains("-->") {
+					self.close_to(container - 1);
+				}
+			}
+			Open::Html(end) => {
+				let end = *end;
+				let text = line.rest();
+				let offset = self.line_start + line.offset();
+				self.stack[container].content.push_line(&text, offset);
+				if end.ends_on(&text) {
+					self.close_to(container - 1);
+				}
+			}
+			_ if line.blank => {}
+			Open::Paragraph => {
+				line.skip_indent();
+				let offset = self.line_start + line.offset();
+				self.stack[container]
+					.content
+					.push_line(&line.rest(), offset)
+			}
+			Open::Table {
+				header,
+				rows,
+				autocompleted,
+				..
+			} => {
+				let mut row = split_row(line.nonspace_rest(), row_start);
+				*autocompleted += header.len().saturating_sub(row.len());
+				if *autocompleted <= MAX_AUTOCOMPLETED_CELLS {
+					row.resize(header.len(), Text::default());
+					rows.push(row);
+					return;
+				}
+				line.skip_indent();
+				let offset = self.line_start + line.offset();
+				let paragraph = self.open(container - 1, Open::Paragraph);
+				self.stack[paragraph]
+					.content
+					.push_line(&line.rest(), offset)
+			}
+			_ => {
+				line.skip_indent();
+				let offset = self.line_start + line.offset();
+				let paragraph = self.open(container, Open::Paragraph);
+				self.stack[paragraph]
+					.content
+					.push_line(&line.rest(), offset)
+			}
+		}
+	}
+
+	/// Opens a block of `kind` inside the block at depth `container`, closing
+	/// every block that is not matched or cannot contain it. Returns the
+	/// depth of the new block.
+	fn open(&mut self, container: usize, kind: Open) -> usize {
+		self.close_to(container);
+		while !self.stack[self.stack.len() - 1].kind.can_contain(&kind) {
+			let block = self.pop();
+			self.close_block(block);
+		}
+		self.stack.push(OpenBlock::new(kind, self.line_number));
+		self.stack.len() - 1
+	}
+
+	/// Adds a block that never receives further lines, like a heading.
+	fn add_closed(&mut self, container: usize, block: Block) {
+		self.close_to(container);
+		while !self.stack[self.stack.len() - 1]
+			.kind
+			.can_contain(&Open::Paragraph)
+		{
+			let open = self.pop();
+			self.close_block(open);
+		}
+		for open in self.stack.iter_mut() {
+			open.last_line_blank = false;
+		}
+		let parent = self.stack.len() - 1;
+		self.stack[parent].children.push(Child {
+			block,
+			ends_with_blank: false,
+			blank_inside: false,
+		});
+	}
+
+	/// Closes every block deeper than `depth`.
+	fn close_to(&mut self, depth: usize) {
+		while self.stack.len() > depth + 1 {
+			let block = self.pop();
+			self.close_block(block);
+		}
+	}
+
+	fn pop(&mut self) -> OpenBlock {
+		self.stack.pop().expect("the document is never closed")
+	}
+
+	/// Turns `open` into a [Block] of the block that is now innermost.
+	fn close_block(&mut self, open: OpenBlock) {
+		let OpenBlock {
+			kind,
+			mut children,
+			mut content,
+			last_line_blank,
+			..
+		} = open;
+		let last_child_blank =
+			children.last().is_some_and(|c| c.ends_with_blank);
+		let mut ends_with_blank = last_line_blank;
+		let mut blank_inside = false;
+
+		let block = match kind {
+			Open::Document => unreachable!("the document is never closed"),
+			Open::BlockQuote => Block::BlockQuote(blocks(children)),
+			Open::List(list_kind, offset) => {
+				ends_with_blank |= last_child_blank;
+				let last = children.len().saturating_sub(1);
+				let tight = children.iter().enumerate().all(|(i, item)| {
+					!item.blank_inside && (i == last || !item.ends_with_blank)
+				});
+				Block::List(list_kind, tight, blocks(children), offset)
+			}
+			Open::ListItem { .. } => {
+				ends_with_blank |= last_child_blank;
+				let inner = children.len().saturating_sub(1);
+				blank_inside =
+					children[..inner].iter().any(|c| c.ends_with_blank);
+				let task = match children.first_mut().map(|c| &mut c.block) {
+					Some(Block::Paragraph(text)) => strip_task_box(text),
+					_ => None,
+				};
+				Block::ListItem(task, blocks(children))
+			}
+			Open::Paragraph => {
+				content.trim_end();
+				if content.text.is_empty() {
+					return;
+				}
+				Block::Paragraph(content)
+			}
+			Open::IndentedCode => Block::CodeBlock(
+				trim_trailing_blank_lines(&content.text),
+				String::new(),
+			),
+			Open::FencedCode { info, .. } => {
+				Block::CodeBlock(content.text, info)
+			}
+			Open::Table {
+				alignments,
+				header,
+				rows,
+				..
+			} => Block::Table(alignments, header, rows),
+			Open::Detail(summary) => {
+				Block::Detail(summary.unwrap_or_default(), blocks(children))
+			}
+			Open::Footnote(label) => Block::Footnote(label, blocks(children)),
+			Open::Comment => return,
+			Open::Html(_) => {
+				let text = content.text;
+				Block::Html(
+					text.strip_suffix('\n').unwrap_or(&text).to_string(),
+				)
+			}
+		};
+
+		let parent = self.stack.len() - 1;
+		self.stack[parent].children.push(Child {
+			block,
+			ends_with_blank,
+			blank_inside,
+		});
+	}
+
+	/// Checks whether the line is the delimiter row of a table, whose header
+	/// row is the last line of the open paragraph.
+	fn table_start(&self, rest: &str) -> Option<Open> {
+		let paragraph = &self.stack.last()?.content;
+		let content = paragraph.text.trim_end();
+		let header_start = content.rfind('\n').map_or(0, |i| i + 1);
+		let header_line = &content[header_start..];
+		if !rest.contains('|') && !header_line.contains('|') {
+			return None;
+		}
+		let alignments = delimiter_row(rest)?;
+		let header =
+			split_row(header_line, paragraph.source_offset(header_start));
+		(header.len() == alignments.len()).then_some(Open::Table {
+			alignments,
+			header,
+			rows: Vec::new(),
+			autocompleted: 0,
+		})
+	}
+
+	/// Checks whether a container may be opened at `depth`, and remembers the
+	/// line if it may not.
+	fn can_nest(&mut self, depth: usize) -> bool {
+		if depth <= self.max_nesting {
+			return true;
+		}
+		if self.too_deep.is_none() {
+			let line = self.source[self.line_start..].lines().next();
+			let end = self.line_start + line.map_or(0, str::len);
+			self.too_deep = Some(Diagnostic::error(
+				DiagnosticKind::NestingTooDeep,
+				self.line_start..end,
+				format!(
+					"blocks are nested deeper than the limit of {}",
+					self.max_nesting
+				),
+			));
+		}
+		false
+	}
+
+	/// Returns the byte offset in the source of a slice of it, eg. the rest
+	/// of the current line.
+	fn offset_of(&self, slice: &str) -> usize {
+		slice.as_ptr() as usize - self.source.as_ptr() as usize
+	}
+
+	/// Looks for a syntax extension that takes up the rest of the line.
+	fn custom_block(&self, rest: &'a str) -> Option<Block> {
+		let line = rest.trim_end();
+		let trigger = line.chars().next()?;
+		self.syntaxes
+			.iter()
+			.filter(|s| s.trigger() == trigger)
+			.find_map(|syntax| {
+				let found =
+					syntax.parse(line).filter(|m| m.length == line.len())?;
+				let content = match found.content {
+					Some(range) => {
+						let content = line.get(range)?;
+						Text::new(content, self.offset_of(content))
+					}
+					None => Text::default(),
+				};
+				Some(Block::Custom(
+					syntax.name().to_string(),
+					found.value,
+					content,
+				))
+			})
+	}
+
+	/// Returns the summary of a `<details>` block opened on the line before.
+	fn summary(&self, container: usize, rest: &str) -> Option<Text> {
+		let block = &self.stack[container];
+		if !matches!(block.kind, Open::Detail(None)) ||
+			!block.children.is_empty() ||
+			container + 1 != self.stack.len()
+		{
+			return None;
+		}
+		rest.trim_end()
+			.strip_prefix("<summary>")?
+			.strip_suffix("</summary>")
+			.map(|summary| Text::new(summary, self.offset_of(summary)))
+	}
+}
+
+fn blocks(children: Vec<Child>) -> Vec<Block> {
+	children.into_iter().map(|c| c.block).collect()
+}
+
+/// Consumes the spaces between a list marker and the content of its item and
+/// returns their width.
+///
+/// Content indented by five or more columns is an indented code block, which
+/// starts one column after the marker. So does the content of an item that
+/// starts with a blank line.
+fn list_padding(line: &mut Line) -> usize {
+	let start = line.clone();
+	while line.column() - start.column() <= 5 &&
+		matches!(line.peek(), Some(b' ' | b'\t'))
+	{
+		line.advance(1, true);
+	}
+	let width = line.column() - start.column();
+	if (1..5).contains(&width) && line.peek().is_some() {
+		return width;
+	}
+	*line = start;
+	if width > 0 {
+		line.advance(1, true);
+	}
+	1
+}
+
+/// Returns what ends the html block started by a line, if it starts one.
+/// Only blocks that end with a known tag or marker may interrupt a
+/// paragraph.
+///
+/// Comments are left to [Open::Comment], so they are not rendered.
+///
+/// See <https://spec.commonmark.org/0.29/#html-blocks>.
+fn html_block_start(rest: &str, interrupts_paragraph: bool) -> Option<HtmlEnd> {
+	let line = rest.trim_end();
+	if !line.starts_with('<') {
+		return None;
+	}
+	if line.starts_with("<?") {
+		return Some(HtmlEnd::Marker("?>"));
+	}
+	if line.starts_with("<![CDATA[") {
+		return Some(HtmlEnd::Marker("]]>"));
+	}
+	if line.starts_with("<!") &&
+		line.as_bytes().get(2).is_some_and(u8::is_ascii_uppercase)
+	{
+		return Some(HtmlEnd::Marker(">"));
+	}
+
+	let closing = line.starts_with("</");
+	let name_start = if closing { 2 } else { 1 };
+	let length = html::tag_name(line.get(name_start..)?)?;
+	let name = line[name_start..name_start + length].to_ascii_lowercase();
+	let after = &line[name_start + length..];
+	let ends_name = after.is_empty() ||
+		after.starts_with([' ', '\t', '>']) ||
+		after.starts_with("/>");
+	if !closing && ends_name && RAW_TEXT_TAGS.contains(&name.as_str()) {
+		return Some(HtmlEnd::RawText);
+	}
+	if ends_name && BLOCK_TAGS.contains(&name.as_str()) {
+		return Some(HtmlEnd::BlankLine);
+	}
+	if interrupts_paragraph || RAW_TEXT_TAGS.contains(&name.as_str()) {
+		return None;
+	}
+	let (tag, end) = html::tag(line, 0, &mut html::Scan::default())?;
+	(!matches!(tag, html::Tag::Other) && line[end..].trim().is_empty())
+		.then_some(HtmlEnd::BlankLine)
+}
+
+/// Parses an ATX heading into its level, content and optional `{#id}`.
+///
+/// See <https://spec.commonmark.org/0.29/#atx-headings>.
+fn atx_heading(rest: &str) -> Option<(usize, &str, Option<String>)> {
+	let level = rest.bytes().take_while(|&b| b == b'#').count();
+	let content = &rest[level..];
+	if !(1..=6).contains(&level) ||
+		!(content.is_empty() || content.starts_with([' ', '\t']))
+	{
+		return None;
+	}
+	let mut content = content.trim_matches([' ', '\t']);
+	// The optional closing sequence has to be preceded by a space
+	let without_closing = content.trim_end_matches('#');
+	if without_closing.is_empty() {
+		content = &content[..0];
+	} else if without_closing.ends_with([' ', '\t']) {
+		content = without_closing.trim_end_matches([' ', '\t']);
+	}
+
+	let id = content
+		.strip_suffix('}')
+		.and_then(|c| c.rsplit_once("{#"))
+		.filter(|(_, id)| !id.is_empty() && !id.contains(char::is_whitespace));
+	match id {
+		Some((text, id)) => Some((
+			level,
+			text.trim_end_matches([' ', '\t']),
+			Some(id.to_string()),
+		)),
+		None => Some((level, content, None)),
+	}
+}
+
+/// Returns the level of a setext heading underline.
+///
+/// See <https://spec.commonmark.org/0.29/#setext-headings>.
+fn setext_level(rest: &str) -> Option<usize> {
+	let level = match rest.bytes().next()? {
+		b'=' => 1,
+		b'-' => 2,
+		_ => return None,
+	};
+	let marker = rest.chars().next()?;
+	rest.trim_start_matches(marker)
+		.trim_matches([' ', '\t'])
+		.is_empty()
+		.then_some(level)
+}
+
+/// Checks whether a line is a thematic break made of `*`, `-` or `_`.
+///
+/// See <https://spec.commonmark.org/0.29/#thematic-breaks>.
+fn is_thematic_break(rest: &str) -> bool {
+	let Some(marker) = rest.chars().next() else {
+		return false;
+	};
+
+	matches!(marker, '*' | '-' | '_') &&
+		rest.chars().all(|c| c == marker || c == ' ' || c == '\t') &&
+		rest.matches(marker).count() >= 3
+}
+
+/// Parses the fence, its length and the info string of an opening code fence.
+///
+/// See <https://spec.commonmark.org/0.29/#fenced-code-blocks>.
+fn opening_fence(rest: &str) -> Option<(u8, usize, &str)> {
+	let fence = *rest
+		.as_bytes()
+		.first()
+		.filter(|b| matches!(b, b'`' | b'~'))?;
+	let length = rest.bytes().take_while(|&b| b == fence).count();
+	let info = rest[length..].trim();
+	(length >= 3 && !(fence == b'`' && info.contains('`')))
+		.then_some((fence, length, info))
+}
+
+fn is_closing_fence(rest: &str, fence: u8, length: usize) -> bool {
+	let run = rest.bytes().take_while(|&b| b == fence).count();
+	run >= length && rest[run..].trim_matches([' ', '\t']).is_empty()
+}
+
+/// Parses a list marker into the kind of list and the length of the marker.
+///
+/// Only lists starting at 1 with a non blank first line may interrupt a
+/// paragraph.
+///
+/// See <https://spec.commonmark.org/0.29/#list-items>.
+fn list_marker(
+	rest: &str,
+	interrupts_paragraph: bool,
+) -> Option<(ListKind, usize)> {
+	let bytes = rest.as_bytes();
+	let (kind, length) = match bytes.first()? {
+		b @ (b'*' | b'+' | b'-') => (ListKind::Bullet(*b as char), 1),
+		b'0'..=b'9' => {
+			let digits =
+				bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+			let delimiter = *bytes.get(digits)?;
+			if digits > 9 || !matches!(delimiter, b'.' | b')') {
+				return None;
+			}
+			let start = rest[..digits].parse().ok()?;
+			(ListKind::Ordered(start, delimiter as char), digits + 1)
+		}
+		_ => return None,
+	};
+	if !matches!(bytes.get(length), None | Some(b' ' | b'\t')) {
+		return None;
+	}
+	if interrupts_paragraph &&
+		(rest[length..].trim_matches([' ', '\t']).is_empty() ||
+			matches!(kind, ListKind::Ordered(start, _) if start != 1))
+	{
+		return None;
+	}
+	Some((kind, length))
+}
+
+/// Parses the start of a footnote definition, `[^label]:`, into the label and
+/// the length of the definition marker including the spaces after it.
+fn footnote_label(rest: &str) -> Option<(String, usize)> {
+	let after = rest.strip_prefix("[^")?;
+	let (label, after) = after.split_once("]:")?;
+	if label.is_empty() ||
+		label.contains(|c: char| c.is_whitespace() || c == ']')
+	{
+		return None;
+	}
+	let spaces = after.len() - after.trim_start_matches([' ', '\t']).len();
+	Some((label.to_string(), rest.len() - after.len() + spaces))
+}
+
+/// Parses the delimiter row of a table into the alignment of every column.
+///
+/// See <https://github.github.com/gfm/#tables-extension->.
+fn delimiter_row(rest: &str) -> Option<Vec<Option<Alignment>>> {
+	split_row(rest, 0)
+		.iter()
+		.map(|Text { text: cell, .. }| {
+			let left = cell.starts_with(':');
+			let right = cell.ends_with(':');
+			let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+			if dashes.is_empty() || !dashes.bytes().all(|b| b == b'-') {
+				return None;
+			}
+			Some(match (left, right) {
+				(true, true) => Some(Alignment::Center),
+				(true, false) => Some(Alignment::Left),
+				(false, true) => Some(Alignment::Right),
+				(false, false) => None,
+			})
+		})
+		.collect()
+}
+
+/// Splits a table row into its trimmed cells. Leading and trailing pipes are
+/// optional and escaped pipes are part of the cell content. `source_offset`
+/// is where the row starts in the source.
+fn split_row(row: &str, source_offset: usize) -> Vec<Text> {
+	let trimmed = row.trim_matches([' ', '\t']);
+	let start = row.len() - row.trim_start_matches([' ', '\t']).len();
+	let (row, start) = match trimmed.strip_prefix('|') {
+		Some(r) => (r, start + 1),
+		None => (trimmed, start),
+	};
+	let row = match row.strip_suffix('|') {
+		Some(r) if !r.ends_with('\\') => r,
+		_ => row,
+	};
+
+	let mut cells = Vec::new();
+	let mut cell = String::new();
+	let mut cell_start = 0;
+	let mut chars = row.char_indices();
+	while let Some((i, c)) = chars.next() {
+		match c {
+			'\\' => match chars.next() {
+				Some((_, '|')) => cell.push('|'),
+				Some((_, next)) => {
+					cell.push('\\');
+					cell.push(next);
+				}
+				None => cell.push('\\'),
+			},
+			'|' => {
+				cells.push((std::mem::take(&mut cell), cell_start));
+				cell_start = i + 1;
+			}
+			_ => cell.push(c),
+		}
+	}
+	cells.push((cell, cell_start));
+	cells
+		.into_iter()
+		.map(|(c, cell_start)| {
+			let text = c.trim_matches([' ', '\t']);
+			let indent = c.len() - c.trim_start_matches([' ', '\t']).len();
+			Text::new(text, source_offset + start + cell_start + indent)
+		})
+		.collect()
+}
+
+/// Removes the checkbox of a task list item from its first paragraph.
+///
+/// See <https://github.github.com/gfm/#task-list-items-extension->.
+fn strip_task_box(text: &mut Text) -> Option<TaskBox> {
+	let task = match text.text.get(..3)? {
+		"[ ]" => TaskBox::Unchecked,
+		"[x]" | "[X]" => TaskBox::Checked,
+		_ => return None,
+	};
+	if !text.text[3..].starts_with([' ', '\t', '\n']) {
+		return None;
+	}
+	text.remove_start(text.text.len() - text.text[3..].trim_start().len());
+	Some(task)
+}
+
+/// Removes the blank lines at the end of an indented code block.
+fn trim_trailing_blank_lines(content: &str) -> String {
+	let mut end = 0;
+	let mut offset = 0;
+	for line in content.split_inclusive('\n') {
+		offset += line.len();
+		if !line.trim().is_empty() {
+			end = offset;
+		}
+	}
+	content[..end].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn paragraph(block: &Block) -> &str {
+		match block {
+			Block::Paragraph(text) => &text.text,
+			other => panic!("{:?} is not a paragraph", other),
+		}
+	}
+
+	#[test]
+	fn test_split_lines() {
+		let source = "this is some plaintext in a line\nAnd a new line with \
+		              more content";
+		let (blocks, _) = parse(source, &Options::default());
+		let [Block::Paragraph(text)] = &blocks[..] else {
+			panic!("{:?} is not a single paragraph", blocks);
+		};
+		assert_eq!(text.text, source);
+		let second = text.text.find("And").unwrap();
+		assert_eq!(text.source_offset(second), source.find("And").unwrap());
+	}
+
+	#[test]
+	fn test_degenerate_newlines() {
+		let source = "\n\n\n\n\nfoo\n";
+		let (blocks, _) = parse(source, &Options::default());
+		let [Block::Paragraph(text)] = &blocks[..] else {
+			panic!("{:?} is not a single paragraph", blocks);
+		};
+		assert_eq!(text.text, "foo");
+		assert_eq!(text.source_offset(0), 5);
+	}
+
+	#[test]
+	fn test_mixed_chars() {
+		let (blocks, _) = parse("  - foo\n\n\tbar\n", &Options::default());
+		let [Block::List(ListKind::Bullet('-'), false, items, 2)] = &blocks[..]
+		else {
+			panic!("{:?} is not a loose list", blocks);
+		};
+		let [Block::ListItem(None, content)] = &items[..] else {
+			panic!("{:?} is not a single list item", items);
+		};
+		let content: Vec<&str> = content.iter().map(paragraph).collect();
+		assert_eq!(content, ["foo", "bar"]);
+	}
+}
@@ -0,0 +1,134 @@
+use core::fmt;
+use std::ops::Range;
+
+/// Something in the source that was rendered differently than its author
+/// likely intended, eg. a link that is missing its closing parenthesis.
+///
+/// Returned by
+/// [Parser::render_with_diagnostics](crate::Parser::render_with_diagnostics).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+	pub kind:     DiagnosticKind,
+	pub severity: Severity,
+	pub message:  String,
+	/// Byte range of the offending markup in the source
+	pub span:     Range<usize>,
+}
+
+/// What went wrong, independent of the wording of the message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagnosticKind {
+	/// A `~~` without a matching `~~` on the same line
+	UnclosedStrikethrough,
+	/// A run of backticks without a run of the same length after it
+	UnclosedCodeSpan,
+	/// A `[text](` that is not followed by a destination and `)`
+	MalformedLink,
+	/// A link or image destination that is refused, eg. `javascript:`
+	UnsafeLink,
+	/// Text in angle brackets that is neither an autolink nor rendered as
+	/// html
+	InvalidAutolink,
+	/// Blocks or links nested deeper than
+	/// [Options::max_nesting](crate::Options::max_nesting)
+	NestingTooDeep,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+	/// The markup is kept as text
+	Warning,
+	/// Part of the document is not rendered as written
+	Error,
+}
+
+impl fmt::Display for Severity {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Severity::Warning => write!(f, "warning"),
+			Severity::Error => write!(f, "error"),
+		}
+	}
+}
+
+impl Diagnostic {
+	pub(crate) fn warning(
+		kind: DiagnosticKind,
+		span: Range<usize>,
+		message: String,
+	) -> Self {
+		Diagnostic {
+			kind,
+			severity: Severity::Warning,
+			message,
+			span,
+		}
+	}
+
+	pub(crate) fn error(
+		kind: DiagnosticKind,
+		span: Range<usize>,
+		message: String,
+	) -> Self {
+		Diagnostic {
+			kind,
+			severity: Severity::Error,
+			message,
+			span,
+		}
+	}
+
+	/// Returns the 1-based line and column, counted in characters, at which
+	/// the diagnostic starts in `source`.
+	pub fn line_column(&self, source: &str) -> (usize, usize) {
+		line_column(source, self.span.start)
+	}
+}
+
+impl fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}: {}", self.severity, self.message)
+	}
+}
+
+/// Returns the 1-based line and column, counted in characters, of a byte
+/// offset in `source`.
+pub(crate) fn line_column(source: &str, offset: usize) -> (usize, usize) {
+	let before = &source[..floor_char_boundary(source, offset)];
+	let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+	(
+		before.matches('\n').count() + 1,
+		before[line_start..].chars().count() + 1,
+	)
+}
+
+/// Returns the largest character boundary of `source` not after `index`.
+fn floor_char_boundary(source: &str, index: usize) -> usize {
+	let mut index = index.min(source.len());
+	while !source.is_char_boundary(index) {
+		index -= 1;
+	}
+	index
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn line_and_column_are_one_based() {
+		let source = "foo\nbär `baz";
+		let diagnostic = Diagnostic::warning(
+			DiagnosticKind::UnclosedCodeSpan,
+			9..10,
+			String::new(),
+		);
+		assert_eq!(diagnostic.line_column(source), (2, 5));
+		let diagnostic = Diagnostic::warning(
+			DiagnosticKind::UnclosedCodeSpan,
+			0..1,
+			String::new(),
+		);
+		assert_eq!(diagnostic.line_column(source), (1, 1));
+	}
+}
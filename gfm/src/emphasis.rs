@@ -0,0 +1,318 @@
+//! Resolves [Token::Delimiter] runs into nested [Token::Emphasis] and
+//! [Token::Strong] tokens using the delimiter stack algorithm described in
+//! <https://spec.commonmark.org/0.29/#phase-2-inline-structure>.
+
+use crate::token::{
+	DelimiterRun,
+	Token,
+};
+
+/// Resolves the delimiter runs of the inline content of a leaf block.
+///
+/// Runs that do not take part in any emphasis are turned into plaintext, and
+/// so are the delimiters of emphasis that would end up nested deeper than
+/// `max_nesting`.
+pub(crate) fn resolve(
+	mut tokens: Vec<Token<'_>>,
+	max_nesting: usize,
+) -> Vec<Token<'_>> {
+	if !tokens.iter().any(|t| matches!(t, Token::Delimiter(_))) {
+		return tokens;
+	}
+	let marks = process_emphasis(&mut tokens);
+	build(tokens, marks, max_nesting)
+}
+
+/// Emphasis a delimiter run closes and opens. Both are ordered from the
+/// innermost to the outermost emphasis, `true` marking strong emphasis.
+#[derive(Debug, Default)]
+struct Marks {
+	closes: Vec<bool>,
+	opens:  Vec<bool>,
+}
+
+/// Matches closers with the nearest possible opener, from left to right, and
+/// returns the emphasis every delimiter run takes part in. The delimiters of
+/// a run that are used up are subtracted from its count.
+///
+/// Delimiter runs are kept in a linked list, so those between a matched
+/// opener and closer are removed at once, and the search for an opener
+/// never looks at the same runs twice for the same kind of closer. This
+/// keeps the whole pass linear.
+fn process_emphasis(tokens: &mut [Token<'_>]) -> Vec<Marks> {
+	let mut runs: Vec<(usize, DelimiterRun)> = tokens
+		.iter()
+		.enumerate()
+		.filter_map(|(i, t)| match t {
+			Token::Delimiter(run) => Some((i, *run)),
+			_ => None,
+		})
+		.collect();
+	let len = runs.len();
+	let mut marks: Vec<Marks> = (0..len).map(|_| Marks::default()).collect();
+	let mut prev: Vec<Option<usize>> =
+		(0..len).map(|i| i.checked_sub(1)).collect();
+	let mut next: Vec<Option<usize>> =
+		(0..len).map(|i| (i + 1 < len).then_some(i + 1)).collect();
+
+	// Runs at or below this index are known to not match a closer, indexed
+	// by delimiter character, closer length modulo 3 and whether the closer
+	// can open.
+	let mut openers_bottom = [[[None; 2]; 3]; 2];
+
+	let mut closer = (len > 0).then_some(0);
+	while let Some(c) = closer {
+		let run = runs[c].1;
+		if !run.can_close {
+			closer = next[c];
+			continue;
+		}
+		let bottom = &mut openers_bottom[usize::from(run.ch == '_')]
+			[run.length % 3][usize::from(run.can_open)];
+
+		let mut opener = prev[c];
+		while let Some(o) = opener {
+			if bottom.is_some_and(|b| o <= b) {
+				opener = None;
+				break;
+			}
+			if is_match(&runs[o].1, &run) {
+				break;
+			}
+			opener = prev[o];
+		}
+		let Some(o) = opener else {
+			*bottom = prev[c];
+			if !run.can_open {
+				unlink(&mut prev, &mut next, c);
+			}
+			closer = next[c];
+			continue;
+		};
+
+		let strong = runs[o].1.count >= 2 && run.count >= 2;
+		let used = if strong { 2 } else { 1 };
+		runs[o].1.count -= used;
+		runs[c].1.count -= used;
+		marks[o].opens.push(strong);
+		marks[c].closes.push(strong);
+
+		// Delimiters between opener and closer can no longer match.
+		next[o] = Some(c);
+		prev[c] = Some(o);
+		if runs[o].1.count == 0 {
+			unlink(&mut prev, &mut next, o);
+		}
+		if runs[c].1.count == 0 {
+			closer = next[c];
+			unlink(&mut prev, &mut next, c);
+		}
+	}
+
+	for (i, run) in runs.iter() {
+		tokens[*i] = Token::Delimiter(*run);
+	}
+	marks
+}
+
+/// Removes the run `i` from the linked list of delimiter runs.
+fn unlink(prev: &mut [Option<usize>], next: &mut [Option<usize>], i: usize) {
+	if let Some(p) = prev[i] {
+		next[p] = next[i];
+	}
+	if let Some(n) = next[i] {
+		prev[n] = prev[i];
+	}
+}
+
+/// Nests the tokens between the delimiters of every emphasis.
+///
+/// The delimiters a run closes with come first, followed by those left as
+/// text and those it opens with.
+fn build<'a>(
+	tokens: Vec<Token<'a>>,
+	marks: Vec<Marks>,
+	max_nesting: usize,
+) -> Vec<Token<'a>> {
+	let nested = nested_emphasis(&tokens, &marks, max_nesting);
+	let mut nested = nested.into_iter();
+	let mut marks = marks.into_iter();
+	// Tokens of the emphasis that is nested, innermost last
+	let mut stack: Vec<Vec<Token<'a>>> = vec![Vec::new()];
+	// Every open emphasis as its delimiter, whether it is strong and whether
+	// it is nested or rendered as text
+	let mut open: Vec<(char, bool, bool)> = Vec::new();
+
+	for token in tokens.into_iter() {
+		let Token::Delimiter(run) = token else {
+			if let Some(top) = stack.last_mut() {
+				top.push(token);
+			}
+			continue;
+		};
+		let Marks { closes, opens } = marks.next().unwrap_or_default();
+		for _ in closes.iter() {
+			let Some((ch, strong, is_nested)) = open.pop() else {
+				continue;
+			};
+			let token = match is_nested.then(|| stack.pop()).flatten() {
+				Some(inner) if strong => Token::Strong(inner),
+				Some(inner) => Token::Emphasis(inner),
+				None => literal(ch, strong),
+			};
+			if let Some(top) = stack.last_mut() {
+				top.push(token);
+			}
+		}
+		if run.count > 0 {
+			if let Some(top) = stack.last_mut() {
+				top.push(into_literal(token));
+			}
+		}
+		for strong in opens.iter().rev() {
+			let is_nested = nested.next().unwrap_or(false);
+			match is_nested {
+				true => stack.push(Vec::new()),
+				false => {
+					if let Some(top) = stack.last_mut() {
+						top.push(literal(run.ch, *strong));
+					}
+				}
+			}
+			open.push((run.ch, *strong, is_nested));
+		}
+	}
+
+	// Every emphasis is closed, this only keeps the tokens if one is not
+	while stack.len() > 1 {
+		let inner = stack.pop().unwrap_or_default();
+		if let Some(top) = stack.last_mut() {
+			top.extend(inner);
+		}
+	}
+	stack.pop().unwrap_or_default()
+}
+
+/// Decides, in the order they are opened, which emphasis is nested and which
+/// is rendered as text, so no token ends up nested deeper than
+/// `max_nesting`. Inner emphasis is kept over the emphasis around it.
+fn nested_emphasis(
+	tokens: &[Token<'_>],
+	marks: &[Marks],
+	max_nesting: usize,
+) -> Vec<bool> {
+	let mut nested = Vec::new();
+	let mut marks = marks.iter();
+	// Index into `nested` and depth of the most deeply nested token of every
+	// open emphasis, innermost last
+	let mut stack: Vec<(usize, usize)> = vec![(0, 0)];
+
+	for token in tokens.iter() {
+		let Token::Delimiter(_) = token else {
+			if let Some(top) = stack.last_mut() {
+				top.1 = top.1.max(token.depth());
+			}
+			continue;
+		};
+		let Some(Marks { closes, opens }) = marks.next() else {
+			continue;
+		};
+		for _ in closes.iter() {
+			if stack.len() < 2 {
+				continue;
+			}
+			let Some((index, depth)) = stack.pop() else {
+				continue;
+			};
+			let is_nested = depth < max_nesting;
+			nested[index] = is_nested;
+			if let Some(top) = stack.last_mut() {
+				top.1 = top.1.max(depth + usize::from(is_nested));
+			}
+		}
+		for _ in opens.iter() {
+			stack.push((nested.len(), 0));
+			nested.push(false);
+		}
+	}
+	nested
+}
+
+/// Delimiters of an emphasis that is rendered as text.
+fn literal(ch: char, strong: bool) -> Token<'static> {
+	Token::Plaintext(
+		std::iter::repeat_n(ch, 1 + usize::from(strong))
+			.collect::<String>()
+			.into(),
+	)
+}
+
+/// Checks whether `opener` can open the emphasis `closer` closes.
+fn is_match(opener: &DelimiterRun, closer: &DelimiterRun) -> bool {
+	if opener.ch != closer.ch || !opener.can_open {
+		return false;
+	}
+	// The rule of 3: if one of the runs can both open and close, the sum of
+	// both lengths must not be a multiple of 3 unless both lengths are.
+	!((opener.can_close || closer.can_open) &&
+		(opener.length + closer.length).is_multiple_of(3) &&
+		!(opener.length.is_multiple_of(3) &&
+			closer.length.is_multiple_of(3)))
+}
+
+fn into_literal(token: Token<'_>) -> Token<'_> {
+	match token {
+		Token::Delimiter(run) => Token::Plaintext(run.literal().into()),
+		t => t,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn run(ch: char, length: usize, prev: char, next: char) -> Token<'static> {
+		Token::Delimiter(DelimiterRun::new(ch, length, Some(prev), Some(next)))
+	}
+
+	fn text(t: &'static str) -> Token<'static> {
+		Token::Plaintext(t.into())
+	}
+
+	#[test]
+	fn intraword_underscore_does_not_open() {
+		let tokens = vec![text("foo"), run('_', 1, 'o', 'b'), text("bar")];
+		assert_eq!(resolve(tokens, 64), vec![
+			text("foo"),
+			text("_"),
+			text("bar")
+		]);
+	}
+
+	#[test]
+	fn nested_strong_in_emphasis() {
+		// ***foo***
+		let tokens =
+			vec![run('*', 3, ' ', 'f'), text("foo"), run('*', 3, 'o', ' ')];
+		assert_eq!(resolve(tokens, 64), vec![Token::Emphasis(vec![
+			Token::Strong(vec![text("foo")])
+		])]);
+	}
+
+	#[test]
+	fn rule_of_three() {
+		// *foo**bar*
+		let tokens = vec![
+			run('*', 1, ' ', 'f'),
+			text("foo"),
+			run('*', 2, 'o', 'b'),
+			text("bar"),
+			run('*', 1, 'r', ' '),
+		];
+		assert_eq!(resolve(tokens, 64), vec![Token::Emphasis(vec![
+			text("foo"),
+			text("**"),
+			text("bar"),
+		])]);
+	}
+}
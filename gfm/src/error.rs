@@ -0,0 +1,51 @@
+use core::fmt;
+
+use crate::Diagnostic;
+
+/// Reasons [Parser::try_render](crate::Parser::try_render) refuses to render
+/// a source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+	/// The source is larger than
+	/// [Options::max_input_size](crate::Options::max_input_size)
+	InputTooLarge { size: usize, limit: usize },
+	/// Blocks starting on `line` are nested deeper than
+	/// [Options::max_nesting](crate::Options::max_nesting)
+	NestingTooDeep { line: usize, limit: usize },
+	/// Markup starting at `line` and `column` cannot be parsed and
+	/// [Options::on_parse_error](crate::Options::on_parse_error) is
+	/// [ParseErrorAction::Abort](crate::ParseErrorAction::Abort)
+	InvalidMarkup {
+		line:       usize,
+		column:     usize,
+		diagnostic: Diagnostic,
+	},
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Error::InputTooLarge { size, limit } => write!(
+				f,
+				"input of {} bytes exceeds the limit of {} bytes",
+				size, limit
+			),
+			Error::NestingTooDeep { line, limit } => write!(
+				f,
+				"line {}: blocks are nested deeper than the limit of {}",
+				line, limit
+			),
+			Error::InvalidMarkup {
+				line,
+				column,
+				diagnostic,
+			} => write!(
+				f,
+				"line {}, column {}: {}",
+				line, column, diagnostic.message
+			),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,224 @@
+//! Syntax that is not part of GFM, eg. `==highlight==`, `[[wikilinks]]` or
+//! `{{< shortcodes >}}`, registered in [Options::extensions].
+//!
+//! A syntax is looked for wherever its trigger character is found, before
+//! the markup of GFM that starts with the same character, and produces a
+//! [Custom] node of the document tree. Nodes of a syntax that is not
+//! registered when the document is rendered, eg. because it was read from
+//! JSON, are rendered as their content.
+
+use std::{
+	fmt,
+	ops::Range,
+	sync::Arc,
+};
+
+use crate::{
+	ast::Custom,
+	html,
+	Options,
+};
+
+/// Where a syntax was found, as returned by its parse function.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Match {
+	/// Number of bytes of the input that belong to the syntax
+	pub length:  usize,
+	/// Stored as [Custom::value], eg. the page a wikilink points to
+	pub value:   String,
+	/// Part of the input that is lexed as markdown into
+	/// [Custom::children], eg. the text of a highlight
+	pub content: Option<Range<usize>>,
+}
+
+/// Syntax within the inline content of paragraphs, headings and table cells.
+///
+/// ### Example
+///
+/// ```rust
+/// use gfm::Custom;
+/// use gfm::Extensions;
+/// use gfm::InlineSyntax;
+/// use gfm::Match;
+/// use gfm::Options;
+/// use gfm::Parser;
+///
+/// /// Marks text wrapped in `==`.
+/// struct Highlight;
+///
+/// impl InlineSyntax for Highlight {
+/// 	fn name(&self) -> &str {
+/// 		"highlight"
+/// 	}
+///
+/// 	fn trigger(&self) -> char {
+/// 		'='
+/// 	}
+///
+/// 	fn parse(&self, input: &str) -> Option<Match> {
+/// 		let end = input.strip_prefix("==")?.find("==")? + 2;
+/// 		Some(Match {
+/// 			length: end + 2,
+/// 			content: Some(2..end),
+/// 			..Default::default()
+/// 		})
+/// 	}
+///
+/// 	fn render(&self, _: &Custom, content: &str) -> String {
+/// 		format!("<mark>{}</mark>", content)
+/// 	}
+/// }
+///
+/// let options = Options {
+/// 	extensions: Extensions::default().inline(Highlight),
+/// 	..Default::default()
+/// };
+/// let html = Parser::render_with_options("a ==*b*== c", &options);
+/// assert_eq!(html, "<p>a <mark><em>b</em></mark> c</p>\n");
+/// ```
+pub trait InlineSyntax: Send + Sync {
+	/// Name of the syntax, stored in every node it produces.
+	fn name(&self) -> &str;
+
+	/// First character of the syntax.
+	fn trigger(&self) -> char;
+
+	/// Looks for the syntax at the start of `input`, which begins with the
+	/// trigger and runs to the end of the inline content. Returns `None` if
+	/// it is not there, so the input is lexed as usual.
+	fn parse(&self, input: &str) -> Option<Match>;
+
+	/// Renders a node produced by the syntax. `content` is the rendered html
+	/// of its children. Anything taken from [Custom::value] has to be
+	/// escaped, eg. with [escape].
+	fn render(&self, node: &Custom, content: &str) -> String;
+}
+
+/// Syntax that takes up a whole line, like a heading.
+pub trait BlockSyntax: Send + Sync {
+	/// Name of the syntax, stored in every node it produces.
+	fn name(&self) -> &str;
+
+	/// First character of the syntax, after the indentation of the line.
+	fn trigger(&self) -> char;
+
+	/// Looks for the syntax in a line, without its indentation and trailing
+	/// whitespace, which begins with the trigger. Matches that do not take
+	/// up the whole line are ignored.
+	fn parse(&self, line: &str) -> Option<Match>;
+
+	/// Renders a node produced by the syntax. `content` is the rendered html
+	/// of its children. Anything taken from [Custom::value] has to be
+	/// escaped, eg. with [escape].
+	fn render(&self, node: &Custom, content: &str) -> String;
+}
+
+/// Syntax extensions of the parser, none by default.
+#[derive(Clone, Default)]
+pub struct Extensions {
+	inline: Vec<Arc<dyn InlineSyntax>>,
+	block:  Vec<Arc<dyn BlockSyntax>>,
+}
+
+impl Extensions {
+	/// Adds an inline syntax. Syntaxes with the same trigger are tried in the
+	/// order they are added.
+	pub fn inline(mut self, syntax: impl InlineSyntax + 'static) -> Self {
+		self.inline.push(Arc::new(syntax));
+		self
+	}
+
+	/// Adds a block syntax. Syntaxes with the same trigger are tried in the
+	/// order they are added.
+	pub fn block(mut self, syntax: impl BlockSyntax + 'static) -> Self {
+		self.block.push(Arc::new(syntax));
+		self
+	}
+
+	pub(crate) fn inline_syntaxes(&self) -> &[Arc<dyn InlineSyntax>] {
+		&self.inline
+	}
+
+	pub(crate) fn block_syntaxes(&self) -> &[Arc<dyn BlockSyntax>] {
+		&self.block
+	}
+}
+
+impl fmt::Debug for Extensions {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Extensions")
+			.field(
+				"inline",
+				&self.inline.iter().map(|s| s.name()).collect::<Vec<_>>(),
+			)
+			.field(
+				"block",
+				&self.block.iter().map(|s| s.name()).collect::<Vec<_>>(),
+			)
+			.finish()
+	}
+}
+
+/// Extensions are equal if they hold the very same syntaxes.
+impl PartialEq for Extensions {
+	fn eq(&self, other: &Self) -> bool {
+		self.inline.len() == other.inline.len() &&
+			self.block.len() == other.block.len() &&
+			self.inline
+				.iter()
+				.zip(other.inline.iter())
+				.all(|(a, b)| Arc::ptr_eq(a, b)) &&
+			self.block
+				.iter()
+				.zip(other.block.iter())
+				.all(|(a, b)| Arc::ptr_eq(a, b))
+	}
+}
+
+impl Eq for Extensions {}
+
+/// Escapes text for the content of an element or the value of an
+/// attribute, like the text of the document is escaped.
+pub fn escape(text: &str) -> String {
+	html::attribute(text)
+}
+
+/// Renders an inline node with its syntax, or only its content if the
+/// syntax is not registered.
+pub(crate) fn render_inline(
+	node: &Custom,
+	content: &str,
+	options: &Options,
+) -> String {
+	match options
+		.extensions
+		.inline
+		.iter()
+		.find(|s| s.name() == node.name)
+	{
+		Some(syntax) => syntax.render(node, content),
+		None if node.children.is_empty() => escape(&node.value),
+		None => content.to_string(),
+	}
+}
+
+/// Renders a block node with its syntax, or its content as a paragraph if
+/// the syntax is not registered.
+pub(crate) fn render_block(
+	node: &Custom,
+	content: &str,
+	options: &Options,
+) -> String {
+	match options
+		.extensions
+		.block
+		.iter()
+		.find(|s| s.name() == node.name)
+	{
+		Some(syntax) => syntax.render(node, content),
+		None if node.children.is_empty() => {
+			format!("<p>{}</p>\n", escape(&node.value))
+		}
+		None => format!("<p>{}</p>\n", content),
+	}
+}
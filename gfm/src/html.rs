@@ -0,0 +1,421 @@
+//! Encoding of text for the html context it is written to, and recognition
+//! of the raw html that may be written in markdown.
+//!
+//! Everything the renderer writes goes through one of [text], [attribute]
+//! and [url], so nothing taken from the source can end an element or an
+//! attribute early. Raw html is only ever written by the
+//! [Sanitizer](crate::Sanitizer).
+
+use crate::token::percent_encode;
+
+/// Escapes text for the content of an element.
+pub(crate) fn text(source: &str) -> String {
+	let mut escaped = String::with_capacity(source.len());
+	for c in source.chars() {
+		match c {
+			'&' => escaped.push_str("&amp;"),
+			'<' => escaped.push_str("&lt;"),
+			'>' => escaped.push_str("&gt;"),
+			'"' => escaped.push_str("&quot;"),
+			c => escaped.push(c),
+		}
+	}
+	escaped
+}
+
+/// Escapes text for the value of a double quoted attribute. Single quotes
+/// are escaped as well, so the value is safe in either kind of quotes.
+pub(crate) fn attribute(source: &str) -> String {
+	text(source).replace('\'', "&#39;")
+}
+
+/// Encodes a link destination for the value of an `href` or `src`
+/// attribute. Characters that are not allowed in a url are percent encoded.
+pub(crate) fn url(source: &str) -> String {
+	percent_encode(source).replace('&', "&amp;")
+}
+
+/// Escapes raw html that is kept as it is written, eg. the text between
+/// allowed tags. Entity and character references are kept, every other `&`
+/// is escaped along with `<`, `>` and `"`.
+pub(crate) fn raw(source: &str) -> String {
+	let mut escaped = String::with_capacity(source.len());
+	for (i, c) in source.char_indices() {
+		match c {
+			'&' if is_reference(&source[i..]) => escaped.push('&'),
+			'&' => escaped.push_str("&amp;"),
+			'<' => escaped.push_str("&lt;"),
+			'>' => escaped.push_str("&gt;"),
+			'"' => escaped.push_str("&quot;"),
+			c => escaped.push(c),
+		}
+	}
+	escaped
+}
+
+/// Checks whether `source` starts with an entity or numeric character
+/// reference, eg. `&amp;`, `&#35;` or `&#x23;`.
+fn is_reference(source: &str) -> bool {
+	let Some(end) =
+		source.get(..source.len().min(34)).and_then(|s| s.find(';'))
+	else {
+		return false;
+	};
+	let name = &source[1..end];
+	match name.strip_prefix('#') {
+		Some(hex) if hex.starts_with(['x', 'X']) => {
+			(2..=7).contains(&hex.len()) &&
+				hex[1..].bytes().all(|b| b.is_ascii_hexdigit())
+		}
+		Some(dec) => {
+			(1..=7).contains(&dec.len()) &&
+				dec.bytes().all(|b| b.is_ascii_digit())
+		}
+		None => {
+			!name.is_empty() &&
+				name.starts_with(|c: char| c.is_ascii_alphabetic()) &&
+				name.bytes().all(|b| b.is_ascii_alphanumeric())
+		}
+	}
+}
+
+/// Replaces the character references of an attribute value with the
+/// characters they stand for. Returns `None` for named references other
+/// than a few common ones, whose meaning cannot be checked.
+pub(crate) fn decode(source: &str) -> Option<String> {
+	let mut decoded = String::with_capacity(source.len());
+	let mut rest = source;
+	while let Some(start) = rest.find('&') {
+		decoded.push_str(&rest[..start]);
+		rest = &rest[start..];
+		if !is_reference(rest) {
+			decoded.push('&');
+			rest = &rest[1..];
+			continue;
+		}
+		let end = rest.find(';').unwrap_or_default();
+		let name = &rest[1..end];
+		let c = match name.strip_prefix('#') {
+			Some(hex) if hex.starts_with(['x', 'X']) => {
+				u32::from_str_radix(&hex[1..], 16)
+					.ok()
+					.and_then(char::from_u32)
+			}
+			Some(dec) => dec.parse().ok().and_then(char::from_u32),
+			None => match name {
+				"amp" => Some('&'),
+				"lt" => Some('<'),
+				"gt" => Some('>'),
+				"quot" => Some('"'),
+				"apos" => Some('\''),
+				"colon" => Some(':'),
+				"sol" => Some('/'),
+				"Tab" => Some('\t'),
+				"NewLine" => Some('\n'),
+				_ => return None,
+			},
+		};
+		decoded.push(c.unwrap_or(char::REPLACEMENT_CHARACTER));
+		rest = &rest[end + 1..];
+	}
+	decoded.push_str(rest);
+	Some(decoded)
+}
+
+/// Raw html at the start of some input.
+///
+/// See <https://spec.commonmark.org/0.29/#raw-html>.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Tag<'a> {
+	/// `<name attribute="value">`, which closes itself if it ends in `/>`
+	Open {
+		name:         &'a str,
+		attributes:   Vec<Attribute<'a>>,
+		self_closing: bool,
+	},
+	/// `</name>`
+	Close(&'a str),
+	/// A comment, processing instruction, declaration or CDATA section
+	Other,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Attribute<'a> {
+	pub(crate) name:  &'a str,
+	/// Value without its quotes, as it is written
+	pub(crate) value: Option<&'a str>,
+}
+
+/// Ends of raw html that are searched for further along the input.
+const DOUBLE_QUOTE: usize = 0;
+const SINGLE_QUOTE: usize = 1;
+const COMMENT_END: usize = 2;
+const INSTRUCTION_END: usize = 3;
+const DECLARATION_END: usize = 4;
+const CDATA_END: usize = 5;
+const TERMINATORS: [&str; 6] = ["\"", "'", "-->", "?>", ">", "]]>"];
+
+/// Remembers from where on each terminator is known to be missing in the
+/// input, so looking for raw html at every `<` takes linear time.
+#[derive(Debug)]
+pub(crate) struct Scan {
+	missing: [usize; TERMINATORS.len()],
+}
+
+impl Default for Scan {
+	fn default() -> Self {
+		Scan {
+			missing: [usize::MAX; TERMINATORS.len()],
+		}
+	}
+}
+
+impl Scan {
+	/// Returns the offset of the first terminator at or after `from`.
+	fn find(
+		&mut self,
+		input: &str,
+		from: usize,
+		terminator: usize,
+	) -> Option<usize> {
+		if from >= self.missing[terminator] {
+			return None;
+		}
+		match input.get(from..)?.find(TERMINATORS[terminator]) {
+			Some(i) => Some(from + i),
+			None => {
+				self.missing[terminator] = from;
+				None
+			}
+		}
+	}
+}
+
+/// Recognizes raw html at `start` in the input and returns it along with the
+/// offset of its end.
+pub(crate) fn tag<'a>(
+	input: &'a str,
+	start: usize,
+	scan: &mut Scan,
+) -> Option<(Tag<'a>, usize)> {
+	let rest = input.get(start..)?;
+	let bytes = rest.as_bytes();
+	if !rest.starts_with('<') {
+		return None;
+	}
+
+	if let Some(text) = rest.strip_prefix("<!--") {
+		if text.starts_with('>') || text.starts_with("->") {
+			return None;
+		}
+		let end = scan.find(input, start + 4, COMMENT_END)?;
+		let text = &input[start + 4..end];
+		if text.contains("--") || text.ends_with('-') {
+			return None;
+		}
+		return Some((Tag::Other, end + 3));
+	}
+	if rest.starts_with("<?") {
+		let end = scan.find(input, start + 2, INSTRUCTION_END)?;
+		return Some((Tag::Other, end + 2));
+	}
+	if rest.starts_with("<![CDATA[") {
+		let end = scan.find(input, start + 9, CDATA_END)?;
+		return Some((Tag::Other, end + 3));
+	}
+	if rest.starts_with("<!") &&
+		bytes.get(2).is_some_and(u8::is_ascii_alphabetic)
+	{
+		let end = scan.find(input, start + 2, DECLARATION_END)?;
+		return Some((Tag::Other, end + 1));
+	}
+
+	if let Some(after) = rest.strip_prefix("</") {
+		let length = tag_name(after)?;
+		let end = 2 + length + whitespace(&rest[2 + length..]);
+		return (bytes.get(end) == Some(&b'>'))
+			.then(|| (Tag::Close(&rest[2..2 + length]), start + end + 1));
+	}
+
+	let length = tag_name(&rest[1..])?;
+	let name = &rest[1..1 + length];
+	let mut i = 1 + length;
+	let mut attributes = Vec::new();
+	loop {
+		let spaces = whitespace(&rest[i..]);
+		i += spaces;
+		match bytes.get(i) {
+			Some(b'>') => {
+				let tag = Tag::Open {
+					name,
+					attributes,
+					self_closing: false,
+				};
+				return Some((tag, start + i + 1));
+			}
+			Some(b'/') if bytes.get(i + 1) == Some(&b'>') => {
+				let tag = Tag::Open {
+					name,
+					attributes,
+					self_closing: true,
+				};
+				return Some((tag, start + i + 2));
+			}
+			_ if spaces == 0 => return None,
+			_ => {}
+		}
+
+		let name_length = attribute_name(&rest[i..])?;
+		let attribute = &rest[i..i + name_length];
+		i += name_length;
+		let before_value = i + whitespace(&rest[i..]);
+		if bytes.get(before_value) != Some(&b'=') {
+			attributes.push(Attribute {
+				name:  attribute,
+				value: None,
+			});
+			continue;
+		}
+		let v = before_value + 1 + whitespace(&rest[before_value + 1..]);
+		let value = match bytes.get(v) {
+			Some(quote @ (b'"' | b'\'')) => {
+				let terminator = match quote {
+					b'"' => DOUBLE_QUOTE,
+					_ => SINGLE_QUOTE,
+				};
+				let end = scan.find(input, start + v + 1, terminator)? - start;
+				i = end + 1;
+				&rest[v + 1..end]
+			}
+			_ => {
+				let length = rest[v..]
+					.find(|c: char| c.is_whitespace() || "\"'=<>`".contains(c))
+					.unwrap_or(rest.len() - v);
+				if length == 0 {
+					return None;
+				}
+				i = v + length;
+				&rest[v..i]
+			}
+		};
+		attributes.push(Attribute {
+			name:  attribute,
+			value: Some(value),
+		});
+	}
+}
+
+/// Length of the tag name at the start of `source`.
+pub(crate) fn tag_name(source: &str) -> Option<usize> {
+	if !source.starts_with(|c: char| c.is_ascii_alphabetic()) {
+		return None;
+	}
+	Some(
+		source
+			.find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+			.unwrap_or(source.len()),
+	)
+}
+
+/// Length of the attribute name at the start of `source`.
+fn attribute_name(source: &str) -> Option<usize> {
+	if !source
+		.starts_with(|c: char| c.is_ascii_alphabetic() || "_:".contains(c))
+	{
+		return None;
+	}
+	Some(
+		source
+			.find(|c: char| !(c.is_ascii_alphanumeric() || "_.:-".contains(c)))
+			.unwrap_or(source.len()),
+	)
+}
+
+/// Length of the whitespace, including line endings, at the start of
+/// `source`.
+pub(crate) fn whitespace(source: &str) -> usize {
+	source.len() -
+		source
+			.trim_start_matches([' ', '\t', '\n', '\r', '\x0b', '\x0c'])
+			.len()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn tag_of(input: &str) -> Option<(Tag<'_>, usize)> {
+		tag(input, 0, &mut Scan::default())
+	}
+
+	#[test]
+	fn open_tags_keep_their_attributes() {
+		assert_eq!(
+			tag_of("<a href='x' b = \"y\" c=z d/> e"),
+			Some((
+				Tag::Open {
+					name:         "a",
+					attributes:   vec![
+						Attribute {
+							name:  "href",
+							value: Some("x"),
+						},
+						Attribute {
+							name:  "b",
+							value: Some("y"),
+						},
+						Attribute {
+							name:  "c",
+							value: Some("z"),
+						},
+						Attribute {
+							name:  "d",
+							value: None,
+						},
+					],
+					self_closing: true,
+				},
+				27
+			))
+		);
+	}
+
+	#[test]
+	fn other_raw_html_is_recognized() {
+		assert_eq!(tag_of("</a >b"), Some((Tag::Close("a"), 5)));
+		assert_eq!(tag_of("<!-- a -->b"), Some((Tag::Other, 10)));
+		assert_eq!(tag_of("<?a?>b"), Some((Tag::Other, 5)));
+		assert_eq!(tag_of("<!A b>c"), Some((Tag::Other, 6)));
+		assert_eq!(tag_of("<![CDATA[<a>]]>b"), Some((Tag::Other, 15)));
+	}
+
+	#[test]
+	fn malformed_raw_html_is_not_recognized() {
+		for input in [
+			"<",
+			"<1a>",
+			"<a",
+			"<a b='c>",
+			"<a b=>",
+			"<a\"b\">",
+			"</a b>",
+			"<!-- a -- b -->",
+			"<!-->",
+		] {
+			assert_eq!(tag_of(input), None, "{}", input);
+		}
+	}
+
+	#[test]
+	fn raw_html_keeps_references() {
+		assert_eq!(
+			raw("a &amp; &#35; &#x23; & <b>"),
+			"a &amp; &#35; &#x23; &amp; &lt;b&gt;"
+		);
+		assert_eq!(
+			decode("jav&#x61;script&colon;"),
+			Some("javascript:".into())
+		);
+		assert_eq!(decode("&unknown;"), None);
+	}
+}
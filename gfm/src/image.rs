@@ -0,0 +1,287 @@
+//! How images are rendered, registered in [Options::images](crate::Options),
+//! and the size of local image files.
+
+use std::{
+	fs,
+	path::{
+		Component,
+		Path,
+		PathBuf,
+	},
+};
+
+use crate::{
+	html::{
+		self,
+		Scan,
+		Tag,
+	},
+	resolve,
+};
+
+/// Attributes and markup added to images. Nothing is added by default.
+///
+/// ### Example
+///
+/// ```rust
+/// use gfm::ImageOptions;
+/// use gfm::Options;
+/// use gfm::Parser;
+///
+/// let options = Options {
+/// 	images: ImageOptions {
+/// 		lazy: true,
+/// 		figures: true,
+/// 		..Default::default()
+/// 	},
+/// 	..Default::default()
+/// };
+/// let html = Parser::render_with_options(
+/// 	"![a](a.png \"The a\"){width=300 height=200}",
+/// 	&options,
+/// );
+/// assert_eq!(
+/// 	html,
+/// 	"<figure>\n<img src=\"a.png\" alt=\"a\" title=\"The a\" width=\"300\" \
+/// 	 height=\"200\" loading=\"lazy\" decoding=\"async\" \
+/// 	 referrerpolicy=\"no-referrer\">\n<figcaption>The \
+/// 	 a</figcaption>\n</figure>\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImageOptions {
+	/// Adds `loading="lazy"` and `decoding="async"`, so images are only
+	/// fetched once they are about to be scrolled into view.
+	pub lazy:       bool,
+	/// Renders paragraphs made of a single image with a title as a
+	/// `<figure>`, with the title as its `<figcaption>`.
+	pub figures:    bool,
+	/// Directory the relative sources of local PNG, JPEG and SVG files are
+	/// read from, to add the width and height that are not given. Keeps the
+	/// page from shifting while the images load. Sources outside of it, eg.
+	/// `../a.png`, are not read.
+	pub local_root: Option<PathBuf>,
+}
+
+impl ImageOptions {
+	/// Width and height of an image, where those that are not given are
+	/// taken from the local file, keeping its aspect ratio.
+	pub(crate) fn size(
+		&self,
+		source: &str,
+		width: Option<u32>,
+		height: Option<u32>,
+	) -> (Option<u32>, Option<u32>) {
+		if width.is_some() && height.is_some() {
+			return (width, height);
+		}
+		let Some((file_width, file_height)) = self
+			.local_root
+			.as_deref()
+			.and_then(|root| local_path(root, source))
+			.and_then(|path| dimensions(&path))
+		else {
+			return (width, height);
+		};
+		let scale = |value: u32, from: u32, to: u32| {
+			(u64::from(value) * u64::from(to) + u64::from(from) / 2) /
+				u64::from(from)
+		};
+		match (width, height) {
+			(Some(w), None) => (
+				Some(w),
+				u32::try_from(scale(w, file_width, file_height)).ok(),
+			),
+			(None, Some(h)) => (
+				u32::try_from(scale(h, file_height, file_width)).ok(),
+				Some(h),
+			),
+			_ => (Some(file_width), Some(file_height)),
+		}
+	}
+}
+
+/// Parses the `width=300 height=200` given in braces after an image. Values
+/// are pixels, optionally written with `px` and in quotes.
+pub(crate) fn size(attributes: &str) -> Option<(Option<u32>, Option<u32>)> {
+	let mut size = (None, None);
+	for attribute in attributes.split_whitespace() {
+		let (name, value) = attribute.split_once('=')?;
+		let value = value
+			.strip_prefix('"')
+			.and_then(|v| v.strip_suffix('"'))
+			.unwrap_or(value);
+		let value = value.strip_suffix("px").unwrap_or(value);
+		if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+			return None;
+		}
+		let value = value.parse().ok()?;
+		match name {
+			"width" => size.0 = Some(value),
+			"height" => size.1 = Some(value),
+			_ => return None,
+		}
+	}
+	(size != (None, None)).then_some(size)
+}
+
+/// Path of a relative source within `root`, eg. `root/a/b.png` of
+/// `./a/b.png?c#d`.
+fn local_path(root: &Path, source: &str) -> Option<PathBuf> {
+	if source.starts_with("//") || resolve::scheme(source).is_some() {
+		return None;
+	}
+	let end = source.find(['?', '#']).unwrap_or(source.len());
+	let path = Path::new(source[..end].trim_start_matches('/'));
+	path.components()
+		.all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+		.then(|| root.join(path))
+}
+
+/// Width and height in pixels of a PNG, JPEG or SVG file.
+fn dimensions(path: &Path) -> Option<(u32, u32)> {
+	let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+	let (width, height) = match extension.as_str() {
+		"png" => png(&fs::read(path).ok()?)?,
+		"jpg" | "jpeg" => jpeg(&fs::read(path).ok()?)?,
+		"svg" => svg(&fs::read_to_string(path).ok()?)?,
+		_ => return None,
+	};
+	(width > 0 && height > 0).then_some((width, height))
+}
+
+/// Reads the size from the header chunk, which comes first.
+fn png(bytes: &[u8]) -> Option<(u32, u32)> {
+	if !bytes.starts_with(b"\x89PNG\r\n\x1a\n") || bytes.get(12..16)? != b"IHDR"
+	{
+		return None;
+	}
+	let width = u32::from_be_bytes(bytes.get(16..20)?.try_into().ok()?);
+	let height = u32::from_be_bytes(bytes.get(20..24)?.try_into().ok()?);
+	Some((width, height))
+}
+
+/// Reads the size from the first start of frame segment.
+fn jpeg(bytes: &[u8]) -> Option<(u32, u32)> {
+	if !bytes.starts_with(&[0xff, 0xd8]) {
+		return None;
+	}
+	let u16_at = |i: usize| -> Option<u32> {
+		Some(u16::from_be_bytes(bytes.get(i..i + 2)?.try_into().ok()?).into())
+	};
+	let mut i = 2;
+	loop {
+		if *bytes.get(i)? != 0xff {
+			return None;
+		}
+		while *bytes.get(i)? == 0xff {
+			i += 1;
+		}
+		let marker = bytes[i];
+		i += 1;
+		match marker {
+			// Markers without a segment
+			0x01 | 0xd0..=0xd9 => {}
+			// Start of frame, but not the huffman and arithmetic tables
+			0xc0..=0xcf if !matches!(marker, 0xc4 | 0xc8 | 0xcc) => {
+				return Some((u16_at(i + 5)?, u16_at(i + 3)?));
+			}
+			_ => i += usize::try_from(u16_at(i)?).ok()?,
+		}
+	}
+}
+
+/// Reads the size from the `width` and `height` of the root element, or from
+/// its `viewBox`.
+fn svg(source: &str) -> Option<(u32, u32)> {
+	let start = source.find("<svg")?;
+	let Some((Tag::Open { attributes, .. }, _)) =
+		html::tag(source, start, &mut Scan::default())
+	else {
+		return None;
+	};
+	let attribute = |name: &str| {
+		attributes
+			.iter()
+			.find(|a| a.name.eq_ignore_ascii_case(name))
+			.and_then(|a| a.value)
+	};
+	let pixels = |value: &str| -> Option<u32> {
+		let value: f64 = value.trim().trim_end_matches("px").parse().ok()?;
+		(value.is_finite() && value > 0.0 && value < f64::from(u32::MAX))
+			.then(|| value.round() as u32)
+	};
+	if let (Some(width), Some(height)) = (
+		attribute("width").and_then(pixels),
+		attribute("height").and_then(pixels),
+	) {
+		return Some((width, height));
+	}
+	let view_box: Vec<&str> = attribute("viewBox")?
+		.split([' ', ',', '\t', '\n'])
+		.filter(|v| !v.is_empty())
+		.collect();
+	match view_box[..] {
+		[_, _, width, height] => Some((pixels(width)?, pixels(height)?)),
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sizes_are_parsed_in_pixels() {
+		assert_eq!(size("width=300"), Some((Some(300), None)));
+		assert_eq!(
+			size(" width=\"300px\"  height=20 "),
+			Some((Some(300), Some(20)))
+		);
+		assert_eq!(size("width=50%"), None);
+		assert_eq!(size("width=300 class=a"), None);
+		assert_eq!(size("#id"), None);
+		assert_eq!(size(""), None);
+	}
+
+	#[test]
+	fn dimensions_are_read_from_headers() {
+		let mut png_bytes = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+		png_bytes.extend([0, 0, 1, 44, 0, 0, 0, 200]);
+		assert_eq!(png(&png_bytes), Some((300, 200)));
+		assert_eq!(png(b"\x89PNG\r\n\x1a\n"), None);
+
+		let jpeg_bytes = [
+			0xff, 0xd8, 0xff, 0xe0, 0, 4, 0, 0, 0xff, 0xc0, 0, 11, 8, 0, 200,
+			1, 44,
+		];
+		assert_eq!(jpeg(&jpeg_bytes), Some((300, 200)));
+		assert_eq!(jpeg(&jpeg_bytes[..12]), None);
+
+		assert_eq!(
+			svg("<?xml?>\n<svg width=\"300px\" height='200'></svg>"),
+			Some((300, 200))
+		);
+		assert_eq!(
+			svg("<svg width=\"100%\" viewBox=\"0 0 30.4 20\">"),
+			Some((30, 20))
+		);
+		assert_eq!(svg("<svg>"), None);
+	}
+
+	#[test]
+	fn only_relative_paths_within_the_root_are_read() {
+		let root = Path::new("docs");
+		assert_eq!(
+			local_path(root, "./a/b.png?c#d"),
+			Some(PathBuf::from("docs/./a/b.png"))
+		);
+		assert_eq!(
+			local_path(root, "/a.png"),
+			Some(PathBuf::from("docs/a.png"))
+		);
+		assert_eq!(local_path(root, "../a.png"), None);
+		assert_eq!(local_path(root, "https://x.y/a.png"), None);
+		assert_eq!(local_path(root, "//x.y/a.png"), None);
+	}
+}
@@ -0,0 +1,158 @@
+//! Column-aware indentation as described in
+//! <https://spec.commonmark.org/0.29/#tabs>.
+//!
+//! Tabs are kept in the content, but behave as if they were replaced by
+//! spaces up to the next tab stop when determining the block structure.
+
+/// Number of columns between two tab stops.
+pub(crate) const TAB_STOP: usize = 4;
+
+/// A line of the source that is consumed from left to right while the block
+/// structure is determined.
+#[derive(Debug, Clone)]
+pub(crate) struct Line<'a> {
+	text: &'a str,
+	/// Byte offset up to which the line is consumed
+	offset: usize,
+	/// Column of `offset`
+	column: usize,
+	/// Whether the tab at `offset` is already consumed up to `column`
+	partial_tab: bool,
+	/// Byte offset of the first character after the indentation
+	pub(crate) first_nonspace: usize,
+	/// Width in columns of the indentation in front of `first_nonspace`
+	pub(crate) indent: usize,
+	/// Whether only spaces and tabs are left on the line
+	pub(crate) blank: bool,
+}
+
+impl<'a> Line<'a> {
+	pub(crate) fn new(text: &'a str) -> Self {
+		let mut line = Line {
+			text,
+			offset: 0,
+			column: 0,
+			partial_tab: false,
+			first_nonspace: 0,
+			indent: 0,
+			blank: false,
+		};
+		line.find_first_nonspace();
+		line
+	}
+
+	/// Measures the indentation in front of the unconsumed part of the line.
+	pub(crate) fn find_first_nonspace(&mut self) {
+		let mut column = self.column;
+		let mut first_nonspace = self.offset;
+		for b in self.text.bytes().skip(self.offset) {
+			match b {
+				b' ' => column += 1,
+				b'\t' => column += TAB_STOP - column % TAB_STOP,
+				_ => break,
+			}
+			first_nonspace += 1;
+		}
+		self.first_nonspace = first_nonspace;
+		self.indent = column - self.column;
+		self.blank = first_nonspace == self.text.len();
+	}
+
+	/// Consumes `count` columns, splitting tabs if necessary, or `count`
+	/// characters if `columns` is not set.
+	///
+	/// Only ever called on indentation and ASCII markers, so every step stays
+	/// on a character boundary.
+	pub(crate) fn advance(&mut self, mut count: usize, columns: bool) {
+		let bytes = self.text.as_bytes();
+		while count > 0 && self.offset < bytes.len() {
+			if bytes[self.offset] != b'\t' {
+				self.partial_tab = false;
+				self.offset += 1;
+				self.column += 1;
+				count -= 1;
+				continue;
+			}
+			let to_tab_stop = TAB_STOP - self.column % TAB_STOP;
+			if columns {
+				let advance = to_tab_stop.min(count);
+				self.partial_tab = to_tab_stop > count;
+				self.column += advance;
+				self.offset += usize::from(!self.partial_tab);
+				count -= advance;
+			} else {
+				self.partial_tab = false;
+				self.column += to_tab_stop;
+				self.offset += 1;
+				count -= 1;
+			}
+		}
+	}
+
+	/// Consumes the indentation in front of `first_nonspace`.
+	pub(crate) fn skip_indent(&mut self) {
+		self.advance(self.first_nonspace - self.offset, false);
+	}
+
+	/// Returns the column up to which the line is consumed.
+	pub(crate) fn column(&self) -> usize {
+		self.column
+	}
+
+	/// Returns the byte offset up to which the line is consumed.
+	pub(crate) fn offset(&self) -> usize {
+		self.offset
+	}
+
+	/// Returns the byte at `offset`.
+	pub(crate) fn peek(&self) -> Option<u8> {
+		self.text.as_bytes().get(self.offset).copied()
+	}
+
+	/// Returns the line from `first_nonspace` on.
+	pub(crate) fn nonspace_rest(&self) -> &'a str {
+		&self.text[self.first_nonspace..]
+	}
+
+	/// Returns the unconsumed part of the line. What is left of a partially
+	/// consumed tab is replaced by the spaces it still spans.
+	pub(crate) fn rest(&self) -> String {
+		if !self.partial_tab {
+			return self.text[self.offset..].to_string();
+		}
+		let spaces = TAB_STOP - self.column % TAB_STOP;
+		" ".repeat(spaces) + &self.text[self.offset + 1..]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn tabs_advance_to_the_next_tab_stop() {
+		let mut line = Line::new("  \tfoo");
+		assert_eq!(4, line.indent);
+		line.advance(1, true);
+		line.find_first_nonspace();
+		assert_eq!(3, line.indent);
+		assert_eq!(3, line.first_nonspace);
+	}
+
+	#[test]
+	fn partially_consumed_tabs_become_spaces() {
+		let mut line = Line::new("\t\tfoo");
+		line.advance(1, true);
+		assert_eq!("   \tfoo", line.rest());
+		line.advance(5, true);
+		assert_eq!("  foo", line.rest());
+		line.skip_indent();
+		assert_eq!("foo", line.rest());
+	}
+
+	#[test]
+	fn blank_lines() {
+		assert!(Line::new(" \t ").blank);
+		assert!(!Line::new(" \tfoo").blank);
+	}
+}
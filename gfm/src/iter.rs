@@ -28,7 +28,7 @@ impl<'a> MarkdownIter<'a> {
 		}
 	}
 
-	fn update_index_to(&mut self, i: usize) {
+	pub fn update_index_to(&mut self, i: usize) {
 		self.index = i;
 	}
 
@@ -59,21 +59,6 @@ impl<'a> MarkdownIter<'a> {
 		self.the_str.get(start_index..self.index)
 	}
 
-	pub fn consume_until_tail_is(&mut self, tail: &str) -> Option<&'a str> {
-		let start_index = self.index;
-		while self.peek().is_some() &&
-			!self
-				.the_str
-				.get(start_index..self.index)
-				.unwrap_or(tail)
-				.ends_with(tail)
-		{
-			//unwrap_or(tail) to ensure exit in unforseen situation
-			self.next();
-		}
-		self.the_str.get(start_index..self.index)
-	}
-
 	pub fn consume_until_end(&mut self) -> Option<&'a str> {
 		let start_index = self.index;
 		while self.peek().is_some() {
@@ -82,6 +67,16 @@ impl<'a> MarkdownIter<'a> {
 		self.the_str.get(start_index..self.index)
 	}
 
+	/// Returns the character right before the current position.
+	pub fn peek_behind(&self) -> Option<char> {
+		self.the_str.get(..self.index)?.chars().next_back()
+	}
+
+	/// Returns the whole source, regardless of the current position.
+	pub fn source(&self) -> &'a str {
+		self.the_str
+	}
+
 	pub fn peek_until_end(&self) -> Option<&'a str> {
 		self.the_str.get(self.index..=(self.the_str.len() - 1))
 	}
@@ -93,57 +88,6 @@ impl<'a> MarkdownIter<'a> {
 	pub fn get_substring_from(&self, start: usize) -> Option<&'a str> {
 		self.the_str.get(start..self.index)
 	}
-
-	pub fn get_substring_ahead(&self, end: usize) -> Option<&'a str> {
-		self.the_str.get(self.index..(self.index + end))
-	}
-
-	pub fn find_next(&self, pattern: &str) -> Option<usize> {
-		self.the_str[self.index..].find(pattern)
-	}
-
-	pub fn peek_line_ahead(&self) -> Option<&'a str> {
-		match self.find_next("\n") {
-			Some(newline_index) => {
-				self.the_str.get(self.index..=(self.index + newline_index))
-			}
-			None if self.peek().is_some() => {
-				self.the_str.get(self.index..=(self.the_str.len() - 1))
-			}
-			_ => None,
-		}
-	}
-
-	pub fn consume_line_ahead(&mut self) -> Option<&'a str> {
-		match self.find_next("\n") {
-			Some(newline_index) => {
-				let ret =
-					self.the_str.get(self.index..=(self.index + newline_index));
-				self.update_index_to(self.index + newline_index + 1);
-				ret
-			}
-			None if self.peek().is_some() => {
-				let ret =
-					self.the_str.get(self.index..=(self.the_str.len() - 1));
-				self.update_index_to(self.the_str.len());
-				ret
-			}
-			_ => None,
-		}
-	}
-
-	/// Clone of [Iterator::skip_while]
-	pub fn skip_while_true<F>(&mut self, predicate: F)
-	where
-		F: Fn(&str) -> bool,
-	{
-		while let Some(c) = self.peek() {
-			if !predicate(c) {
-				break;
-			}
-			self.next();
-		}
-	}
 }
 
 #[cfg(test)]
@@ -173,7 +117,7 @@ mod tests {
 		let some_text = "الْﺦﷺأَ"; // لْ is a weird character. 2 bytes are valid for the base and two more
 						   // add the little circle on top.
 		let mut some_text_iter = MarkdownIter::new(some_text);
-		assert_eq!(Some("الْﺦﷺأَ"), some_text_iter.consume_line_ahead());
+		assert_eq!(Some("الْﺦﷺأَ"), some_text_iter.consume_until_end());
 		assert_eq!(None, some_text_iter.next());
 	}
 
@@ -237,7 +181,7 @@ mod tests {
 		);
 		assert_eq!(
 			Some(" is some plain"),
-			some_text_iter.consume_until_tail_is("plain")
+			some_text_iter.consume_while_case_holds(&|c| c != "t")
 		);
 		assert_eq!(Some("text"), some_text_iter.consume_until_end());
 		assert_eq!(None, some_text_iter.next());
@@ -245,64 +189,14 @@ mod tests {
 		let other_text = "jkfsgbkfgbdklfdsbh gkhsdfbg <details> and more chars";
 		let mut other_text_iter = MarkdownIter::new(other_text);
 		assert_eq!(
-			Some("jkfsgbkfgbdklfdsbh gkhsdfbg <details>"),
-			other_text_iter.consume_until_tail_is("<details>")
+			Some("jkfsgbkfgbdklfdsbh gkhsdfbg <details"),
+			other_text_iter.consume_while_case_holds(&|c| c != ">")
 		);
+		assert_eq!(Some(">"), other_text_iter.next_if_eq(">"));
 		assert_eq!(
 			Some(" and more chars"),
 			other_text_iter.consume_until_end()
 		);
 		assert_eq!(None, other_text_iter.peek());
 	}
-
-	#[test]
-	fn consume_peek_line_test() {
-		let some_text = "this is some plaintext in a line\nAnd a new line \
-		                 with more content";
-		let mut some_text_iter = MarkdownIter::new(some_text);
-		assert_eq!(
-			Some("this is some plaintext in a line\n"),
-			some_text_iter.peek_line_ahead()
-		);
-		assert_eq!(
-			Some("this is some plaintext in a line\n"),
-			some_text_iter.consume_line_ahead()
-		);
-		assert_ne!(
-			Some("this is some plaintext in a line\n"),
-			some_text_iter.peek_line_ahead()
-		);
-		assert_eq!(
-			Some("And a new line with more content"),
-			some_text_iter.peek_line_ahead()
-		);
-		assert_eq!(
-			Some("And a new line with more content"),
-			some_text_iter.consume_line_ahead()
-		);
-		assert_eq!(None, some_text_iter.peek_line_ahead());
-	}
-
-	#[test]
-	fn test_degenerate_newlines() {
-		let some_text = "\n\n\n\n\nfoo\n";
-		let mut some_text_iter = MarkdownIter::new(some_text);
-		assert_eq!(Some("\n"), some_text_iter.peek_line_ahead());
-		assert_eq!(Some("\n"), some_text_iter.consume_line_ahead());
-		assert_eq!(Some("\n"), some_text_iter.consume_line_ahead());
-		assert_eq!(Some("\n"), some_text_iter.consume_line_ahead());
-		assert_eq!(Some("\n"), some_text_iter.consume_line_ahead());
-		assert_eq!(Some("\n"), some_text_iter.consume_line_ahead());
-		assert_eq!(Some("foo\n"), some_text_iter.consume_line_ahead());
-		assert_eq!(None, some_text_iter.consume_line_ahead());
-	}
-
-	#[test]
-	fn test_mixed_chars() {
-		let some_text = "  - foo\n\n\tbar\n";
-		let mut some_text_iter = MarkdownIter::new(some_text);
-		assert_eq!(Some("  - foo\n"), some_text_iter.consume_line_ahead());
-		assert_eq!(Some("\n"), some_text_iter.consume_line_ahead());
-		assert_eq!(Some("\tbar\n"), some_text_iter.consume_line_ahead());
-	}
 }
@@ -1,28 +1,64 @@
 use core::fmt;
-
-use log::{
-	debug,
-	warn,
+use std::{
+	borrow::Cow,
+	collections::HashMap,
+	ops::Range,
+	sync::Arc,
 };
 
+use log::debug;
+
 use crate::{
+	autolink::{
+		self,
+		AutolinkKind,
+	},
+	diagnostic::{
+		Diagnostic,
+		DiagnosticKind,
+	},
+	html::{
+		self,
+		Scan,
+	},
+	image,
 	token::{
 		validate_link,
-		Alignment,
+		DelimiterRun,
+		Scheme,
+		ValidURL,
 	},
+	Image,
+	InlineSyntax,
+	ParseErrorAction,
 	Parser,
 };
 
 use super::{
 	iter::MarkdownIter,
-	token::{
-		TaskBox,
-		Token,
-	},
+	token::Token,
 };
 
+/// Lexes the inline content of a leaf block, eg. a paragraph or table cell.
 pub struct Lexer<'a> {
-	iter: MarkdownIter<'a>,
+	iter:              MarkdownIter<'a>,
+	/// How many more levels links may be nested in this one
+	max_nesting:       usize,
+	/// What to do with markup that cannot be lexed
+	on_error:          ParseErrorAction,
+	/// Start of the last run of backticks of each length seen while looking
+	/// for the end of a code span
+	backtick_runs:     HashMap<usize, usize>,
+	/// Whether `backtick_runs` covers the source up to its end
+	backticks_scanned: bool,
+	/// Markup that was kept as text, with spans relative to the input
+	diagnostics:       Vec<Diagnostic>,
+	/// Syntax extensions, looked for before the markup of GFM
+	syntaxes:          &'a [Arc<dyn InlineSyntax>],
+	/// Whether urls and email addresses outside of `<>` are links
+	autolinks:         bool,
+	/// Ends of raw html known to be missing from the source
+	scan:              Scan,
 }
 
 #[derive(Debug)]
@@ -37,162 +73,151 @@ impl fmt::Display for ParseError<'_> {
 }
 
 impl<'a> Lexer<'a> {
-	/// Initializes a new Lexer with the given input.
-	pub fn new(input: &'a str) -> Self {
+	/// Initializes a new Lexer with the given input, in which links are
+	/// nested at most `max_nesting` levels deep.
+	pub fn new(
+		input: &'a str,
+		max_nesting: usize,
+		on_error: ParseErrorAction,
+	) -> Self {
 		debug!("Initializing Lexer with input of length: {}", input.len());
 		Lexer {
 			iter: MarkdownIter::new(input),
+			max_nesting,
+			on_error,
+			backtick_runs: HashMap::new(),
+			backticks_scanned: false,
+			diagnostics: Vec::new(),
+			syntaxes: &[],
+			autolinks: false,
+			scan: Scan::default(),
 		}
 	}
 
-	/// Produces the next token or returns None when done.
-	pub fn next_token(
+	/// Looks for the given syntax extensions in addition to GFM.
+	pub(crate) fn with_syntaxes(
+		mut self,
+		syntaxes: &'a [Arc<dyn InlineSyntax>],
+	) -> Self {
+		self.syntaxes = syntaxes;
+		self
+	}
+
+	/// Lexes urls and email addresses outside of `<>` as links.
+	pub(crate) fn with_autolinks(mut self, autolinks: bool) -> Self {
+		self.autolinks = autolinks;
+		self
+	}
+
+	/// Returns the byte offset in the input of the next token.
+	pub(crate) fn offset(&self) -> usize {
+		self.iter.get_index()
+	}
+
+	/// Returns what went wrong while lexing, with spans relative to the
+	/// input.
+	pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+		self.diagnostics
+	}
+
+	fn warn(
 		&mut self,
-		ignore: &[char],
-		tokens: &Vec<Token>,
-	) -> Option<Token<'a>> {
-		while let Some(ch) = self.iter.peek() {
+		kind: DiagnosticKind,
+		span: Range<usize>,
+		message: String,
+	) {
+		debug!("{:?} at {:?}: {}", kind, span, message);
+		self.diagnostics
+			.push(Diagnostic::warning(kind, span, message));
+	}
+
+	/// Lexes the text of a link, which starts at `offset` in the input.
+	fn lex_link_text(
+		&mut self,
+		text: &'a str,
+		offset: usize,
+	) -> Vec<Token<'a>> {
+		let mut diagnostics = Vec::new();
+		let tokens = Parser::lex_inline(
+			text,
+			self.max_nesting - 1,
+			self.on_error,
+			self.syntaxes,
+			self.autolinks,
+			&mut diagnostics,
+		);
+		self.diagnostics
+			.extend(diagnostics.into_iter().map(|mut d| {
+				d.span = d.span.start + offset..d.span.end + offset;
+				d
+			}));
+		tokens
+	}
+
+	/// Produces the next token or returns None when done.
+	pub fn next_token(&mut self) -> Option<Token<'a>> {
+		let start_index = self.iter.get_index();
+		let reported = self.diagnostics.len();
+		if let Some(ch) = self.iter.peek() {
 			debug!("Processing character: {:?}", self.iter.peek());
+			if let Some(custom) = self.lex_custom() {
+				return Some(custom);
+			}
+			if let Some(link) = self.lex_extended_autolink() {
+				return Some(link);
+			}
 			match ch {
-				"#" if !ignore.contains(&'#') => {
-					return match self.lex_heading() {
-						Ok(t) => Some(t),
-						Err(e) => {
-							warn!("Error while lexing heading: {}", e);
-							Some(Token::Plaintext(e.content.to_string()))
-						}
-					};
-				}
-				"\n" => {
-					return match self.lex_newlines() {
-						Ok(t) => Some(t),
-						Err(e) => {
-							warn!("Error while lexing newline: {}", e);
-							Some(Token::Plaintext(e.content.to_string()))
-						}
-					}
-				}
-				" " | "\t" => {
-					return match self.lex_tabs_spaces(tokens) {
-						Ok(t) => Some(t),
-						Err(e) => {
-							warn!("Error while lexing whitespaces: {}", e);
-							Some(Token::Plaintext(e.content.to_string()))
-						}
-					}
-				}
-				"1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" | "0" => {
-					return match self.lex_numbers() {
-						Ok(t) => Some(t),
-						Err(e) => {
-							warn!("Error while lexing number: {}", e);
-							Some(Token::Plaintext(e.content.to_string()))
-						}
-					}
+				"\n" => return Some(self.lex_soft_break()),
+				" " => {
+					return Some(
+						self.lex_hard_break()
+							.unwrap_or_else(|| self.lex_text()),
+					)
 				}
 				"*" | "_" => {
-					return match self.lex_asterisk_underscore() {
-						Ok(t) => Some(t),
-						Err(e) => {
-							warn!(
-								"Error while lexing asterisks and \
-								 underscores: {}",
-								e
-							);
-							Some(Token::Plaintext(e.content.to_string()))
-						}
-					}
-				}
-				"-" | "+" => {
-					return match self.lex_plus_minus() {
+					return match self.lex_delimiter_run() {
 						Ok(t) => Some(t),
-						Err(e) => {
-							warn!("Error while lexing plus or minus: {}", e);
-							Some(Token::Plaintext(e.content.to_string()))
-						}
-					}
-				}
-				">" => {
-					return match self.lex_blockquotes() {
-						Ok(t) => Some(t),
-						Err(e) => {
-							warn!("Error while lexing block quotes: {}", e);
-							Some(Token::Plaintext(e.content.to_string()))
-						}
+						Err(e) => Some(self.recover(e, start_index, reported)),
 					}
 				}
 				"~" => {
 					return match self.lex_tilde() {
 						Ok(t) => Some(t),
-						Err(e) => {
-							warn!("Error while tilde: {}", e);
-							Some(Token::Plaintext(e.content.to_string()))
-						}
+						Err(e) => Some(self.recover(e, start_index, reported)),
 					}
 				}
 				"`" => {
 					return match self.lex_backticks() {
 						Ok(t) => Some(t),
-						Err(e) => {
-							warn!("Error while lexing backticks: {}", e);
-							Some(Token::Plaintext(e.content.to_string()))
-						}
+						Err(e) => Some(self.recover(e, start_index, reported)),
 					}
 				}
 				"[" => {
 					return match self.lex_links() {
 						Ok(t) => Some(t),
-						Err(e) => {
-							warn!("Error while lexing link: {}", e);
-							Some(Token::Plaintext(e.content.to_string()))
-						}
+						Err(e) => Some(self.recover(e, start_index, reported)),
 					}
 				}
 				"!" => {
 					return match self.lex_images() {
 						Ok(t) => Some(t),
-						Err(e) => {
-							warn!("Error while lexing image: {}", e);
-							Some(Token::Plaintext(e.content.to_string()))
-						}
+						Err(e) => Some(self.recover(e, start_index, reported)),
 					}
 				}
 				"<" => {
 					return match self.lex_side_carrot() {
 						Ok(t) => Some(t),
-						Err(e) => {
-							warn!("Error while lexing side carrot: {}", e);
-							Some(Token::Plaintext(e.content.to_string()))
-						}
-					}
-				}
-				"|" => {
-					return match self.lex_pipes() {
-						Ok(t) => Some(t),
-						Err(e) => {
-							warn!("Error while lexing pipe: {}", e);
-							Some(Token::Plaintext(e.content.to_string()))
-						}
+						Err(e) => Some(self.recover(e, start_index, reported)),
 					}
 				}
 				// Parse "\" to escape a markdown control character
 				"\\" => {
 					return match self.lex_escaped_character() {
 						Ok(t) => Some(t),
-						Err(e) => {
-							warn!(
-								"Error while lexing escaped character: {}",
-								e
-							);
-							Some(Token::Plaintext(e.content.to_string()))
-						}
-					}
-				}
-				_ => {
-					if let Some(c) = self.iter.next() {
-						return Some(Token::Plaintext(c.to_string()));
+						Err(e) => Some(self.recover(e, start_index, reported)),
 					}
 				}
+				_ => return Some(self.lex_text()),
 			}
 		}
 
@@ -200,361 +225,154 @@ impl<'a> Lexer<'a> {
 		None
 	}
 
-	fn lex_heading(&mut self) -> Result<Token<'a>, ParseError<'a>> {
-		let hashes = self
-			.iter
-			.consume_while_case_holds(&|c| c == "#")
-			.unwrap_or("");
-		if hashes.len() > 6 {
-			return Err(ParseError { content: hashes });
-		}
-		if self.iter.next_if_eq(" ").is_none() &&
-			self.iter.next_if_eq("\t").is_none() &&
-			self.iter.peek() != Some("\n")
+	/// Turns markup that failed to lex into text. If the failure was
+	/// reported and such markup is skipped, everything from `start_index` to
+	/// the end of the reported span is dropped instead.
+	fn recover(
+		&mut self,
+		e: ParseError<'a>,
+		start_index: usize,
+		reported: usize,
+	) -> Token<'a> {
+		match self.diagnostics[reported..]
+			.iter()
+			.map(|d| d.span.end)
+			.max()
 		{
-			return Err(ParseError { content: hashes });
-		}
-		let mut line = self
-			.iter
-			.consume_while_case_holds(&|c| c != "\n")
-			.unwrap_or("");
-		if line.contains("{#") && line.contains('}') {
-			let (heading, _title) = line.split_once("{").unwrap_or(("", ""));
-			line = line
-				.strip_prefix(heading)
-				.unwrap()
-				.strip_prefix("{#")
-				.unwrap()
-				.strip_suffix("}")
-				.unwrap();
-		}
-		let line_without_optional_trailing_hash_sequence =
-			match line.trim_end().rsplit_once(' ') {
-				Some((left, right)) => match right.chars().all(|c| c == '#') {
-					true => left,
-					false => line,
-				},
-				None => line,
-			};
-		if line.chars().all(|c| c == '#') {
-			return Ok(Token::Header(hashes.len(), "".to_string(), None));
-		}
-		let parsed_line = Parser::render_ignore(
-			line_without_optional_trailing_hash_sequence
-				.trim_end_matches([' ', '\t']),
-			&['#'],
-		)
-		.strip_prefix("<p>")
-		.unwrap_or("")
-		.strip_suffix("</p>\n")
-		.unwrap_or("")
-		.trim()
-		.to_string();
-
-		Ok(Token::Header(hashes.len(), parsed_line, None))
-	}
-
-	fn lex_newlines(&mut self) -> Result<Token<'a>, ParseError<'a>> {
-		match self.iter.consume_while_case_holds(&|c| c == "\n") {
-			Some(s) if s.len() >= 2 => Ok(Token::Newline),
-			Some(s) if s.len() < 2 => Err(ParseError { content: s }),
-			_ => Err(ParseError { content: "" }),
+			Some(end) if self.on_error == ParseErrorAction::Skip => {
+				debug!("Skipping markup from {} to {}", start_index, end);
+				self.iter.update_index_to(end);
+				Token::Plaintext(Cow::Borrowed(""))
+			}
+			_ => Token::Plaintext(e.content.into()),
 		}
 	}
 
-	fn lex_tabs_spaces(
-		&mut self,
-		tokens: &Vec<Token>,
-	) -> Result<Token<'a>, ParseError<'a>> {
+	/// Lexes a run of text without any markup as a single token borrowed from
+	/// the source. The run ends in front of the next character that may start
+	/// a different token, which includes spaces that may form a hard line
+	/// break and the start of extended autolinks, if they are enabled.
+	fn lex_text(&mut self) -> Token<'a> {
 		let start_index = self.iter.get_index();
-		let whitespace = self
-			.iter
-			.consume_while_case_holds(&|c| c == "\t" || c == " ");
-
-		match whitespace {
-			None => return Err(ParseError { content: "" }),
-			Some(s) if (1..=3).contains(&s.len()) && !s.contains("\t") => {
-				return Err(ParseError { content: s })
-			}
-			Some(s)
-				if s.len() >= 2 &&
-					!s.contains("\t") &&
-					self.iter.peek() == Some("\n") =>
-			{
-				return Ok(Token::LineBreak)
-			}
-			Some(_s) => {}
-		}
-
-		let whitespace = whitespace.unwrap_or("");
-		let line = self.iter.consume_until_tail_is("\n").unwrap_or("");
-
-		match whitespace {
-			"    "
-				if (matches!(tokens.last(), Some(Token::Plaintext(_))) &&
-					line.contains('#')) =>
-			{
-				return Err(ParseError { content: line })
-			}
-			"    "
-				if (matches!(tokens.last(), Some(Token::Newline)) &&
-					line.contains('#')) =>
-			{
-				return Err(ParseError { content: line })
-			}
-			"\t" if matches!(tokens.last(), Some(Token::Code(_))) => {
-				return Ok(Token::Code(line.to_string()))
-			}
-			"\t" | "    " | "  \t" => return Ok(Token::Code(line.to_string())),
-			"\t\t" => return Ok(Token::Code("\t".to_owned() + line)),
-			_ => {}
-		}
-
-		if self.iter.peek() == Some("\t") || self.iter.peek() == Some(" ") {
-			match self.lex_tabs_spaces(tokens) {
-				Ok(Token::CodeBlock(_content, _lang)) => {
-					return Ok(Token::CodeBlock(
-						self.iter
-							.get_substring_from(start_index)
-							.unwrap_or("")
-							.to_string(),
-						"".to_string(),
-					))
+		self.iter.next();
+		while let Some(c) = self.iter.peek() {
+			let rest = self.iter.peek_until_end().unwrap_or("");
+			match c {
+				"\n" | "*" | "_" | "~" | "`" | "[" | "!" | "<" | "\\" => break,
+				_ if self
+					.syntaxes
+					.iter()
+					.any(|s| c.starts_with(s.trigger())) =>
+				{
+					break
 				}
-				Err(e) => return Err(e),
-				Ok(_) => return Err(ParseError { content: "" }),
+				" " => {
+					let spaces =
+						rest.len() - rest.trim_start_matches(' ').len();
+					if rest[spaces..].starts_with('\n') {
+						break;
+					}
+					for _ in 0..spaces {
+						self.iter.next();
+					}
+					continue;
+				}
+				_ if self.autolinks &&
+					autolink::match_autolink(
+						rest,
+						self.iter.peek_behind(),
+					)
+					.is_some() =>
+				{
+					break
+				}
+				_ => {}
 			}
+			self.iter.next();
 		}
-		Err(ParseError {
-			content: self.iter.get_substring_from(start_index).unwrap_or(""),
-		})
+		Token::Plaintext(Cow::Borrowed(
+			self.iter.get_substring_from(start_index).unwrap_or(""),
+		))
 	}
 
-	fn lex_numbers(&mut self) -> Result<Token<'a>, ParseError<'a>> {
-		let start_index = self.iter.get_index();
-		let c = self.iter.next().unwrap();
-		match self.iter.next_if_eq(".") {
-			Some(".") => {
-				if self.iter.next_if_eq(" ") != Some(" ") {
-					return Err(ParseError {
-						content: self
-							.iter
-							.get_substring_from(start_index)
-							.unwrap_or(""),
-					});
-				}
+	/// Lexes a line ending inside a paragraph. The indentation of the
+	/// following line is not part of the paragraph content.
+	///
+	/// See <https://spec.commonmark.org/0.29/#soft-line-breaks>.
+	fn lex_soft_break(&mut self) -> Token<'a> {
+		self.iter.next_if_eq("\n");
+		self.iter
+			.consume_while_case_holds(&|c| c == " " || c == "\t");
+		Token::SoftBreak
+	}
 
-				let s = self
-					.iter
-					.consume_while_case_holds(&|c| c != "\n")
-					.unwrap_or("");
-				Ok(Token::OrderedListEntry(s.to_string()))
-			}
-			_ => Err(ParseError { content: c }),
+	/// Lexes two or more spaces in front of a line ending as a hard line
+	/// break. Returns `None` without consuming anything if there is no line
+	/// ending or the paragraph ends with it.
+	///
+	/// See <https://spec.commonmark.org/0.29/#hard-line-breaks>.
+	fn lex_hard_break(&mut self) -> Option<Token<'a>> {
+		let rest = self.iter.peek_until_end()?;
+		let spaces = rest.len() - rest.trim_start_matches(' ').len();
+		if spaces < 2 || !continues_paragraph(&rest[spaces..]) {
+			return None;
+		}
+		for _ in 0..spaces {
+			self.iter.next();
 		}
+		self.lex_soft_break();
+		Some(Token::LineBreak)
 	}
 
 	fn lex_escaped_character(&mut self) -> Result<Token<'a>, ParseError<'a>> {
 		self.iter.next();
-		if self.iter.peek() == Some("#") {
+		if self.iter.peek() == Some("\n") {
+			// A backslash at the end of a line is a hard line break, unless
+			// it ends the paragraph
+			let rest = self.iter.peek_until_end().unwrap_or("");
+			if !continues_paragraph(rest) {
+				return Err(ParseError { content: "\\" });
+			}
+			self.lex_soft_break();
+			return Ok(Token::LineBreak);
+		} else if self.iter.peek() == Some("#") {
 			let hashes = self
 				.iter
 				.consume_while_case_holds(&|c| c == "#")
 				.unwrap_or("");
-			return Ok(Token::Plaintext(hashes.to_string()));
-		} else if self.iter.peek().is_some() {
-			return Ok(Token::Plaintext(
-				self.iter.next().unwrap_or_default().to_string(),
-			));
-		}
-
-		Err(ParseError { content: "EOF" })
-	}
-
-	fn lex_asterisk_underscore(&mut self) -> Result<Token<'a>, ParseError<'a>> {
-		let start_index = self.iter.get_index();
-		let asterunds = self
+			return Ok(Token::Plaintext(hashes.into()));
+		} else if self
 			.iter
-			.consume_while_case_holds(&|c| c == "*" || c == "_" || c == "\t")
-			.unwrap_or("");
-		if asterunds.len() == 1 && self.iter.next_if_eq(" ").is_some() {
-			let s = self
-				.iter
-				.consume_while_case_holds(&|c| c != "\n")
-				.unwrap_or("");
-			self.iter.next();
-			return Ok(Token::UnorderedListEntry(vec![Token::Plaintext(
-				s.to_string(),
-			)]));
-		}
-		if asterunds.chars().all(|x| x == '*') && self.iter.peek() == Some("\n")
+			.peek()
+			.is_some_and(|c| c.starts_with(|c: char| c.is_ascii_punctuation()))
 		{
-			return Ok(Token::HorizontalRule);
-		}
-		match asterunds.len() {
-			1 => {
-				let s = self
-					.iter
-					.consume_while_case_holds(&|c| c != "*" && c != "_")
-					.unwrap_or("");
-				if self.iter.peek() != Some("*") ||
-					self.iter.peek() != Some("_")
-				{
-					self.iter.next();
-					Ok(Token::Italic(s.to_string()))
-				} else {
-					Err(ParseError {
-						content: self
-							.iter
-							.get_substring_from(start_index)
-							.unwrap_or(""),
-					})
-				}
-			}
-			2 => {
-				let s = self
-					.iter
-					.consume_while_case_holds(&|c| c != "*" && c != "_")
-					.unwrap_or("");
-				let trailing_astunds = self
-					.iter
-					.consume_while_case_holds(&|c| c == "*" || c == "_")
-					.unwrap_or("");
-				if trailing_astunds.len() == 2 {
-					Ok(Token::Bold(s.to_string()))
-				} else {
-					Err(ParseError {
-						content: self
-							.iter
-							.get_substring_from(start_index)
-							.unwrap_or(""),
-					})
-				}
-			}
-			3 => {
-				let s = self
-					.iter
-					.consume_while_case_holds(&|c| c != "*" && c != "_")
-					.unwrap_or("");
-				let trailing_astunds = self
-					.iter
-					.consume_while_case_holds(&|c| c == "*" || c == "_")
-					.unwrap_or("");
-				if trailing_astunds.len() == 3 {
-					Ok(Token::BoldItalic(s.to_string()))
-				} else {
-					Err(ParseError {
-						content: self
-							.iter
-							.get_substring_from(start_index)
-							.unwrap_or(""),
-					})
-				}
-			}
-			_ => {
-				if asterunds.replace("\t", "").chars().all(|x| x == '*') ||
-					asterunds.chars().all(|x| x == '_')
-				{
-					Ok(Token::HorizontalRule)
-				} else {
-					Err(ParseError { content: asterunds })
-				}
-			}
+			return Ok(Token::Plaintext(
+				self.iter.next().unwrap_or_default().into(),
+			));
 		}
-	}
 
-	pub fn lex_blockquotes(&mut self) -> Result<Token<'a>, ParseError<'a>> {
-		let right_arrows = self
-			.iter
-			.consume_while_case_holds(&|c| c == ">")
-			.unwrap_or("");
-		match self.iter.peek() {
-			Some(" ") | Some("\t") => {}
-			_ => {
-				return Err(ParseError {
-					content: right_arrows,
-				})
-			}
-		}
-		let s = self
-			.iter
-			.consume_while_case_holds(&|c| c != "\n")
-			.unwrap_or("");
-		self.iter.next_if_eq("\n");
-		Ok(Token::BlockQuote(right_arrows.len() as u8, s.to_string()))
+		// Only ASCII punctuation can be escaped, so the backslash is kept
+		// along with whatever follows it
+		Err(ParseError { content: "\\" })
 	}
 
-	pub(crate) fn lex_plus_minus(
-		&mut self,
-	) -> Result<Token<'a>, ParseError<'a>> {
-		let start_index = self.iter.get_index();
-		let s = self
+	/// Lexes a run of `*` or `_` that may open or close emphasis, depending
+	/// on the characters around it.
+	fn lex_delimiter_run(&mut self) -> Result<Token<'a>, ParseError<'a>> {
+		let prev = self.iter.peek_behind();
+		let ch = self.iter.peek().ok_or(ParseError { content: "EOF" })?;
+		let run = self
 			.iter
-			.consume_while_case_holds(&|c| c == "-" || c == "+")
+			.consume_while_case_holds(&|c| c == ch)
 			.unwrap_or("");
-		match s.len() {
-			3..=usize::MAX => return Ok(Token::HorizontalRule),
-			2 => {
-				return Err(ParseError {
-					content: self
-						.iter
-						.get_substring_from(start_index)
-						.unwrap_or(""),
-				})
-			}
-			1 => {}
-			_ => {
-				return Err(ParseError {
-					content: "string length error",
-				})
-			}
-		}
-		let line_index = self.iter.get_index();
-		let mut list_element_tokens = vec![Token::Plaintext(
-			self.iter
-				.consume_while_case_holds(&|c| c != "\n")
-				.unwrap_or("")
-				.to_string(),
-		)];
-		while self.iter.get_substring_ahead(3) == Some("\n\n\t") {
-			self.iter.next();
-			self.iter.next();
-			self.iter.next();
-			list_element_tokens.push(Token::Plaintext(
-				self.iter
-					.consume_while_case_holds(&|c| c != "\n")
-					.unwrap_or("")
-					.to_string(),
-			));
-		}
-		let line = self.iter.get_substring_from(line_index).unwrap_or("");
-		self.iter.next_if_eq("\n");
-		if line.starts_with(" [ ] ") {
-			Ok(Token::TaskListItem(
-				TaskBox::Unchecked,
-				line[5..].to_string(),
-			))
-		} else if line.starts_with(" [x] ") || line.starts_with(" [X] ") {
-			return Ok(Token::TaskListItem(
-				TaskBox::Checked,
-				line[5..].to_string(),
-			));
-		} else {
-			// List entries may contain other lists
-			match self.iter.peek_line_ahead() {
-				Some(s) if s.starts_with("  ") => {
-					let line = self.iter.consume_line_ahead().unwrap_or("");
-					list_element_tokens.append(&mut Parser::lex(line, &[]))
-				}
-				Some(s) if s.starts_with("\t") => {
-					let line = self.iter.consume_line_ahead().unwrap_or("");
-					list_element_tokens
-						.append(&mut Parser::lex(&line[1..], &[]))
-				}
-				_ => {}
-			}
-			return Ok(Token::UnorderedListEntry(list_element_tokens));
-		}
+		let next = self.iter.peek().and_then(|c| c.chars().next());
+
+		Ok(Token::Delimiter(DelimiterRun::new(
+			ch.chars().next().unwrap_or('*'),
+			run.len(),
+			prev,
+			next,
+		)))
 	}
 
 	fn lex_tilde(&mut self) -> Result<Token<'a>, ParseError<'a>> {
@@ -584,6 +402,11 @@ impl<'a> Lexer<'a> {
 					.consume_while_case_holds(&|s| s == "~")
 					.unwrap_or("");
 				if lead_tildes.len() != tail_tildes.len() {
+					self.warn(
+						DiagnosticKind::UnclosedStrikethrough,
+						start_index..start_index + 2,
+						"strikethrough is not closed by `~~`".to_string(),
+					);
 					return Err(ParseError {
 						content: self
 							.iter
@@ -591,7 +414,7 @@ impl<'a> Lexer<'a> {
 							.unwrap_or(""),
 					});
 				}
-				return Ok(Token::Strikethrough(line.to_string()));
+				Ok(Token::Strikethrough(line))
 			}
 			_ => {
 				return Err(ParseError {
@@ -602,64 +425,77 @@ impl<'a> Lexer<'a> {
 	}
 
 	fn lex_backticks(&mut self) -> Result<Token<'a>, ParseError<'a>> {
-		let start_index = self.iter.get_index();
 		let leading_ticks = self
 			.iter
 			.consume_while_case_holds(&|c| c == "`")
 			.unwrap_or("");
-		let mut lang = "";
-		if leading_ticks.len() == 3 {
-			if self.iter.next_if_eq("\n") != Some(&"\n") {
-				lang = self
-					.iter
-					.consume_while_case_holds(&|c| c != "\n")
-					.unwrap_or("");
-				self.iter.next();
-			}
-			let s = self
-				.iter
-				.consume_while_case_holds(&|c| c != "`")
-				.unwrap_or("");
-			let trailing_ticks = self
-				.iter
-				.consume_while_case_holds(&|c| c == "`")
-				.unwrap_or("");
-			if leading_ticks.len() != trailing_ticks.len() {
-				return Err(ParseError {
-					content: self
-						.iter
-						.get_substring_from(start_index)
-						.unwrap_or(""),
-				});
-			} else {
-				return Ok(Token::CodeBlock(s.to_string(), lang.to_string()));
-			}
-		}
-
-		// let s = self.iter.consume_while_case_holds(&|c| c != "`" && c!=
-		// "\n").unwrap_or("");
-		let tail = &(0..leading_ticks.len() as u64)
-			.map(|_| "`")
-			.collect::<String>();
-		let s = self.iter.consume_until_tail_is(tail).unwrap_or("");
-		if !s.ends_with(tail) {
+		let content_start = self.iter.get_index();
+		let Some((content_end, end)) =
+			self.find_closing_backticks(leading_ticks.len())
+		else {
+			// The backticks are literal, the text after them is lexed as usual
+			self.warn(
+				DiagnosticKind::UnclosedCodeSpan,
+				content_start - leading_ticks.len()..content_start,
+				format!(
+					"code span opened by {} backtick(s) is never closed",
+					leading_ticks.len()
+				),
+			);
 			return Err(ParseError {
-				content: self
-					.iter
-					.get_substring_from(start_index)
-					.unwrap_or(""),
+				content: leading_ticks,
 			});
-		} else {
-			let s = s.trim_end_matches(tail);
-			if s.starts_with(' ') && s.ends_with(' ') {
-				return Ok(Token::Code(
-					s.trim_start_matches(' ').trim_end_matches(' ').to_string(),
-				));
-			}
-			return Ok(Token::Code(s.to_string()));
+		};
+		self.iter.update_index_to(content_end);
+		let content = self.iter.get_substring_from(content_start).unwrap_or("");
+		self.iter.update_index_to(end);
+
+		// Line endings inside code spans are treated like spaces
+		let mut s = content;
+		if s.starts_with([' ', '\n']) && s.ends_with([' ', '\n']) {
+			s = s.trim_matches([' ', '\n']);
+		}
+		if s.contains('\n') {
+			return Ok(Token::Code(Cow::Owned(s.replace('\n', " "))));
 		}
+		Ok(Token::Code(Cow::Borrowed(s)))
+	}
 
-		// leading_ticks.len() == 3. Check for lang
+	/// Finds the next run of exactly `length` backticks and returns where it
+	/// starts and ends.
+	///
+	/// Every run passed on the way is remembered, so once the source was
+	/// scanned up to its end, a code span without an end is known to have
+	/// none without scanning again.
+	fn find_closing_backticks(
+		&mut self,
+		length: usize,
+	) -> Option<(usize, usize)> {
+		let offset = self.iter.get_index();
+		if self.backticks_scanned &&
+			self.backtick_runs.get(&length).is_none_or(|&s| s < offset)
+		{
+			return None;
+		}
+		let bytes = self.iter.peek_until_end().unwrap_or("").as_bytes();
+		let mut i = 0;
+		while i < bytes.len() {
+			if bytes[i] != b'`' {
+				i += 1;
+				continue;
+			}
+			let run_start = i;
+			while bytes.get(i) == Some(&b'`') {
+				i += 1;
+			}
+			let last = self.backtick_runs.entry(i - run_start).or_default();
+			*last = (*last).max(offset + run_start);
+			if i - run_start == length {
+				return Some((offset + run_start, offset + i));
+			}
+		}
+		self.backticks_scanned = true;
+		None
 	}
 
 	pub(crate) fn lex_links(&mut self) -> Result<Token<'a>, ParseError<'a>> {
@@ -667,10 +503,17 @@ impl<'a> Lexer<'a> {
 		if self.iter.next_if_eq("[") != Some(&"[") {
 			return Err(ParseError { content: "" });
 		}
-		let title = self
-			.iter
-			.consume_while_case_holds(&|c| c != "]")
-			.unwrap_or("");
+		if self.max_nesting == 0 {
+			// Too deeply nested to look for the end of the link text
+			self.warn(
+				DiagnosticKind::NestingTooDeep,
+				start_index..start_index + 1,
+				"links are nested too deeply, the bracket is kept as text"
+					.to_string(),
+			);
+			return Err(ParseError { content: "[" });
+		}
+		let title = self.consume_link_text();
 		if self.iter.next_if_eq("]") != Some(&"]") {
 			return Err(ParseError {
 				content: self
@@ -679,58 +522,6 @@ impl<'a> Lexer<'a> {
 					.unwrap_or(""),
 			});
 		}
-		// Parse footnotes big and small
-		if title.starts_with("^") && self.iter.next_if_eq(":") == Some(&":") {
-			let ref_id = title.strip_prefix("^").unwrap_or("");
-			let note_index = self.iter.get_index();
-			loop {
-				self.iter.consume_while_case_holds(&|c| c != "\n");
-				self.iter.next();
-				if self.iter.peek() != Some(&" ") &&
-					self.iter.peek() != Some(&"\t")
-				{
-					break;
-				}
-				if self.iter.next_if_eq("\t") == Some(&"\t") {
-					continue;
-				}
-				if self.iter.peek() == Some(&" ") {
-					let spaces = self
-						.iter
-						.consume_while_case_holds(&|c| c == " ")
-						.unwrap_or("");
-					match spaces.len() {
-						2 | 4 => {}
-						_ => {
-							return Err(ParseError {
-								content: self
-									.iter
-									.get_substring_from(start_index)
-									.unwrap_or(""),
-							})
-						}
-					}
-					continue;
-				}
-				break;
-			}
-			if ref_id.contains(char::is_whitespace) {
-				return Err(ParseError {
-					content: self
-						.iter
-						.get_substring_from(start_index)
-						.unwrap_or(""),
-				});
-			}
-			return Ok(Token::Footnote(
-				ref_id.to_string(),
-				self.iter
-					.get_substring_from(note_index)
-					.unwrap_or("")
-					.trim()
-					.to_string(),
-			));
-		}
 		if self.iter.next_if_eq("(") != Some(&"(") {
 			return Err(ParseError {
 				content: self
@@ -744,6 +535,11 @@ impl<'a> Lexer<'a> {
 			.consume_while_case_holds(&|c| c != ")" && c != " ")
 			.unwrap_or("");
 		if self.iter.peek() != Some(&")") && self.iter.peek() != Some(&" ") {
+			self.warn(
+				DiagnosticKind::MalformedLink,
+				start_index..self.iter.get_index(),
+				"link destination is not closed by `)`".to_string(),
+			);
 			return Err(ParseError {
 				content: self
 					.iter
@@ -754,12 +550,19 @@ impl<'a> Lexer<'a> {
 		if self.iter.next_if_eq(")") == Some(&")") {
 			match validate_link(link) {
 				Ok(vl) => {
-					return Ok(Token::Link(vl, Some(title.to_string()), None))
+					return Ok(Token::Link(
+						vl,
+						Some(self.lex_link_text(title, start_index + 1)),
+						None,
+					))
 				}
 				Err(se) => {
-					return Err(ParseError {
-						content: &se.content,
-					})
+					return Ok(self.unsafe_link(
+						se.content,
+						title,
+						start_index,
+						None,
+					))
 				}
 			}
 		}
@@ -768,27 +571,73 @@ impl<'a> Lexer<'a> {
 				.iter
 				.consume_while_case_holds(&|c| c != ")")
 				.unwrap_or("");
-
-			self.iter.skip_while_true(|c| c != "\n");
 			self.iter.next();
+			let hover = link_title(hover);
 			match validate_link(link) {
 				Ok(vl) => {
 					return Ok(Token::Link(
 						vl,
-						Some(title.to_string()),
-						Some(hover.to_string()),
+						Some(self.lex_link_text(title, start_index + 1)),
+						Some(hover),
 					))
 				}
 				Err(se) => {
-					return Err(ParseError {
-						content: &se.content,
-					})
+					return Ok(self.unsafe_link(
+						se.content,
+						title,
+						start_index,
+						Some(hover),
+					))
 				}
 			}
 		}
 		Err(ParseError { content: "" })
 	}
 
+	/// Reports a link whose destination is not allowed, eg. `javascript:`.
+	/// The link keeps its destination, which the renderer refuses like those
+	/// of the [LinkPolicy](crate::LinkPolicy), so both are rendered as their
+	/// text.
+	fn unsafe_link(
+		&mut self,
+		link: &'a str,
+		text: &'a str,
+		start_index: usize,
+		hover: Option<&'a str>,
+	) -> Token<'a> {
+		self.warn(
+			DiagnosticKind::UnsafeLink,
+			start_index..self.iter.get_index(),
+			format!("link destination `{}` is not allowed", link),
+		);
+		let url = ValidURL {
+			content: link,
+			scheme:  None,
+		};
+		Token::Link(url, Some(self.lex_link_text(text, start_index + 1)), hover)
+	}
+
+	/// Consumes the text of a link up to its closing `]`, which is left in
+	/// the iterator. Brackets may be nested if they are balanced, so links can
+	/// contain images.
+	fn consume_link_text(&mut self) -> &'a str {
+		let start_index = self.iter.get_index();
+		let mut depth = 0;
+		while let Some(c) = self.iter.peek() {
+			match c {
+				"\\" => {
+					self.iter.next();
+				}
+				"[" => depth += 1,
+				"]" if depth == 0 => break,
+				"]" => depth -= 1,
+				_ => {}
+			}
+			self.iter.next();
+		}
+		self.iter.get_substring_from(start_index).unwrap_or("")
+	}
+
 	pub(crate) fn lex_images(&mut self) -> Result<Token<'a>, ParseError<'a>> {
 		let start_index = self.iter.get_index();
 		if self.iter.next_if_eq("!") != Some(&"!") {
@@ -804,8 +653,15 @@ impl<'a> Lexer<'a> {
 						.unwrap_or(""),
 				})
 			}
-			Ok(Token::Link(link, title, _)) => {
-				return Ok(Token::Image(link.content.to_string(), title))
+			Ok(Token::Link(link, alt, title)) => {
+				let (width, height) = self.lex_image_size().unwrap_or_default();
+				Ok(Token::Image(Image {
+					source: link.destination(),
+					alt: alt.map(|t| Token::plain_text(&t)),
+					title: title.map(str::to_string),
+					width,
+					height,
+				}))
 			}
 			_ => {
 				return Err(ParseError {
@@ -814,6 +670,16 @@ impl<'a> Lexer<'a> {
 			}
 		}
 	}
+	/// Moves past the `{width=300 height=200}` right after an image and
+	/// returns the size, if it is one.
+	fn lex_image_size(&mut self) -> Option<(Option<u32>, Option<u32>)> {
+		let rest = self.iter.peek_until_end()?.strip_prefix('{')?;
+		let end = rest.find(['}', '\n']).filter(|&i| &rest[i..i + 1] == "}")?;
+		let size = image::size(&rest[..end])?;
+		self.iter.update_index_to(self.iter.get_index() + end + 2);
+		Some(size)
+	}
+
 	pub(crate) fn lex_side_carrot(
 		&mut self,
 	) -> Result<Token<'a>, ParseError<'a>> {
@@ -822,159 +688,202 @@ impl<'a> Lexer<'a> {
 			return Err(ParseError { content: "" });
 		}
 
+		// Autolinks contain neither whitespace nor further angle brackets, so
+		// the search for the closing `>` stops at them
 		let s = self
 			.iter
-			.consume_while_case_holds(&|c| c != ">")
+			.consume_while_case_holds(&|c| {
+				c != ">" && c != "<" && !c.contains(char::is_whitespace)
+			})
 			.unwrap_or("");
 		match (s, self.iter.next_if_eq(">")) {
-			("details", Some(">")) => {
-				self.iter.next_if_eq(&"\r");
-				if !self.iter.next_if_eq(&"\n").is_some() {
-					return Err(ParseError { content: s });
-				}
-				return self.parse_details();
-			}
 			(_, Some(">")) if s.len() >= 1 => {
-				if s.contains(char::is_whitespace) {
-					return Err(ParseError {
-						content: self
-							.iter
-							.get_substring_from(start_index)
-							.unwrap_or(""),
-					});
-				}
 				match validate_link(s) {
+					// Auto links require a scheme
 					Ok(vl) if vl.scheme.is_some() => {
 						return Ok(Token::Link(vl, None, None))
-					} // Auto links require a scheme
+					}
+					_ if self.lex_html(start_index) => Ok(Token::Html(
+						self.iter.get_substring_from(start_index).unwrap_or(""),
+					)),
 					_ => {
-						return Err(ParseError {
-							content: self
-								.iter
-								.get_substring_from(start_index)
-								.unwrap_or(""),
-						})
+						self.warn(
+							DiagnosticKind::InvalidAutolink,
+							start_index..self.iter.get_index(),
+							format!(
+								"`<{}>` is not an autolink and is kept as text",
+								s
+							),
+						);
+						// Lex the text after the `<` again, so escapes and
+						// emphasis inside it are not lost
+						self.iter.update_index_to(start_index + 1);
+						Err(ParseError { content: "<" })
 					}
 				}
 			}
 			(_, Some(">")) if s.len() == 0 => {
 				return Err(ParseError { content: "<>" })
 			}
-			(_, _) => return Err(ParseError { content: s }),
+			(_, _) if self.lex_html(start_index) => Ok(Token::Html(
+				self.iter.get_substring_from(start_index).unwrap_or(""),
+			)),
+			(_, _) => {
+				self.iter.update_index_to(start_index + 1);
+				Err(ParseError { content: "<" })
+			}
 		}
 	}
 
-	fn parse_details(&mut self) -> Result<Token<'a>, ParseError<'a>> {
-		let mut summary_line = self
-			.iter
-			.consume_while_case_holds(&|c| c != "\n" && c != "\r")
-			.unwrap_or("");
-		self.iter.next_if_eq("\r");
-		if (!summary_line.starts_with("<summary>") ||
-			!summary_line.ends_with("</summary>")) &&
-			!summary_line.len() >= 20
-		{
-			return Err(ParseError {
-				content: summary_line,
-			});
-		}
-		summary_line = summary_line
-			.strip_prefix("<summary>")
-			.unwrap()
-			.strip_suffix("</summary>")
-			.unwrap_or("");
-		let remaining_text_index = self.iter.get_index();
-		let mut remaining_text =
-			self.iter.consume_until_tail_is("</details>").unwrap_or("");
-		if remaining_text.contains("<details>") {
-			let mut opens = remaining_text.matches("<details>").count();
-			let mut closes = remaining_text.matches("</details>").count();
-			while opens == closes {
-				self.iter.consume_until_tail_is("</details>").unwrap_or("");
-				remaining_text = self
-					.iter
-					.get_substring_from(remaining_text_index)
-					.unwrap_or("");
-				opens = remaining_text.matches("<details>").count();
-				closes = remaining_text.matches("</details>").count();
+	/// Moves past the raw html at `start_index`, if there is any.
+	///
+	/// See <https://spec.commonmark.org/0.29/#raw-html>.
+	fn lex_html(&mut self, start_index: usize) -> bool {
+		let source = self.iter.source();
+		match html::tag(source, start_index, &mut self.scan) {
+			Some((_, end)) => {
+				self.iter.update_index_to(end);
+				true
 			}
+			None => false,
 		}
-		let inner_tokens = Parser::lex(
-			remaining_text.strip_suffix("</details>").unwrap_or(""),
-			&[],
-		);
-		Ok(Token::Detail(summary_line.to_string(), inner_tokens))
 	}
 
-	pub(crate) fn lex_pipes(&mut self) -> Result<Token<'a>, ParseError<'a>> {
+	/// Lexes the markup of a syntax extension whose trigger is the next
+	/// character. Its content is lexed like the text of a link.
+	fn lex_custom(&mut self) -> Option<Token<'a>> {
+		let rest = self.iter.peek_until_end()?;
+		let trigger = rest.chars().next()?;
 		let start_index = self.iter.get_index();
-		let mut lines = Vec::new();
-		while self.iter.next_if_eq(&"|") == Some("|") {
-			lines.push(
-				self.iter
-					.consume_while_case_holds(&|c| c != "\n")
-					.unwrap_or(""),
-			);
-			self.iter.next();
+		let syntaxes = self.syntaxes;
+		for syntax in syntaxes.iter().filter(|s| s.trigger() == trigger) {
+			let Some(found) = syntax.parse(rest) else {
+				continue;
+			};
+			if found.length == 0 || !rest.is_char_boundary(found.length) {
+				continue;
+			}
+			let markup = &rest[..found.length];
+			let content =
+				found.content.and_then(|r| Some((r.start, markup.get(r)?)));
+			let inner = match content {
+				Some((offset, content)) if self.max_nesting > 0 => {
+					self.lex_link_text(content, start_index + offset)
+				}
+				Some((_, content)) => vec![Token::Plaintext(content.into())],
+				None => Vec::new(),
+			};
+			debug!("Found {} at {}", syntax.name(), start_index);
+			self.iter.update_index_to(start_index + found.length);
+			return Some(Token::Custom(syntax.name(), found.value, inner));
 		}
-		if lines.len() < 3 {
-			return Err(ParseError {
-				content: self
-					.iter
-					.get_substring_from(start_index)
-					.unwrap_or(""),
-			});
+		None
+	}
+
+	/// Lexes `www.`, `http(s)://`, `ftp://`, email and `mailto:`/`xmpp:`
+	/// autolinks that are not wrapped in angle brackets.
+	fn lex_extended_autolink(&mut self) -> Option<Token<'a>> {
+		if !self.autolinks {
+			return None;
 		}
-		if !lines
-			.iter()
-			.all(|l| l.matches("|").count() == lines[0].matches("|").count())
-		{
-			return Err(ParseError {
-				content: self
-					.iter
-					.get_substring_from(start_index)
-					.unwrap_or(""),
-			});
+		let source = self.iter.peek_until_end()?;
+		let (kind, len) =
+			autolink::match_autolink(source, self.iter.peek_behind())?;
+		let start_index = self.iter.get_index();
+		while self.iter.get_index() < start_index + len {
+			self.iter.next();
 		}
-		let headings: Vec<_> = lines
-			.remove(0)
-			.split("|")
-			.filter(|&x| x != "")
-			.map(|x| x.trim().to_string())
-			.collect();
-		let alignments: Vec<_> = lines
-			.remove(0)
-			.split("|")
-			.filter(|&x| x != "")
-			.map(|x| {
-				match (x.trim().starts_with(":"), x.trim().ends_with(":")) {
-					(true, false) => Alignment::Left,
-					(true, true) => Alignment::Center,
-					(false, true) => Alignment::Right,
-					_ => Alignment::Left,
-				}
-			})
-			.collect();
-		let mut rows = Vec::new();
-		for l in lines.into_iter() {
-			let elements: Vec<&str> = l
-				.split("|")
-				.filter(|&x| x != "")
-				.map(|x| x.trim())
-				.collect();
-			let mut r = Vec::new();
-			for e in elements.into_iter() {
-				let mut inner_tokens = Parser::lex(&e, &[]);
-				inner_tokens.retain(|token| token.is_usable_in_table());
-				r.push(inner_tokens);
-			}
-			rows.push(
-				alignments.clone().into_iter().zip(r.into_iter()).collect(),
-			);
+		let link = self.iter.get_substring_from(start_index)?;
+
+		let url = match kind {
+			AutolinkKind::Www => ValidURL {
+				content: link,
+				scheme:  Some(Scheme::Www),
+			},
+			AutolinkKind::Email => ValidURL {
+				content: link,
+				scheme:  Some(Scheme::Email("mailto")),
+			},
+			AutolinkKind::Url | AutolinkKind::ProtocolEmail => {
+				let (scheme, content) = link.split_once(':')?;
+				ValidURL {
+					content,
+					scheme: Some(match scheme {
+						"http" => Scheme::Http(scheme),
+						_ => Scheme::Other(scheme),
+					}),
+				}
+			}
+		};
+		Some(Token::Link(url, None, None))
+	}
+}
+
+/// Removes the quotes or parentheses around a link title, if they are
+/// balanced.
+///
+/// See <https://spec.commonmark.org/0.29/#link-title>.
+fn link_title(title: &str) -> &str {
+	let title = title.trim();
+	[('"', '"'), ('\'', '\''), ('(', ')')]
+		.iter()
+		.find_map(|&(open, close)| {
+			title.strip_prefix(open)?.strip_suffix(close)
+		})
+		.unwrap_or(title)
+}
+
+/// Checks whether `rest` starts with a line ending that is followed by another
+/// line of the same paragraph.
+fn continues_paragraph(rest: &str) -> bool {
+	rest.strip_prefix('\n').is_some_and(|next_line| {
+		let next_line = next_line.trim_start_matches([' ', '\t']);
+		!next_line.is_empty() && !next_line.starts_with('\n')
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn tokens(source: &str) -> Vec<Token<'_>> {
+		let mut lexer = Lexer::new(source, 64, ParseErrorAction::Serialize)
+			.with_autolinks(true);
+		std::iter::from_fn(|| lexer.next_token()).collect()
+	}
+
+	#[test]
+	fn text_runs_are_borrowed_from_the_source() {
+		let source = "plain text, nothing else";
+		match tokens(source).as_slice() {
+			[Token::Plaintext(Cow::Borrowed(t))] => assert_eq!(*t, source),
+			t => panic!("expected a single borrowed text run, got {:?}", t),
 		}
-		Ok(Token::Table(
-			alignments.into_iter().zip(headings.into_iter()).collect(),
-			rows,
-		))
+	}
+
+	#[test]
+	fn text_runs_end_in_front_of_markup() {
+		assert_eq!(tokens("foo `bar` baz www.qux.org"), vec![
+			Token::Plaintext("foo ".into()),
+			Token::Code("bar".into()),
+			Token::Plaintext(" baz ".into()),
+			Token::Link(
+				ValidURL {
+					content: "www.qux.org",
+					scheme:  Some(Scheme::Www),
+				},
+				None,
+				None
+			),
+		]);
+	}
+
+	#[test]
+	fn text_runs_end_in_front_of_hard_breaks() {
+		assert_eq!(tokens("foo bar  \nbaz"), vec![
+			Token::Plaintext("foo bar".into()),
+			Token::LineBreak,
+			Token::Plaintext("baz".into()),
+		]);
 	}
 }
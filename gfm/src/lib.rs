@@ -1,12 +1,90 @@
+// rustfmt indents the examples of doc comments with tabs, like the code.
+#![allow(clippy::tabs_in_doc_comments)]
+
+mod ast;
+mod autolink;
+mod block;
+mod diagnostic;
+mod emphasis;
+mod error;
+mod extension;
+mod html;
+mod image;
+mod indent;
 mod iter;
 mod lexer;
+mod lint;
+#[cfg(feature = "serde")]
+mod mdast;
+mod options;
+mod policy;
+mod resolve;
+mod sanitize;
 mod token;
+mod visit;
+
+use std::sync::Arc;
 
+pub use ast::{
+	Block,
+	Custom,
+	Document,
+	Image,
+	Inline,
+	ListItem,
+};
+pub use block::{
+	Alignment,
+	ListKind,
+	TaskBox,
+};
+pub use diagnostic::{
+	Diagnostic,
+	DiagnosticKind,
+	Severity,
+};
+pub use error::Error;
+pub use extension::{
+	escape,
+	BlockSyntax,
+	Extensions,
+	InlineSyntax,
+	Match,
+};
+pub use image::ImageOptions;
 use lexer::Lexer;
+pub use lint::{
+	Lint,
+	LintOptions,
+	Rule,
+};
 use log::debug;
+pub use options::{
+	Options,
+	ParseErrorAction,
+};
+pub use policy::{
+	LinkAttributes,
+	LinkPolicy,
+};
+pub use resolve::{
+	ResolveUrl,
+	UrlKind,
+	UrlResolver,
+	UrlRewriter,
+};
+pub use sanitize::Sanitizer;
 use token::Token;
-
-use crate::token::TaskBox;
+pub use visit::{
+	walk_block,
+	walk_block_mut,
+	walk_blocks_mut,
+	walk_inline,
+	walk_inline_mut,
+	walk_inlines_mut,
+	Visitor,
+	VisitorMut,
+};
 
 pub struct Parser {}
 
@@ -28,174 +106,285 @@ impl Parser {
 	/// assert_eq!(html, expected_html);
 	/// ```
 	pub fn render(source: &str) -> String {
-		debug!("Rendering source of length: {}", source.len());
-		Self::parse(&Self::lex(source, &[]))
+		Self::render_with_options(source, &Options::default())
 	}
 
-	pub(crate) fn render_ignore(source: &str, ignore: &[char]) -> String {
-		Self::parse(&Self::lex(source, ignore))
+	/// Converts a Markdown string into sanitized HTML, like [Parser::render],
+	/// using the given [Options].
+	///
+	/// The block structure of the whole document is determined first, then
+	/// the inline content of every leaf block is lexed as it is rendered.
+	///
+	/// ### Example
+	///
+	/// ```rust
+	/// let options = gfm::Options {
+	/// 	breaks: true,
+	/// 	..Default::default()
+	/// };
+	/// let html = gfm::Parser::render_with_options("foo\nbar", &options);
+	/// assert_eq!(html, "<p>foo<br />\nbar</p>\n");
+	/// ```
+	pub fn render_with_options(source: &str, options: &Options) -> String {
+		Self::render_document(&Self::parse(source, options), options)
 	}
 
-	pub(crate) fn lex<'a>(source: &'a str, ignore: &[char]) -> Vec<Token<'a>> {
-		debug!("Lexing source with ignore list: {:?}", ignore);
-		let mut l = Lexer::new(source);
-		let mut tokens = Vec::new();
+	/// Converts a Markdown string into sanitized HTML, like
+	/// [Parser::render_with_options], and returns what was kept as text
+	/// instead of being rendered as markup, ordered by where it starts in the
+	/// source.
+	///
+	/// ### Example
+	///
+	/// ```rust
+	/// use gfm::DiagnosticKind;
+	/// use gfm::Options;
+	/// use gfm::Parser;
+	///
+	/// let source = "foo\n[bar](baz";
+	/// let options = Options::default();
+	/// let (_, found) = Parser::render_with_diagnostics(source, &options);
+	/// assert_eq!(found[0].kind, DiagnosticKind::MalformedLink);
+	/// assert_eq!(found[0].line_column(source), (2, 1));
+	/// ```
+	pub fn render_with_diagnostics(
+		source: &str,
+		options: &Options,
+	) -> (String, Vec<Diagnostic>) {
+		let (document, diagnostics) =
+			Self::parse_with_diagnostics(source, options);
+		(Self::render_document(&document, options), diagnostics)
+	}
 
-		while let Some(token) = l.next_token(ignore, &tokens) {
-			tokens.push(token);
+	/// Converts a Markdown string into sanitized HTML, like
+	/// [Parser::render_with_options], but fails instead of rendering a source
+	/// that exceeds the limits set in [Options], or that contains markup
+	/// which cannot be parsed if [Options::on_parse_error] is
+	/// [ParseErrorAction::Abort].
+	///
+	/// ### Example
+	///
+	/// ```rust
+	/// let options = gfm::Options {
+	/// 	max_nesting: 2,
+	/// 	..Default::default()
+	/// };
+	/// let error = gfm::Parser::try_render("> > > foo", &options).unwrap_err();
+	/// assert_eq!(error, gfm::Error::NestingTooDeep { line: 1, limit: 2 });
+	/// ```
+	pub fn try_render(
+		source: &str,
+		options: &Options,
+	) -> Result<String, Error> {
+		if source.len() > options.max_input_size {
+			return Err(Error::InputTooLarge {
+				size:  source.len(),
+				limit: options.max_input_size,
+			});
 		}
+		let blocks = match block::parse(source, options) {
+			(_, Some(too_deep)) => {
+				return Err(Error::NestingTooDeep {
+					line:  too_deep.line_column(source).0,
+					limit: options.max_nesting,
+				})
+			}
+			(blocks, None) => blocks,
+		};
+		let mut diagnostics = Vec::new();
+		let document = ast::build(blocks, options, &mut diagnostics);
+		let html = Self::render_document(&document, options);
+		match diagnostics.into_iter().min_by_key(|d| d.span.start) {
+			Some(diagnostic)
+				if options.on_parse_error == ParseErrorAction::Abort =>
+			{
+				let (line, column) = diagnostic.line_column(source);
+				Err(Error::InvalidMarkup {
+					line,
+					column,
+					diagnostic,
+				})
+			}
+			_ => Ok(html),
+		}
+	}
 
-		debug!("Lexing completed, total tokens: {}", tokens.len());
-		tokens
+	/// Checks a Markdown string against the style rules in [LintOptions] and
+	/// returns the violations, ordered by where they start in the source.
+	///
+	/// ### Example
+	///
+	/// ```rust
+	/// use gfm::LintOptions;
+	/// use gfm::Parser;
+	/// use gfm::Rule;
+	///
+	/// let lints = Parser::lint("# foo\n### bar\n", &LintOptions::default());
+	/// assert_eq!(lints[0].rule, Rule::HeadingIncrement);
+	/// assert_eq!(lints[0].line_column("# foo\n### bar\n"), (2, 5));
+	/// ```
+	pub fn lint(source: &str, options: &LintOptions) -> Vec<Lint> {
+		lint::lint(source, options)
 	}
 
-	fn parse(tokens: &[Token<'_>]) -> String {
-		debug!("Parsing {} tokens", tokens.len());
-		let mut html = String::with_capacity(tokens.len() * 100);
-		let mut quote_level = 0;
-		let mut in_task_list = false;
-		let mut in_paragraph = false;
-		let mut in_ordered_list = false;
-		let mut in_unordered_list = false;
-		let mut in_code = false;
-		let mut references = Vec::new();
-		let mut token_iter = tokens.iter().peekable();
+	/// Parses a Markdown string into a [Document], which can be changed, eg.
+	/// with a [VisitorMut], before it is rendered with
+	/// [Parser::render_document].
+	///
+	/// ### Example
+	///
+	/// ```rust
+	/// use gfm::Block;
+	/// use gfm::Options;
+	/// use gfm::Parser;
+	///
+	/// let document = Parser::parse("# foo\n\nbar", &Options::default());
+	/// assert!(matches!(document.blocks[0], Block::Header(1, _, None)));
+	/// assert!(matches!(document.blocks[1], Block::Paragraph(_)));
+	/// ```
+	pub fn parse(source: &str, options: &Options) -> Document {
+		let (blocks, _) = block::parse(source, options);
+		ast::build(blocks, options, &mut Vec::new())
+	}
 
-		// multi-liners
-		while token_iter.peek().is_some() {
-			let token = token_iter.next().unwrap();
+	/// Parses a Markdown string into a [Document], like [Parser::parse], and
+	/// returns what was kept as text instead of being parsed as markup,
+	/// ordered by where it starts in the source.
+	pub fn parse_with_diagnostics(
+		source: &str,
+		options: &Options,
+	) -> (Document, Vec<Diagnostic>) {
+		let (blocks, too_deep) = block::parse(source, options);
+		let mut diagnostics: Vec<_> = too_deep.into_iter().collect();
+		let document = ast::build(blocks, options, &mut diagnostics);
+		diagnostics.sort_by_key(|d| d.span.start);
+		(document, diagnostics)
+	}
 
-			debug!("Processing token: {:?}", token);
-			match token {
-				Token::Plaintext(t) if t.trim().is_empty() => {} // ignore
-				Token::Tab | Token::DoubleTab => {}
-				Token::OrderedListEntry(_) |
-				Token::UnorderedListEntry(_) |
-				Token::Newline
-					if in_ordered_list | in_unordered_list => {}
-				Token::TaskListItem(_, _) | Token::Newline if in_task_list => {}
-				Token::Plaintext(_) |
-				Token::Italic(_) |
-				Token::Bold(_) |
-				Token::BoldItalic(_) |
-				Token::Strikethrough(_) |
-				Token::Link(_, _, _)
-					if !in_paragraph =>
-				{
-					for _i in 0..quote_level {
-						html.push_str("</blockquote>");
-						quote_level -= 1;
-					}
-					in_paragraph = true;
-					html.push_str("<p>")
-				}
-				_ if in_ordered_list => {
-					in_ordered_list = false;
-					html.push_str("</ol>\n");
-					if !in_paragraph {
-						in_paragraph = true;
-						html.push_str("<p>")
-					}
-				}
-				_ if in_unordered_list => {
-					in_unordered_list = false;
-					html.push_str("</ul>\n");
-					if !in_paragraph {
-						in_paragraph = true;
-						html.push_str("<p>")
-					}
-				}
-				_ if in_task_list => {
-					in_task_list = false;
-					html.push_str("</ul>\n");
-					if !in_paragraph {
-						in_paragraph = true;
-						html.push_str("<p>")
-					}
-				}
-				Token::CodeBlock(_, _) |
-				Token::Newline |
-				Token::Header(_, _, _)
-					if in_paragraph =>
-				{
-					in_paragraph = false;
-					html.push_str("</p>\n")
-				}
-				Token::BlockQuote(_, _) | Token::Newline if quote_level > 0 => {
-				}
-				Token::CodeBlock(_, _) |
-				Token::Newline |
-				Token::Header(_, _, _)
-					if in_paragraph =>
-				{
-					in_paragraph = false;
-					html.push_str("</p>\n")
-				}
-				Token::Code(_) if !in_code => {
-					html.push_str("<pre><code>");
-					in_code = true;
-				}
-				_ => {}
-			}
+	/// Renders a [Document] into sanitized HTML. Links to destinations that
+	/// are not allowed, eg. `javascript:`, are rendered as their text.
+	///
+	/// ### Example
+	///
+	/// ```rust
+	/// use gfm::Block;
+	/// use gfm::Options;
+	/// use gfm::Parser;
+	/// use gfm::VisitorMut;
+	///
+	/// /// Demotes every heading by one level.
+	/// struct Demote;
+	///
+	/// impl VisitorMut for Demote {
+	/// 	fn visit_block_mut(&mut self, block: &mut Block) {
+	/// 		if let Block::Header(level, _, _) = block {
+	/// 			*level = (*level + 1).min(6);
+	/// 		}
+	/// 		gfm::walk_block_mut(self, block);
+	/// 	}
+	/// }
+	///
+	/// let options = Options::default();
+	/// let mut document = Parser::parse("# foo", &options);
+	/// document.visit_mut(&mut Demote);
+	/// assert_eq!(
+	/// 	Parser::render_document(&document, &options),
+	/// 	"<h2>foo</h2>\n"
+	/// );
+	/// ```
+	pub fn render_document(document: &Document, options: &Options) -> String {
+		debug!("Rendering document of {} blocks", document.blocks.len());
+		let mut html = String::new();
+		let mut footnotes = Vec::new();
+		Self::render_blocks(
+			&document.blocks,
+			false,
+			options,
+			&mut html,
+			&mut footnotes,
+		);
+		Self::render_footnotes(&footnotes, options, &mut html);
+		debug!("Rendering completed");
+		html
+	}
 
-			match token {
-				Token::Plaintext(t) => {
-					let mut t: String = t.to_string();
-					if t.trim().is_empty() {
-						continue;
-					}
+	/// Lexes the inline content of a leaf block, eg. a paragraph, heading or
+	/// table cell, or the text of a link. Emphasis and links are nested at
+	/// most `max_nesting` levels deep, and so is the content of syntax
+	/// extensions. Markup that is kept as text is added to `diagnostics`,
+	/// with spans relative to `source`.
+	pub(crate) fn lex_inline<'a>(
+		source: &'a str,
+		max_nesting: usize,
+		on_error: ParseErrorAction,
+		syntaxes: &'a [Arc<dyn InlineSyntax>],
+		autolinks: bool,
+		diagnostics: &mut Vec<Diagnostic>,
+	) -> Vec<Token<'a>> {
+		let mut lexer = Lexer::new(source, max_nesting, on_error)
+			.with_syntaxes(syntaxes)
+			.with_autolinks(autolinks);
+		let mut tokens = Vec::new();
 
-					t.rfind('\n').map(|n| {
-						let (_before, after) = t.split_at(n);
-						if after.chars().all(|c| c.is_whitespace()) {
-							t = t.trim_end_matches(after).to_string();
-						}
-					});
+		while let Some(token) = lexer.next_token() {
+			tokens.push(token);
+		}
+		diagnostics.append(&mut lexer.into_diagnostics());
 
-					if t.contains("[^") && t.contains("]") {
-						let plaintext_tokens = t.split("[^");
-						let mut s = String::new();
-						let mut count = 1;
-						for tok in plaintext_tokens {
-							if tok.trim_end().ends_with("]") {
-								let tok = tok.trim_end().trim_end_matches(']');
-								s.push_str(
-									format!(
-										"<sup id=\"fnref:{reference}\" \
-										 role=\"doc-noteref\"><a \
-										 href=\"#fn:{reference}\" \
-										 class=\"footnote\" \
-										 rel=\"footnote\">{ref_count}</a></\
-										 sup>",
-										reference =
-											Self::sanitize_display_text(tok),
-										ref_count = count
-									)
-									.as_str(),
-								);
-								count += 1;
-							} else {
-								s.push_str(tok)
-							}
-						}
-						html.push_str(&s);
-					} else {
-						html.push_str(&Self::sanitize_display_text(
-							t.trim_start_matches('\n'),
-						))
-					}
+		debug!("Lexing completed, total tokens: {}", tokens.len());
+		emphasis::resolve(tokens, max_nesting)
+	}
+
+	/// Renders blocks and the blocks nested in them.
+	///
+	/// Paragraphs of tight lists are rendered without <p> tags. Footnotes are
+	/// collected to be rendered at the end of the document.
+	fn render_blocks<'b>(
+		blocks: &'b [Block],
+		tight: bool,
+		options: &Options,
+		html: &mut String,
+		footnotes: &mut Vec<(&'b str, &'b [Block])>,
+	) {
+		for block in blocks.iter() {
+			match block {
+				Block::Paragraph(t) if tight => {
+					html.push_str(&Self::render_inline(t, options))
 				}
-				Token::Header(l, t, lbl) => {
+				Block::Paragraph(t) => match &t[..] {
+					[Inline::Image(
+						image @ Image {
+							title: Some(title), ..
+						},
+					)] if options.images.figures => {
+						Self::cr(html);
+						html.push_str(&format!(
+							"<figure>\n{}\n<figcaption>{}</figcaption>\n</\
+							 figure>\n",
+							Self::render_image(image, options),
+							html::text(title)
+						));
+					}
+					_ => {
+						Self::cr(html);
+						html.push_str(
+							format!(
+								"<p>{}</p>\n",
+								Self::render_inline(t, options)
+							)
+							.as_str(),
+						);
+					}
+				},
+				Block::Header(l, t, lbl) => {
+					Self::cr(html);
 					match lbl {
 						Some(lbl_text) => html.push_str(
 							format!(
 								"<h{level} id=\"{id}\">{text}</h{level}>\n",
 								level = l,
-								text = t,
-								id = Self::sanitize_display_text(
-									&lbl_text.replace(" ", "-")
-								) /* TODO:
-								   * is id necessary? */
+								text = Self::render_inline(t, options),
+								id = html::attribute(lbl_text)
 							)
 							.as_str(),
 						),
@@ -203,387 +392,380 @@ impl Parser {
 							format!(
 								"<h{level}>{text}</h{level}>\n",
 								level = l,
-								text = t
+								text = Self::render_inline(t, options)
 							)
 							.as_str(),
 						),
 					};
 				}
-				Token::OrderedListEntry(t) => {
-					if !in_ordered_list {
-						in_ordered_list = true;
-						html.push_str("<ol>\n".to_string().as_str())
-					}
-					html.push_str(
-						format!(
-							"<li>\n{}</li>\n",
-							Self::sanitize_display_text(t)
-						)
-						.as_str(),
-					)
+				Block::HorizontalRule => {
+					Self::cr(html);
+					html.push_str("<hr />\n");
 				}
-				Token::UnorderedListEntry(toks) => {
-					if !in_unordered_list {
-						in_unordered_list = true;
-						html.push_str("<ul>\n")
-					}
-
-					html.push_str("<li>".to_string().as_str());
-					if toks.iter().all(|t| matches!(t, Token::Plaintext(_))) {
-						html.push_str("\n".to_string().as_str());
+				Block::CodeBlock(code, info) => {
+					Self::cr(html);
+					match info.split_whitespace().next() {
+						Some(lang) => html.push_str(
+							format!(
+								"<pre><code class=\"language-{}\">",
+								html::attribute(lang)
+							)
+							.as_str(),
+						),
+						None => html.push_str("<pre><code>"),
 					}
-					for token in toks.iter() {
-						match token {
-							Token::Plaintext(text)
-								if text.starts_with("\t\t") =>
-							{
-								html.push_str(
-									&Self::render(
-										text[1..].trim_start_matches(" "),
-									)
-									.replace("<pre><code>", "<pre><code>  "),
-								);
-							}
-							Token::Plaintext(text) => {
-								let text =
-									&Self::render(text.trim_start_matches(" "))
-										.replace(
-											"<pre><code>",
-											"<pre><code>  ",
-										);
-								html.push_str(text);
-							}
-							_ => {}
+					html.push_str(&html::text(code));
+					html.push_str("</code></pre>\n");
+				}
+				Block::BlockQuote(inner) => {
+					Self::cr(html);
+					html.push_str("<blockquote>\n");
+					Self::render_blocks(inner, false, options, html, footnotes);
+					Self::cr(html);
+					html.push_str("</blockquote>\n");
+				}
+				Block::List(kind, tight, items) => {
+					Self::cr(html);
+					let close = match kind {
+						ListKind::Bullet(_) => {
+							html.push_str("<ul>\n");
+							"</ul>\n"
 						}
+						ListKind::Ordered(1, _) => {
+							html.push_str("<ol>\n");
+							"</ol>\n"
+						}
+						ListKind::Ordered(start, _) => {
+							html.push_str(
+								format!("<ol start=\"{}\">\n", start).as_str(),
+							);
+							"</ol>\n"
+						}
+					};
+					for item in items.iter() {
+						Self::render_list_item(
+							item, *tight, options, html, footnotes,
+						);
 					}
-					html.push_str("</li>\n".to_string().as_str());
+					Self::cr(html);
+					html.push_str(close);
 				}
-				Token::Image(l, t) => match (l, t) {
-					(l, None) if l.trim() == "" => {
-						html.push_str("<p><img src=\"data:,\"></p>")
-					}
-					(l, Some(t)) if l.trim() == "" => html.push_str(
-						format!(
-							"<p><img src=\"data:,\" alt=\"{text}\"></p>",
-							text = Self::sanitize_display_text(t)
-						)
-						.as_str(),
-					),
-					(l, None) => html.push_str(
-						format!(
-							"<p><img src=\"{link}\"> \
-							 referrerpolicy=\"no-referrer\"></p>",
-							link = l
-						)
-						.as_str(),
-					),
-					(l, Some(t)) => html.push_str(
-						format!(
-							"<p><img src=\"{link}\" alt=\"{text}\" \
-							 referrerpolicy=\"no-referrer\"></p>",
-							link = l,
-							text = Self::sanitize_display_text(t)
-						)
-						.as_str(),
-					),
-				},
-				Token::Link(l, t, ht) => match (t, ht) {
-					(Some(t), Some(ht)) => html.push_str(
-						format!(
-							"<a href=>\"{link}\" title=\"{hover}\">{text}</a>",
-							link = l,
-							text = Self::sanitize_display_text(t),
-							hover = ht
-						)
-						.as_str(),
-					),
-					(Some(t), None) => html.push_str(
-						format!(
-							"<a href=\"{link}\">{text}</a>",
-							link = l,
-							text = Self::sanitize_display_text(t)
-						)
-						.as_str(),
-					),
-					(None, Some(ht)) => html.push_str(
-						format!(
-							"<a href=\"{link}\" title=\"{hover}\">{link}</a>",
-							link = l,
-							hover = Self::sanitize_display_text(ht)
-						)
-						.as_str(),
-					),
-					(None, None) => html.push_str(
-						format!(
-							"<a href=\"{link}\">{display}</a>",
-							link = l,
-							display = l.fmt_unsafe()
-						)
-						.as_str(),
-					),
-				},
-				Token::Detail(summary, inner_tokens) => {
-					if in_paragraph {
-						html.push_str("</p>\n");
-						in_paragraph = false;
+				Block::Table(alignments, headings, rows) => {
+					Self::cr(html);
+					html.push_str("<table>\n<thead>\n");
+					Self::render_table_row(
+						"th", alignments, headings, options, html,
+					);
+					html.push_str("</thead>\n");
+					if !rows.is_empty() {
+						html.push_str("<tbody>\n");
+						for row in rows.iter() {
+							Self::render_table_row(
+								"td", alignments, row, options, html,
+							);
+						}
+						html.push_str("</tbody>\n");
 					}
-					let inner_html = Self::parse(inner_tokens);
+					html.push_str("</table>\n");
+				}
+				Block::Detail(summary, inner) => {
+					Self::cr(html);
 					html.push_str(
 						format!(
-							"<details>\n<summary>{sum}</summary>\n{in_html}\\
-							 n</details>",
-							sum = Self::sanitize_display_text(summary),
-							in_html = inner_html
+							"<details>\n<summary>{}</summary>\n",
+							Self::render_inline(summary, options)
 						)
 						.as_str(),
 					);
+					Self::render_blocks(inner, false, options, html, footnotes);
+					Self::cr(html);
+					html.push_str("</details>\n");
 				}
-				Token::Italic(t) => html.push_str(
-					format!("<em>{}</em>", Self::sanitize_display_text(t))
-						.as_str(),
-				),
-				Token::Bold(t) => html.push_str(
+				Block::Footnote(ref_id, inner) => {
+					footnotes.push((ref_id, inner))
+				}
+				Block::Custom(node) => {
+					Self::cr(html);
+					let content = Self::render_inline(&node.children, options);
+					html.push_str(&extension::render_block(
+						node, &content, options,
+					));
+				}
+				Block::Html(source) => {
+					// Comments and declarations are removed entirely
+					let sanitized = options
+						.sanitizer
+						.sanitize_links(source, &options.link_policy);
+					if !sanitized.trim().is_empty() {
+						Self::cr(html);
+						html.push_str(&sanitized);
+						html.push('\n');
+					}
+				}
+			}
+		}
+	}
+
+	fn render_list_item<'b>(
+		item: &'b ListItem,
+		tight: bool,
+		options: &Options,
+		html: &mut String,
+		footnotes: &mut Vec<(&'b str, &'b [Block])>,
+	) {
+		Self::cr(html);
+		html.push_str("<li>");
+		match item.task {
+			Some(TaskBox::Checked) => html.push_str(
+				"<input checked=\"\" disabled=\"\" type=\"checkbox\"> ",
+			),
+			Some(TaskBox::Unchecked) => {
+				html.push_str("<input disabled=\"\" type=\"checkbox\"> ")
+			}
+			None => {}
+		}
+		Self::render_blocks(&item.children, tight, options, html, footnotes);
+		html.push_str("</li>\n");
+	}
+
+	fn render_table_row(
+		tag: &str,
+		alignments: &[Option<Alignment>],
+		cells: &[Vec<Inline>],
+		options: &Options,
+		html: &mut String,
+	) {
+		html.push_str("<tr>\n");
+		for (cell, align) in cells.iter().zip(alignments.iter()) {
+			match align {
+				Some(align) => html.push_str(
 					format!(
-						"<strong>{}</strong>",
-						Self::sanitize_display_text(t)
+						"<{tag} align=\"{align}\">{text}</{tag}>\n",
+						text = Self::render_inline(cell, options)
 					)
 					.as_str(),
 				),
-				Token::BoldItalic(t) => html.push_str(
+				None => html.push_str(
 					format!(
-						"<strong><em>{}</em></strong>",
-						Self::sanitize_display_text(t)
+						"<{tag}>{text}</{tag}>\n",
+						text = Self::render_inline(cell, options)
 					)
 					.as_str(),
 				),
-				Token::BlockQuote(l, t) => {
-					if in_paragraph {
-						html.push_str("</p>");
-						in_paragraph = false;
-					}
-					match quote_level {
-						_ if l == &quote_level => {}
-						_ if l < &quote_level => {
-							let diff = quote_level - l;
-							quote_level = *l;
-							for _i in 0..diff {
-								html.push_str("</blockquote>");
-							}
-						}
-						_ if l > &quote_level => {
-							let diff = l - quote_level;
-							quote_level = *l;
-							for _i in 0..diff {
-								html.push_str("<blockquote>\n");
-							}
-						}
-						_ => {}
-					}
-					if !t.is_empty() {
-						html.push_str(
-							&Self::render(&Self::sanitize_display_text(
-								t.trim_start_matches(" "),
-							))
-							.replace("\t", "  "),
-						);
-					}
-				}
-				Token::TaskListItem(c, t) => {
-					if !in_task_list {
-						in_task_list = true;
-						html.push_str("<ul class=\"contains-task-list\">")
-					}
-					match c {
-						TaskBox::Checked => html.push_str(
-							format!(
-								"<li class=\"task-list-item\"><input \
-								 type=\"checkbox\" \
-								 class=\"task-list-item-checkbox\" \
-								 checked=\"\">{}</li>",
-								Self::sanitize_display_text(t)
-							)
-							.as_str(),
-						),
-						TaskBox::Unchecked => html.push_str(
-							format!(
-								"<li class=\"task-list-item\"><input \
-								 type=\"checkbox\" \
-								 class=\"task-list-item-checkbox\">{}</li>",
-								Self::sanitize_display_text(t)
-							)
-							.as_str(),
-						),
-					}
+			}
+		}
+		html.push_str("</tr>\n");
+	}
+
+	fn render_footnotes(
+		footnotes: &[(&str, &[Block])],
+		options: &Options,
+		html: &mut String,
+	) {
+		if footnotes.is_empty() {
+			return;
+		}
+		html.push_str(
+			"<div class=\"footnotes\" role=\"doc-endnotes\">\n<ol>\n",
+		);
+		for (ref_id, blocks) in footnotes.iter() {
+			let ref_id = html::attribute(ref_id);
+			html.push_str(
+				format!("<li id=\"fn:{}\" role=\"doc-endnote\">\n", ref_id)
+					.as_str(),
+			);
+			let mut content = String::new();
+			Self::render_blocks(
+				blocks,
+				false,
+				options,
+				&mut content,
+				&mut Vec::new(),
+			);
+			let backlink = format!(
+				"<a href=\"#fnref:{}\" class=\"reversefootnote\" \
+				 role=\"doc-backlink\">↩</a>",
+				ref_id
+			);
+			// The link back to the reference ends the last paragraph
+			match content.strip_suffix("</p>\n") {
+				Some(content) => {
+					html.push_str(content);
+					html.push_str(&backlink);
+					html.push_str("</p>\n");
 				}
-				Token::Table(headings, rows) => {
-					if headings.len() != rows[0].len() {
-						continue;
-					}
-					html.push_str(
-						"<table class=\"table \
-						 table-bordered\">\n\t<thead>\n\t<tr>\n",
-					);
-					for h in headings.into_iter() {
-						html.push_str(
-							format!(
-								"\t\t<th style=\"text-align: \
-								 {align}\">{heading}</th>",
-								heading = Self::sanitize_display_text(&h.1),
-								align = h.0
-							)
-							.as_str(),
-						);
-					}
-					html.push_str("\t</tr>\n\t</thead>\n\t<tbody>");
-					for row in rows.iter() {
-						html.push_str("\n\t<tr>");
-						for elem in row.iter() {
-							let mut row_string = String::new();
-							for token in elem.1.iter() {
-								match token {
-									Token::Plaintext(s) => row_string.push_str(
-										&Self::sanitize_display_text(&s),
-									),
-									Token::Italic(t) => row_string.push_str(
-										format!(
-											"<em>{}</em>",
-											Self::sanitize_display_text(t)
-										)
-										.as_str(),
-									),
-									Token::Bold(t) => row_string.push_str(
-										format!(
-											"<strong>{}</strong>",
-											Self::sanitize_display_text(t)
-										)
-										.as_str(),
-									),
-									Token::BoldItalic(t) => row_string
-										.push_str(
-											format!(
-												"<strong><em>{}</em></strong>",
-												Self::sanitize_display_text(t)
-											)
-											.as_str(),
-										),
-									Token::LineBreak => {
-										row_string.push_str("<br>")
-									}
-									Token::HorizontalRule => {
-										row_string.push_str("<hr />")
-									}
-									Token::Strikethrough(t) => row_string
-										.push_str(
-											format!(
-												"<strike>{}</strike>",
-												Self::sanitize_display_text(t)
-											)
-											.as_str(),
-										),
-									_ => row_string
-										.push_str(&Self::parse(&elem.1)),
-								}
-							}
-							html.push_str(
-								format!(
-									"\n\t\t<td style=\"text-align: \
-									 {align}\">{row_text}</td>",
-									align = elem.0,
-									row_text = row_string
-								)
-								.as_str(),
-							);
-						}
-						html.push_str("\n\t</tr>");
-					}
-					html.push_str("\n\t</tbody>\n</table>");
+				None => {
+					html.push_str(&content);
+					html.push_str(&backlink);
+					html.push('\n');
 				}
-				Token::Strikethrough(t) => html.push_str(
+			}
+			html.push_str("</li>\n");
+		}
+		html.push_str("</ol>\n</div>\n");
+	}
+
+	/// Renders inline content, eg. of a paragraph or an emphasis.
+	fn render_inline(inlines: &[Inline], options: &Options) -> String {
+		let mut html = String::new();
+		for inline in inlines.iter() {
+			match inline {
+				Inline::FootnoteReference(reference) => html.push_str(
+					format!(
+						"<sup id=\"fnref:{reference}\" \
+						 role=\"doc-noteref\"><a href=\"#fn:{reference}\" \
+						 class=\"footnote\" \
+						 rel=\"footnote\">{reference}</a></sup>",
+						reference = html::attribute(reference),
+					)
+					.as_str(),
+				),
+				Inline::Text(t) => html.push_str(&html::text(t)),
+				Inline::Emphasis(inner) => html.push_str(
+					format!("<em>{}</em>", Self::render_inline(inner, options))
+						.as_str(),
+				),
+				Inline::Strong(inner) => html.push_str(
 					format!(
-						"<strike>{}</strike>",
-						Self::sanitize_display_text(t)
+						"<strong>{}</strong>",
+						Self::render_inline(inner, options)
 					)
 					.as_str(),
 				),
-				Token::Code(t) => html.push_str(
-					format!("{}", Self::sanitize_display_text(t)).as_str(),
+				Inline::Strikethrough(t) => html.push_str(
+					format!("<strike>{}</strike>", html::text(t)).as_str(),
+				),
+				Inline::Code(t) => html.push_str(
+					format!("<code>{}</code>", html::text(t)).as_str(),
 				),
-				Token::HorizontalRule => html.push_str("<hr />\n"),
-				Token::Newline => {}
-				Token::Tab => html.push('\t'),
-				Token::DoubleTab => html.push_str("\t\t"),
-				Token::Footnote(ref_id, text) => {
-					references.push((ref_id, text));
+				Inline::Link(l, t, ht) => {
+					html.push_str(&Self::render_link(l, t, ht, options))
 				}
-				_ => {}
+				Inline::Autolink(l, t) => {
+					html.push_str(&Self::render_autolink(l, t, options))
+				}
+				Inline::Image(image) => {
+					html.push_str(&Self::render_image(image, options))
+				}
+				Inline::LineBreak => html.push_str("<br />\n"),
+				Inline::SoftBreak => Self::push_soft_break(&mut html, options),
+				Inline::Custom(node) => {
+					let content = Self::render_inline(&node.children, options);
+					html.push_str(&extension::render_inline(
+						node, &content, options,
+					))
+				}
+				Inline::Html(source) => html.push_str(
+					&options
+						.sanitizer
+						.sanitize_links(source, &options.link_policy),
+				),
+			}
+		}
+		html
+	}
+
+	/// Renders a link, or only its text if the destination is not allowed.
+	fn render_link(
+		l: &str,
+		t: &[Inline],
+		ht: &Option<String>,
+		options: &Options,
+	) -> String {
+		let text = Self::render_inline(t, options);
+		let l = options.url_resolver.resolve(l, UrlKind::Link);
+		match (Self::allowed_link(&l, options), ht) {
+			(Some((link, attributes)), Some(ht)) => format!(
+				"<a href=\"{link}\" title=\"{hover}\"{attributes}>{text}</a>",
+				hover = html::attribute(ht)
+			),
+			(Some((link, attributes)), None) => {
+				format!("<a href=\"{link}\"{attributes}>{text}</a>")
 			}
+			(None, _) => text,
 		}
+	}
 
-		if in_paragraph {
-			html.push_str("</p>\n");
+	/// Renders an autolink, or only its text if the destination is not
+	/// allowed.
+	fn render_autolink(l: &str, t: &str, options: &Options) -> String {
+		let text = html::text(t);
+		let l = options.url_resolver.resolve(l, UrlKind::Autolink);
+		match Self::allowed_link(&l, options) {
+			Some((link, attributes)) => {
+				format!("<a href=\"{link}\"{attributes}>{text}</a>")
+			}
+			None => text,
 		}
-		if quote_level > 0 {
-			for _i in (0..quote_level).rev() {
-				html.push_str("</blockquote>\n");
+	}
+
+	/// Returns the encoded destination of a link along with the attributes
+	/// its policy adds, or `None` if it is not allowed.
+	fn allowed_link(l: &str, options: &Options) -> Option<(String, String)> {
+		let link = token::validate_link(l).ok()?;
+		let destination = link.destination();
+		options.link_policy.allows(&destination).then(|| {
+			(
+				link.to_string(),
+				options.link_policy.attributes(&destination),
+			)
+		})
+	}
+
+	/// Renders an image. Sources that are not allowed are left empty.
+	fn render_image(image: &Image, options: &Options) -> String {
+		let l = options.url_resolver.resolve(&image.source, UrlKind::Image);
+		let src = match token::validate_link(&l) {
+			Ok(link)
+				if !l.trim().is_empty() &&
+					options.link_policy.allows(&link.destination()) =>
+			{
+				Some(html::url(l.trim()))
 			}
+			_ => None,
+		};
+		let mut html =
+			format!("<img src=\"{}\"", src.as_deref().unwrap_or("data:,"));
+		if let Some(alt) = &image.alt {
+			html.push_str(&format!(" alt=\"{}\"", html::attribute(alt)));
 		}
-		if in_task_list | in_unordered_list {
-			html.push_str("</ul>");
+		if let Some(title) = &image.title {
+			html.push_str(&format!(" title=\"{}\"", html::attribute(title)));
 		}
-		if in_ordered_list {
-			html.push_str("</ol>");
+		let (width, height) =
+			options
+				.images
+				.size(&image.source, image.width, image.height);
+		if let Some(width) = width {
+			html.push_str(&format!(" width=\"{}\"", width));
 		}
-		if html.chars().last().unwrap_or(' ') != '\n' {
-			html.push('\n');
+		if let Some(height) = height {
+			html.push_str(&format!(" height=\"{}\"", height));
 		}
-		if in_code && !matches!(token_iter.peek(), Some(Token::Code(_))) {
-			match html.chars().last().unwrap() {
-				'\n' => {}
-				_ => html.push('\n'),
-			}
-			html.push_str("</code></pre>");
+		if options.images.lazy {
+			html.push_str(" loading=\"lazy\" decoding=\"async\"");
 		}
-
-		if references.len() > 0 {
-			html.push_str("<div class=\"footnotes\" role=\"doc-endnotes\">\n");
-			html.push_str("\t<ol>\n");
-			for reference in references.iter() {
-				html.push_str("\t\t<li id=\"fn:1\" role=\"doc-endnote\">");
-				html.push_str(
-					format!(
-						"\t\t\t<p>{ref_text}<a href=\"#fnref:{ref_count}\" \
-						 class=\"reversefootnote\" \
-						 role=\"doc-backlink\">↩</a></p>",
-						ref_count = Self::sanitize_display_text(reference.0),
-						ref_text = Self::sanitize_display_text(reference.1)
-					)
-					.as_str(),
-				);
-				html.push_str("\t\t</li>");
-			}
-			html.push_str("\t</ol>\n");
-			html.push_str("</div>\n");
+		if src.is_some() {
+			html.push_str(" referrerpolicy=\"no-referrer\"");
 		}
-
-		debug!("Parsing completed");
+		html.push('>');
 		html
 	}
 
-	pub(crate) fn sanitize_display_text(source: &str) -> String {
-		source
-			.replace('&', "&amp;")
-			.replace('<', "&lt;")
-			.replace('>', "&gt;")
-			.replace('"', "&quot;")
-			.replace('\'', "&apos;")
-			.replace('[', "&lbrack;")
-			.replace(']', "&rbrack;")
-			.replace('{', "&lbrace;")
-			.replace('}', "&rbrace;")
-			.replace('|', "&mid;")
-			.replace('\\', "")
-			.replace('~', "&tilde;")
-			.replace(')', "&#41;")
-			.replace('(', "&#40;")
+	/// Starts a new line, unless the html is empty or already ends with one.
+	fn cr(html: &mut String) {
+		if !html.is_empty() && !html.ends_with('\n') {
+			html.push('\n');
+		}
+	}
+
+	/// Ends a line of a paragraph. Spaces at the end of the line are not
+	/// rendered.
+	fn push_soft_break(html: &mut String, options: &Options) {
+		html.truncate(html.trim_end_matches(' ').len());
+		match options.breaks {
+			true => html.push_str("<br />\n"),
+			false => html.push('\n'),
+		}
 	}
 }
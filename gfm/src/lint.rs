@@ -0,0 +1,462 @@
+//! Style checks in the spirit of markdownlint, run on the block structure and
+//! the inline tokens of a document.
+//!
+//! Rules can be turned off for parts of a document with html comments, which
+//! are not rendered. `<!-- smd-lint-disable -->` and
+//! `<!-- smd-lint-enable -->` affect their own line and every line after it,
+//! `<!-- smd-lint-disable-line -->` only their own line and
+//! `<!-- smd-lint-disable-next-line -->` only the line after it. Each of them
+//! may be followed by the names or codes of the rules it affects, eg.
+//! `<!-- smd-lint-disable line-length MD034 -->`, otherwise it affects all
+//! rules.
+
+use core::fmt;
+use std::{
+	collections::HashSet,
+	ops::Range,
+	str::FromStr,
+};
+
+use crate::{
+	block::{
+		self,
+		Block,
+		ListKind,
+		Text,
+	},
+	diagnostic,
+	lexer::Lexer,
+	token::Token,
+	Image,
+	Options,
+	ParseErrorAction,
+};
+
+/// A style rule, named like its markdownlint counterpart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+	/// Heading levels only increase by one level at a time
+	HeadingIncrement,
+	/// Bullet lists use the same marker
+	UlStyle,
+	/// Lines do not end with spaces, except for hard line breaks
+	NoTrailingSpaces,
+	/// Lines are not longer than [LintOptions::line_length]
+	LineLength,
+	/// No two headings have the same content
+	NoDuplicateHeading,
+	/// Urls are wrapped in angle brackets or links
+	NoBareUrls,
+	/// Links have text and a destination
+	NoEmptyLinks,
+	/// Images have alternate text
+	NoAltText,
+}
+
+impl Rule {
+	/// Every rule, in the order of their markdownlint codes.
+	pub const ALL: [Rule; 8] = [
+		Rule::HeadingIncrement,
+		Rule::UlStyle,
+		Rule::NoTrailingSpaces,
+		Rule::LineLength,
+		Rule::NoDuplicateHeading,
+		Rule::NoBareUrls,
+		Rule::NoEmptyLinks,
+		Rule::NoAltText,
+	];
+
+	/// Returns the markdownlint code of the rule, eg. `MD001`.
+	pub fn code(&self) -> &'static str {
+		match self {
+			Rule::HeadingIncrement => "MD001",
+			Rule::UlStyle => "MD004",
+			Rule::NoTrailingSpaces => "MD009",
+			Rule::LineLength => "MD013",
+			Rule::NoDuplicateHeading => "MD024",
+			Rule::NoBareUrls => "MD034",
+			Rule::NoEmptyLinks => "MD042",
+			Rule::NoAltText => "MD045",
+		}
+	}
+
+	/// Returns the markdownlint name of the rule, eg. `heading-increment`.
+	pub fn name(&self) -> &'static str {
+		match self {
+			Rule::HeadingIncrement => "heading-increment",
+			Rule::UlStyle => "ul-style",
+			Rule::NoTrailingSpaces => "no-trailing-spaces",
+			Rule::LineLength => "line-length",
+			Rule::NoDuplicateHeading => "no-duplicate-heading",
+			Rule::NoBareUrls => "no-bare-urls",
+			Rule::NoEmptyLinks => "no-empty-links",
+			Rule::NoAltText => "no-alt-text",
+		}
+	}
+}
+
+impl fmt::Display for Rule {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}/{}", self.code(), self.name())
+	}
+}
+
+impl FromStr for Rule {
+	type Err = String;
+
+	/// Parses the name or the code of a rule, ignoring case.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Rule::ALL
+			.into_iter()
+			.find(|r| {
+				r.name().eq_ignore_ascii_case(s) ||
+					r.code().eq_ignore_ascii_case(s)
+			})
+			.ok_or_else(|| format!("unknown lint rule `{}`", s))
+	}
+}
+
+/// Options that change which rules are checked and how.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintOptions {
+	/// Rules that are not checked
+	pub disabled:    Vec<Rule>,
+	/// Maximum number of characters on a line. Longer lines are fine if
+	/// there is no whitespace after the limit, eg. because of a long url.
+	pub line_length: usize,
+	/// Number of spaces that end a line with a hard line break, which are
+	/// not reported as trailing spaces. Zero reports all of them.
+	pub br_spaces:   usize,
+	/// Marker every bullet list has to use, or `None` for the marker of the
+	/// first bullet list in the document
+	pub list_marker: Option<char>,
+}
+
+impl Default for LintOptions {
+	fn default() -> Self {
+		LintOptions {
+			disabled:    Vec::new(),
+			line_length: 80,
+			br_spaces:   2,
+			list_marker: None,
+		}
+	}
+}
+
+/// A violation of a [Rule].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+	pub rule:    Rule,
+	pub message: String,
+	/// Byte range of the offending markup in the source
+	pub span:    Range<usize>,
+}
+
+impl Lint {
+	/// Returns the 1-based line and column, counted in characters, at which
+	/// the lint starts in `source`.
+	pub fn line_column(&self, source: &str) -> (usize, usize) {
+		diagnostic::line_column(source, self.span.start)
+	}
+}
+
+impl fmt::Display for Lint {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}: {}", self.rule, self.message)
+	}
+}
+
+/// Checks `source` against every rule that is not disabled and returns the
+/// violations ordered by where they start.
+pub(crate) fn lint(source: &str, options: &LintOptions) -> Vec<Lint> {
+	let (blocks, _) = block::parse(source, &Options::default());
+	let mut linter = Linter {
+		options,
+		lints: Vec::new(),
+		headings: HashSet::new(),
+		level: None,
+		list_marker: options.list_marker,
+	};
+	linter.blocks(&blocks);
+	linter.lines(source);
+
+	let line_starts: Vec<usize> = source
+		.split_inclusive('\n')
+		.scan(0, |offset, line| {
+			let start = *offset;
+			*offset += line.len();
+			Some(start)
+		})
+		.collect();
+	let disabled = disabled_rules(source);
+	let mut lints: Vec<Lint> = linter
+		.lints
+		.into_iter()
+		.filter(|lint| {
+			let line = line_starts
+				.partition_point(|&start| start <= lint.span.start)
+				.saturating_sub(1);
+			!disabled.get(line).is_some_and(|d| d[lint.rule as usize])
+		})
+		.collect();
+	lints.sort_by_key(|lint| lint.span.start);
+	lints
+}
+
+struct Linter<'o> {
+	options:     &'o LintOptions,
+	lints:       Vec<Lint>,
+	/// Content of every heading seen so far
+	headings:    HashSet<String>,
+	/// Level of the last heading
+	level:       Option<usize>,
+	/// Marker every bullet list has to use
+	list_marker: Option<char>,
+}
+
+impl Linter<'_> {
+	fn push(&mut self, rule: Rule, span: Range<usize>, message: String) {
+		if !self.options.disabled.contains(&rule) {
+			self.lints.push(Lint {
+				rule,
+				message,
+				span,
+			});
+		}
+	}
+
+	fn blocks(&mut self, blocks: &[Block]) {
+		for block in blocks.iter() {
+			match block {
+				Block::Paragraph(text) | Block::Custom(_, _, text) => {
+					self.inline(text)
+				}
+				Block::Header(level, text, _) => {
+					self.heading(*level, text);
+					self.inline(text);
+				}
+				Block::BlockQuote(inner) |
+				Block::ListItem(_, inner) |
+				Block::Footnote(_, inner) => self.blocks(inner),
+				Block::List(kind, _, items, offset) => {
+					self.list(kind, *offset);
+					self.blocks(items);
+				}
+				Block::Table(_, header, rows) => {
+					for cell in header.iter().chain(rows.iter().flatten()) {
+						self.inline(cell);
+					}
+				}
+				Block::Detail(summary, inner) => {
+					self.inline(summary);
+					self.blocks(inner);
+				}
+				Block::HorizontalRule |
+				Block::CodeBlock(..) |
+				Block::Html(_) => {}
+			}
+		}
+	}
+
+	fn heading(&mut self, level: usize, text: &Text) {
+		let span = text.source_offset(0)..text.source_offset(text.text.len());
+		if let Some(previous) = self.level.filter(|&p| level > p + 1) {
+			self.push(
+				Rule::HeadingIncrement,
+				span.clone(),
+				format!("heading level jumps from h{} to h{}", previous, level),
+			);
+		}
+		self.level = Some(level);
+		if !self.headings.insert(text.text.clone()) {
+			self.push(
+				Rule::NoDuplicateHeading,
+				span,
+				format!("duplicate heading `{}`", text.text),
+			);
+		}
+	}
+
+	fn list(&mut self, kind: &ListKind, offset: usize) {
+		let ListKind::Bullet(marker) = *kind else {
+			return;
+		};
+		match self.list_marker {
+			Some(expected) if expected != marker => self.push(
+				Rule::UlStyle,
+				offset..offset + 1,
+				format!("list marker `{}` should be `{}`", marker, expected),
+			),
+			Some(_) => {}
+			None => self.list_marker = Some(marker),
+		}
+	}
+
+	/// Checks the inline tokens of a leaf block, each with the part of the
+	/// source it was lexed from.
+	fn inline(&mut self, text: &Text) {
+		let max_nesting = Options::default().max_nesting;
+		// bare urls are only found when they are lexed as links
+		let mut lexer =
+			Lexer::new(&text.text, max_nesting, ParseErrorAction::Serialize)
+				.with_autolinks(true);
+		loop {
+			let start = lexer.offset();
+			let Some(token) = lexer.next_token() else {
+				break;
+			};
+			let end = lexer.offset();
+			let span = text.source_offset(start)..text.source_offset(end);
+			self.token(&token, &text.text[start..end], span);
+		}
+	}
+
+	fn token(&mut self, token: &Token<'_>, markup: &str, span: Range<usize>) {
+		match token {
+			Token::Link(_, None, None) if !markup.starts_with('<') => self
+				.push(
+					Rule::NoBareUrls,
+					span,
+					format!("bare url `{}`, wrap it in `<>`", markup),
+				),
+			Token::Link(url, Some(inner), _) => {
+				if url.content.is_empty() || url.content == "#" {
+					self.push(
+						Rule::NoEmptyLinks,
+						span.clone(),
+						format!("link `{}` has no destination", markup),
+					);
+				} else if inner.is_empty() {
+					self.push(
+						Rule::NoEmptyLinks,
+						span.clone(),
+						format!("link `{}` has no text", markup),
+					);
+				}
+				for token in inner.iter() {
+					if matches!(token, Token::Image(..)) {
+						self.token(token, markup, span.clone());
+					}
+				}
+			}
+			Token::Image(Image { alt, .. })
+				if alt.as_deref().is_none_or(|a| a.trim().is_empty()) =>
+			{
+				self.push(
+					Rule::NoAltText,
+					span,
+					format!("image `{}` has no alternate text", markup),
+				)
+			}
+			_ => {}
+		}
+	}
+
+	/// Checks the rules that apply to every line of the source, no matter
+	/// which block it belongs to.
+	fn lines(&mut self, source: &str) {
+		let mut offset = 0;
+		for raw in source.split_inclusive('\n') {
+			let start = offset;
+			offset += raw.len();
+			let line = raw.trim_end_matches(['\n', '\r']);
+
+			let content = line.trim_end_matches([' ', '\t']);
+			let trailing = &line[content.len()..];
+			let hard_break = !content.trim_start().is_empty() &&
+				self.options.br_spaces >= 2 &&
+				trailing.len() == self.options.br_spaces &&
+				trailing.bytes().all(|b| b == b' ');
+			if !trailing.is_empty() && !hard_break {
+				self.push(
+					Rule::NoTrailingSpaces,
+					start + content.len()..start + line.len(),
+					format!("{} trailing space(s)", trailing.len()),
+				);
+			}
+
+			let max = self.options.line_length;
+			if let Some((cut, _)) = line.char_indices().nth(max) {
+				if line[cut..].contains(char::is_whitespace) {
+					self.push(
+						Rule::LineLength,
+						start + cut..start + line.len(),
+						format!(
+							"line is {} characters long, the limit is {}",
+							line.chars().count(),
+							max
+						),
+					);
+				}
+			}
+		}
+	}
+}
+
+/// Returns which rules are disabled on every line by the `smd-lint` comments
+/// in the source.
+fn disabled_rules(source: &str) -> Vec<[bool; Rule::ALL.len()]> {
+	let mut disabled = [false; Rule::ALL.len()];
+	let mut next_line = [false; Rule::ALL.len()];
+	let mut lines = Vec::new();
+	for line in source.lines() {
+		let mut current = disabled;
+		for (rule, d) in std::mem::take(&mut next_line).iter().enumerate() {
+			current[rule] |= *d;
+		}
+		for (directive, rules) in directives(line) {
+			for rule in Rule::ALL.iter().filter(|r| rules[**r as usize]) {
+				let rule = *rule as usize;
+				match directive {
+					"disable" => {
+						disabled[rule] = true;
+						current[rule] = true;
+					}
+					"enable" => {
+						disabled[rule] = false;
+						current[rule] = false;
+					}
+					"disable-line" => current[rule] = true,
+					"disable-next-line" => next_line[rule] = true,
+					_ => {}
+				}
+			}
+		}
+		lines.push(current);
+	}
+	lines
+}
+
+/// Parses the `smd-lint` comments on a line into their directive, eg.
+/// `disable`, and the rules they affect.
+fn directives(line: &str) -> Vec<(&str, [bool; Rule::ALL.len()])> {
+	let mut directives = Vec::new();
+	let mut rest = line;
+	while let Some(start) = rest.find("<!--") {
+		// `<!-->` and `<!--->` do not close the comment they open
+		let start = start + "<!--".len();
+		let Some(end) = rest[start..].find("-->").map(|i| start + i) else {
+			break;
+		};
+		let comment = &rest[start..end];
+		rest = &rest[end + 3..];
+		let Some(body) = comment.trim().strip_prefix("smd-lint-") else {
+			continue;
+		};
+		let mut words = body.split_whitespace();
+		let Some(directive) = words.next() else {
+			continue;
+		};
+		let mut rules = [false; Rule::ALL.len()];
+		let mut any = false;
+		for rule in words.filter_map(|w| w.parse::<Rule>().ok()) {
+			rules[rule as usize] = true;
+			any = true;
+		}
+		if !any {
+			rules = [true; Rule::ALL.len()];
+		}
+		directives.push((directive, rules));
+	}
+	directives
+}
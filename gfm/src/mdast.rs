@@ -0,0 +1,795 @@
+//! [Serialize] and [Deserialize] for [Document], in the JSON shape of
+//! [mdast](https://github.com/syntax-tree/mdast), so documents can be
+//! exchanged with [remark](https://github.com/remarkjs/remark) and its
+//! plugins.
+//!
+//! Line endings within a paragraph are `\n` in `text` nodes. Headings keep
+//! their id in `data.hProperties.id`, like remark-rehype expects it, and
+//! `<details>` blocks become `details` nodes whose first child is a
+//! `summary`. Nodes of syntax extensions become `textDirective` and
+//! `leafDirective` nodes, like those of remark-directive, that keep their
+//! value in `attributes.value`. Raw `html` is kept as it is written and
+//! sanitized when it is rendered. Positions of nodes are ignored.
+//!
+//! `linkReference` and `imageReference` nodes of imported documents are
+//! resolved against their `definition` and become links and images; those
+//! without a definition become their text.
+
+use std::collections::BTreeMap;
+
+use serde::{
+	de,
+	Deserialize,
+	Deserializer,
+	Serialize,
+	Serializer,
+};
+
+use crate::{
+	ast::{
+		Block,
+		Custom,
+		Document,
+		Image,
+		Inline,
+		ListItem,
+	},
+	block::{
+		Alignment,
+		ListKind,
+		TaskBox,
+	},
+};
+
+/// A node of the mdast syntax tree, limited to what a [Document] can hold.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum Node {
+	Root {
+		children: Vec<Node>,
+	},
+	Paragraph {
+		#[serde(default)]
+		children: Vec<Node>,
+	},
+	Heading {
+		depth:    usize,
+		#[serde(default)]
+		children: Vec<Node>,
+		#[serde(default, skip_serializing_if = "Option::is_none")]
+		data:     Option<Data>,
+	},
+	ThematicBreak {},
+	Code {
+		#[serde(default)]
+		lang:  Option<String>,
+		#[serde(default)]
+		meta:  Option<String>,
+		value: String,
+	},
+	Blockquote {
+		#[serde(default)]
+		children: Vec<Node>,
+	},
+	List {
+		#[serde(default)]
+		ordered:  bool,
+		#[serde(default)]
+		start:    Option<usize>,
+		#[serde(default)]
+		spread:   bool,
+		#[serde(default)]
+		children: Vec<Node>,
+	},
+	ListItem {
+		#[serde(default)]
+		checked:  Option<bool>,
+		#[serde(default)]
+		spread:   bool,
+		#[serde(default)]
+		children: Vec<Node>,
+	},
+	Table {
+		#[serde(default)]
+		align:    Vec<Option<Align>>,
+		#[serde(default)]
+		children: Vec<Node>,
+	},
+	TableRow {
+		#[serde(default)]
+		children: Vec<Node>,
+	},
+	TableCell {
+		#[serde(default)]
+		children: Vec<Node>,
+	},
+	Details {
+		#[serde(default)]
+		children: Vec<Node>,
+	},
+	Summary {
+		#[serde(default)]
+		children: Vec<Node>,
+	},
+	FootnoteDefinition {
+		identifier: String,
+		#[serde(default)]
+		label:      Option<String>,
+		#[serde(default)]
+		children:   Vec<Node>,
+	},
+	Definition {
+		identifier: String,
+		url:        String,
+		#[serde(default)]
+		title:      Option<String>,
+	},
+	Html {
+		value: String,
+	},
+	Text {
+		value: String,
+	},
+	Emphasis {
+		#[serde(default)]
+		children: Vec<Node>,
+	},
+	Strong {
+		#[serde(default)]
+		children: Vec<Node>,
+	},
+	Delete {
+		#[serde(default)]
+		children: Vec<Node>,
+	},
+	InlineCode {
+		value: String,
+	},
+	Break {},
+	Link {
+		url:      String,
+		#[serde(default)]
+		title:    Option<String>,
+		#[serde(default)]
+		children: Vec<Node>,
+	},
+	Image {
+		url:   String,
+		#[serde(default)]
+		title: Option<String>,
+		#[serde(default)]
+		alt:   Option<String>,
+		#[serde(default, skip_serializing_if = "Option::is_none")]
+		data:  Option<Data>,
+	},
+	FootnoteReference {
+		identifier: String,
+		#[serde(default)]
+		label:      Option<String>,
+	},
+	LinkReference {
+		identifier: String,
+		#[serde(default)]
+		children:   Vec<Node>,
+	},
+	ImageReference {
+		identifier: String,
+		#[serde(default)]
+		alt:        Option<String>,
+	},
+	TextDirective {
+		name:       String,
+		#[serde(default)]
+		attributes: BTreeMap<String, String>,
+		#[serde(default)]
+		children:   Vec<Node>,
+	},
+	LeafDirective {
+		name:       String,
+		#[serde(default)]
+		attributes: BTreeMap<String, String>,
+		#[serde(default)]
+		children:   Vec<Node>,
+	},
+}
+
+/// Data of a node that is used when it is rendered as html.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Data {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	h_properties: Option<HProperties>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HProperties {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	id:     Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	width:  Option<u32>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	height: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Align {
+	Left,
+	Right,
+	Center,
+}
+
+impl Serialize for Document {
+	fn serialize<S: Serializer>(
+		&self,
+		serializer: S,
+	) -> Result<S::Ok, S::Error> {
+		Node::Root {
+			children: self.blocks.iter().map(from_block).collect(),
+		}
+		.serialize(serializer)
+	}
+}
+
+impl<'de> Deserialize<'de> for Document {
+	fn deserialize<D: Deserializer<'de>>(
+		deserializer: D,
+	) -> Result<Self, D::Error> {
+		match Node::deserialize(deserializer)? {
+			Node::Root { mut children } => {
+				let mut definitions = BTreeMap::new();
+				take_definitions(&mut children, &mut definitions);
+				resolve_references(&mut children, &definitions);
+				Ok(Document {
+					blocks: to_blocks(children).map_err(de::Error::custom)?,
+				})
+			}
+			node => Err(de::Error::custom(format!(
+				"expected a `root` node, found {}",
+				node.name()
+			))),
+		}
+	}
+}
+
+impl Node {
+	/// Name of the node in error messages, eg. "`tableRow`".
+	fn name(&self) -> String {
+		let name = match self {
+			Node::Root { .. } => "root",
+			Node::Paragraph { .. } => "paragraph",
+			Node::Heading { .. } => "heading",
+			Node::ThematicBreak {} => "thematicBreak",
+			Node::Code { .. } => "code",
+			Node::Blockquote { .. } => "blockquote",
+			Node::List { .. } => "list",
+			Node::ListItem { .. } => "listItem",
+			Node::Table { .. } => "table",
+			Node::TableRow { .. } => "tableRow",
+			Node::TableCell { .. } => "tableCell",
+			Node::Details { .. } => "details",
+			Node::Summary { .. } => "summary",
+			Node::FootnoteDefinition { .. } => "footnoteDefinition",
+			Node::Definition { .. } => "definition",
+			Node::Html { .. } => "html",
+			Node::Text { .. } => "text",
+			Node::Emphasis { .. } => "emphasis",
+			Node::Strong { .. } => "strong",
+			Node::Delete { .. } => "delete",
+			Node::InlineCode { .. } => "inlineCode",
+			Node::Break {} => "break",
+			Node::Link { .. } => "link",
+			Node::Image { .. } => "image",
+			Node::FootnoteReference { .. } => "footnoteReference",
+			Node::LinkReference { .. } => "linkReference",
+			Node::ImageReference { .. } => "imageReference",
+			Node::TextDirective { .. } => "textDirective",
+			Node::LeafDirective { .. } => "leafDirective",
+		};
+		format!("`{}`", name)
+	}
+
+	/// Children of the node, if it can have any.
+	fn children_mut(&mut self) -> Option<&mut Vec<Node>> {
+		match self {
+			Node::Root { children } |
+			Node::Paragraph { children } |
+			Node::Heading { children, .. } |
+			Node::Blockquote { children } |
+			Node::List { children, .. } |
+			Node::ListItem { children, .. } |
+			Node::Table { children, .. } |
+			Node::TableRow { children } |
+			Node::TableCell { children } |
+			Node::Details { children } |
+			Node::Summary { children } |
+			Node::FootnoteDefinition { children, .. } |
+			Node::Emphasis { children } |
+			Node::Strong { children } |
+			Node::Delete { children } |
+			Node::Link { children, .. } |
+			Node::LinkReference { children, .. } |
+			Node::TextDirective { children, .. } |
+			Node::LeafDirective { children, .. } => Some(children),
+			_ => None,
+		}
+	}
+}
+
+/// Removes the `definition` nodes of a document and collects their url and
+/// title by identifier. Like in markdown, the first definition of an
+/// identifier wins.
+fn take_definitions(
+	nodes: &mut Vec<Node>,
+	definitions: &mut BTreeMap<String, (String, Option<String>)>,
+) {
+	nodes.retain_mut(|node| match node {
+		Node::Definition {
+			identifier,
+			url,
+			title,
+		} => {
+			definitions
+				.entry(std::mem::take(identifier))
+				.or_insert_with(|| (std::mem::take(url), title.take()));
+			false
+		}
+		node => {
+			if let Some(children) = node.children_mut() {
+				take_definitions(children, definitions);
+			}
+			true
+		}
+	});
+}
+
+/// Turns the references that have a definition into links and images.
+fn resolve_references(
+	nodes: &mut [Node],
+	definitions: &BTreeMap<String, (String, Option<String>)>,
+) {
+	for node in nodes.iter_mut() {
+		if let Some(children) = node.children_mut() {
+			resolve_references(children, definitions);
+		}
+		match node {
+			Node::LinkReference {
+				identifier,
+				children,
+			} => {
+				if let Some((url, title)) = definitions.get(identifier) {
+					*node = Node::Link {
+						url:      url.clone(),
+						title:    title.clone(),
+						children: std::mem::take(children),
+					};
+				}
+			}
+			Node::ImageReference { identifier, alt } => {
+				if let Some((url, title)) = definitions.get(identifier) {
+					*node = Node::Image {
+						url:   url.clone(),
+						title: title.clone(),
+						alt:   alt.take(),
+						data:  None,
+					};
+				}
+			}
+			_ => {}
+		}
+	}
+}
+
+fn from_blocks(blocks: &[Block]) -> Vec<Node> {
+	blocks.iter().map(from_block).collect()
+}
+
+fn from_block(block: &Block) -> Node {
+	match block {
+		Block::Paragraph(inlines) => Node::Paragraph {
+			children: from_inlines(inlines),
+		},
+		Block::Header(level, inlines, id) => Node::Heading {
+			depth:    *level,
+			children: from_inlines(inlines),
+			data:     id.as_ref().map(|id| Data {
+				h_properties: Some(HProperties {
+					id: Some(id.clone()),
+					..Default::default()
+				}),
+			}),
+		},
+		Block::HorizontalRule => Node::ThematicBreak {},
+		Block::Html(source) => Node::Html {
+			value: source.clone(),
+		},
+		Block::CodeBlock(code, info) => {
+			let mut info = info.trim().splitn(2, char::is_whitespace);
+			Node::Code {
+				lang:  info
+					.next()
+					.filter(|l| !l.is_empty())
+					.map(str::to_string),
+				meta:  info.next().map(|m| m.trim_start().to_string()),
+				value: code.strip_suffix('\n').unwrap_or(code).to_string(),
+			}
+		}
+		Block::BlockQuote(blocks) => Node::Blockquote {
+			children: from_blocks(blocks),
+		},
+		Block::List(kind, tight, items) => Node::List {
+			ordered:  matches!(kind, ListKind::Ordered(..)),
+			start:    match kind {
+				ListKind::Ordered(start, _) => Some(*start),
+				ListKind::Bullet(_) => None,
+			},
+			spread:   !tight,
+			children: items
+				.iter()
+				.map(|item| Node::ListItem {
+					checked:  item.task.map(|t| t == TaskBox::Checked),
+					spread:   !tight,
+					children: from_blocks(&item.children),
+				})
+				.collect(),
+		},
+		Block::Table(alignments, headings, rows) => Node::Table {
+			align:    alignments
+				.iter()
+				.map(|a| {
+					a.as_ref().map(|a| match a {
+						Alignment::Left => Align::Left,
+						Alignment::Right => Align::Right,
+						Alignment::Center => Align::Center,
+					})
+				})
+				.collect(),
+			children: std::iter::once(headings)
+				.chain(rows.iter())
+				.map(|row| Node::TableRow {
+					children: row
+						.iter()
+						.map(|cell| Node::TableCell {
+							children: from_inlines(cell),
+						})
+						.collect(),
+				})
+				.collect(),
+		},
+		Block::Detail(summary, blocks) => Node::Details {
+			children: std::iter::once(Node::Summary {
+				children: from_inlines(summary),
+			})
+			.chain(blocks.iter().map(from_block))
+			.collect(),
+		},
+		Block::Footnote(ref_id, blocks) => Node::FootnoteDefinition {
+			identifier: ref_id.clone(),
+			label:      Some(ref_id.clone()),
+			children:   from_blocks(blocks),
+		},
+		Block::Custom(node) => Node::LeafDirective {
+			name:       node.name.clone(),
+			attributes: attributes(node),
+			children:   from_inlines(&node.children),
+		},
+	}
+}
+
+/// Converts inline content. Text and the line endings between it are
+/// joined into a single `text` node.
+fn from_inlines(inlines: &[Inline]) -> Vec<Node> {
+	let mut nodes = Vec::new();
+	for inline in inlines.iter() {
+		let text = match inline {
+			Inline::Text(t) => t.as_str(),
+			Inline::SoftBreak => "\n",
+			inline => {
+				nodes.push(from_inline(inline));
+				continue;
+			}
+		};
+		match nodes.last_mut() {
+			Some(Node::Text { value }) => value.push_str(text),
+			_ => nodes.push(Node::Text {
+				value: text.to_string(),
+			}),
+		}
+	}
+	nodes
+}
+
+fn from_inline(inline: &Inline) -> Node {
+	match inline {
+		Inline::Text(t) => Node::Text { value: t.clone() },
+		Inline::SoftBreak => Node::Text {
+			value: "\n".to_string(),
+		},
+		Inline::Emphasis(inner) => Node::Emphasis {
+			children: from_inlines(inner),
+		},
+		Inline::Strong(inner) => Node::Strong {
+			children: from_inlines(inner),
+		},
+		Inline::Strikethrough(t) => Node::Delete {
+			children: vec![Node::Text { value: t.clone() }],
+		},
+		Inline::Code(t) => Node::InlineCode { value: t.clone() },
+		Inline::Link(url, inner, title) => Node::Link {
+			url:      url.clone(),
+			title:    title.clone(),
+			children: from_inlines(inner),
+		},
+		Inline::Autolink(url, text) => Node::Link {
+			url:      url.clone(),
+			title:    None,
+			children: vec![Node::Text {
+				value: text.clone(),
+			}],
+		},
+		Inline::Image(image) => Node::Image {
+			url:   image.source.clone(),
+			title: image.title.clone(),
+			alt:   image.alt.clone(),
+			data:  (image.width.is_some() || image.height.is_some()).then(
+				|| Data {
+					h_properties: Some(HProperties {
+						width: image.width,
+						height: image.height,
+						..Default::default()
+					}),
+				},
+			),
+		},
+		Inline::FootnoteReference(ref_id) => Node::FootnoteReference {
+			identifier: ref_id.clone(),
+			label:      Some(ref_id.clone()),
+		},
+		Inline::LineBreak => Node::Break {},
+		Inline::Html(source) => Node::Html {
+			value: source.clone(),
+		},
+		Inline::Custom(node) => Node::TextDirective {
+			name:       node.name.clone(),
+			attributes: attributes(node),
+			children:   from_inlines(&node.children),
+		},
+	}
+}
+
+/// Attributes of the directive a custom node becomes.
+fn attributes(node: &Custom) -> BTreeMap<String, String> {
+	match node.value.is_empty() {
+		true => BTreeMap::new(),
+		false => BTreeMap::from([("value".to_string(), node.value.clone())]),
+	}
+}
+
+/// Turns the parts of a directive into a custom node.
+fn to_custom(
+	name: String,
+	mut attributes: BTreeMap<String, String>,
+	children: Vec<Node>,
+) -> Result<Custom, String> {
+	Ok(Custom {
+		name,
+		value: attributes.remove("value").unwrap_or_default(),
+		children: to_inlines(children)?,
+	})
+}
+
+fn to_blocks(nodes: Vec<Node>) -> Result<Vec<Block>, String> {
+	nodes.into_iter().map(to_block).collect()
+}
+
+fn to_block(node: Node) -> Result<Block, String> {
+	Ok(match node {
+		Node::Paragraph { children } => Block::Paragraph(to_inlines(children)?),
+		Node::Heading {
+			depth,
+			children,
+			data,
+		} => {
+			if !(1..=6).contains(&depth) {
+				return Err(format!("heading depth {} is not 1 to 6", depth));
+			}
+			let id = data.and_then(|d| d.h_properties).and_then(|p| p.id);
+			Block::Header(depth, to_inlines(children)?, id)
+		}
+		Node::ThematicBreak {} => Block::HorizontalRule,
+		Node::Code { lang, meta, value } => {
+			let info = match (lang, meta) {
+				(Some(lang), Some(meta)) => format!("{} {}", lang, meta),
+				(Some(lang), None) => lang,
+				(None, _) => String::new(),
+			};
+			match value.is_empty() {
+				true => Block::CodeBlock(value, info),
+				false => Block::CodeBlock(value + "\n", info),
+			}
+		}
+		Node::Blockquote { children } => {
+			Block::BlockQuote(to_blocks(children)?)
+		}
+		Node::List {
+			ordered,
+			start,
+			spread,
+			children,
+		} => Block::List(
+			match ordered {
+				true => ListKind::Ordered(start.unwrap_or(1), '.'),
+				false => ListKind::Bullet('-'),
+			},
+			!spread,
+			children
+				.into_iter()
+				.map(|node| match node {
+					Node::ListItem {
+						checked, children, ..
+					} => Ok(ListItem {
+						task:     checked.map(|c| match c {
+							true => TaskBox::Checked,
+							false => TaskBox::Unchecked,
+						}),
+						children: to_blocks(children)?,
+					}),
+					node => {
+						Err(format!("{} is not allowed in a list", node.name()))
+					}
+				})
+				.collect::<Result<_, _>>()?,
+		),
+		Node::Table { align, children } => {
+			let mut rows = children.into_iter().map(|node| match node {
+				Node::TableRow { children } => children
+					.into_iter()
+					.map(|node| match node {
+						Node::TableCell { children } => to_inlines(children),
+						node => Err(format!(
+							"{} is not allowed in a table row",
+							node.name()
+						)),
+					})
+					.collect::<Result<Vec<_>, _>>(),
+				node => {
+					Err(format!("{} is not allowed in a table", node.name()))
+				}
+			});
+			let headings = rows.next().transpose()?.unwrap_or_default();
+			let rows = rows.collect::<Result<_, _>>()?;
+			let alignments = (0..headings.len())
+				.map(|i| {
+					align.get(i).and_then(Option::as_ref).map(|a| match a {
+						Align::Left => Alignment::Left,
+						Align::Right => Alignment::Right,
+						Align::Center => Alignment::Center,
+					})
+				})
+				.collect();
+			Block::Table(alignments, headings, rows)
+		}
+		Node::Details { children } => {
+			let mut children = children.into_iter().peekable();
+			let summary =
+				match children.next_if(|n| matches!(n, Node::Summary { .. })) {
+					Some(Node::Summary { children }) => to_inlines(children)?,
+					_ => Vec::new(),
+				};
+			Block::Detail(summary, to_blocks(children.collect())?)
+		}
+		Node::FootnoteDefinition {
+			identifier,
+			label,
+			children,
+		} => Block::Footnote(label.unwrap_or(identifier), to_blocks(children)?),
+		Node::Html { value } => Block::Html(value),
+		Node::LeafDirective {
+			name,
+			attributes,
+			children,
+		} => Block::Custom(to_custom(name, attributes, children)?),
+		node => {
+			return Err(format!("{} is not allowed as a block", node.name()))
+		}
+	})
+}
+
+fn to_inlines(nodes: Vec<Node>) -> Result<Vec<Inline>, String> {
+	let mut inlines = Vec::new();
+	for node in nodes.into_iter() {
+		match node {
+			Node::Text { value } => {
+				for (i, line) in value.split('\n').enumerate() {
+					if i > 0 {
+						inlines.push(Inline::SoftBreak);
+					}
+					if !line.is_empty() {
+						inlines.push(Inline::Text(line.to_string()));
+					}
+				}
+			}
+			Node::LinkReference { children, .. } => {
+				inlines.extend(to_inlines(children)?)
+			}
+			Node::ImageReference { alt, .. } => {
+				inlines.extend(alt.map(Inline::Text))
+			}
+			node => inlines.push(to_inline(node)?),
+		}
+	}
+	Ok(inlines)
+}
+
+fn to_inline(node: Node) -> Result<Inline, String> {
+	Ok(match node {
+		Node::Text { value } => Inline::Text(value),
+		Node::Html { value } => Inline::Html(value),
+		Node::Emphasis { children } => Inline::Emphasis(to_inlines(children)?),
+		Node::Strong { children } => Inline::Strong(to_inlines(children)?),
+		Node::Delete { children } => {
+			Inline::Strikethrough(plain_text(&to_inlines(children)?))
+		}
+		Node::InlineCode { value } => Inline::Code(value),
+		Node::Break {} => Inline::LineBreak,
+		Node::Link {
+			url,
+			title,
+			children,
+		} => Inline::Link(url, to_inlines(children)?, title),
+		Node::Image {
+			url,
+			title,
+			alt,
+			data,
+		} => {
+			let properties = data.and_then(|d| d.h_properties);
+			Inline::Image(Image {
+				source: url,
+				alt,
+				title,
+				width: properties.as_ref().and_then(|p| p.width),
+				height: properties.as_ref().and_then(|p| p.height),
+			})
+		}
+		Node::FootnoteReference { identifier, label } => {
+			Inline::FootnoteReference(label.unwrap_or(identifier))
+		}
+		Node::TextDirective {
+			name,
+			attributes,
+			children,
+		} => Inline::Custom(to_custom(name, attributes, children)?),
+		node => {
+			return Err(format!(
+				"{} is not allowed as inline content",
+				node.name()
+			))
+		}
+	})
+}
+
+/// Concatenates the text of inline content without any markup.
+fn plain_text(inlines: &[Inline]) -> String {
+	let mut text = String::new();
+	for inline in inlines.iter() {
+		match inline {
+			Inline::Text(t) | Inline::Code(t) | Inline::Strikethrough(t) => {
+				text.push_str(t)
+			}
+			Inline::Autolink(_, t) => text.push_str(t),
+			Inline::Emphasis(inner) |
+			Inline::Strong(inner) |
+			Inline::Link(_, inner, _) |
+			Inline::Custom(Custom {
+				children: inner, ..
+			}) => text.push_str(&plain_text(inner)),
+			Inline::Image(Image { alt: Some(alt), .. }) => text.push_str(alt),
+			Inline::LineBreak | Inline::SoftBreak => text.push('\n'),
+			Inline::Image(Image { alt: None, .. }) |
+			Inline::FootnoteReference(_) |
+			Inline::Html(_) => {}
+		}
+	}
+	text
+}
@@ -0,0 +1,71 @@
+use crate::{
+	Extensions,
+	ImageOptions,
+	LinkPolicy,
+	Sanitizer,
+	UrlResolver,
+};
+
+/// Options that change how markdown is rendered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+	/// Renders every soft line break inside a paragraph as a hard line break
+	/// (`<br />`), like GitHub does for comments and issues.
+	pub breaks:         bool,
+	/// Links `www.` and `http(s)://` urls and email addresses that are not
+	/// wrapped in `<>`, like the autolinks extension of GFM.
+	pub autolinks:      bool,
+	/// Maximum depth of nested blocks, eg. block quotes and list items, and
+	/// of nested emphasis and links within a paragraph. Anything nested
+	/// deeper is rendered as text.
+	pub max_nesting:    usize,
+	/// Maximum size of the source in bytes. Only enforced by
+	/// [Parser::try_render](crate::Parser::try_render).
+	pub max_input_size: usize,
+	/// What to do with inline markup that cannot be parsed, eg. a link
+	/// without its closing parenthesis.
+	pub on_parse_error: ParseErrorAction,
+	/// Syntax that is parsed in addition to GFM, eg. `==highlight==`.
+	pub extensions:     Extensions,
+	/// Raw html that is kept, the rest of it is escaped or removed.
+	pub sanitizer:      Sanitizer,
+	/// Rewrites the destinations of links, images and autolinks, eg. with a
+	/// [UrlRewriter](crate::UrlRewriter).
+	pub url_resolver:   UrlResolver,
+	/// Schemes that destinations may use and the attributes of internal
+	/// and external links, eg. `target="_blank"`.
+	pub link_policy:    LinkPolicy,
+	/// Lazy loading, figures and sizes of images.
+	pub images:         ImageOptions,
+}
+
+/// What to do with inline markup that cannot be parsed, ie. everything that
+/// is reported as a [Diagnostic](crate::Diagnostic) of the inline phase.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseErrorAction {
+	/// [Parser::try_render](crate::Parser::try_render) fails with the first
+	/// of them. The other render functions keep them as text.
+	Abort,
+	/// Leaves them out of the output.
+	Skip,
+	/// Keeps them as text.
+	#[default]
+	Serialize,
+}
+
+impl Default for Options {
+	fn default() -> Self {
+		Options {
+			breaks:         false,
+			autolinks:      false,
+			max_nesting:    64,
+			max_input_size: 16 * 1024 * 1024,
+			on_parse_error: ParseErrorAction::default(),
+			extensions:     Extensions::default(),
+			sanitizer:      Sanitizer::default(),
+			url_resolver:   UrlResolver::default(),
+			link_policy:    LinkPolicy::default(),
+			images:         ImageOptions::default(),
+		}
+	}
+}
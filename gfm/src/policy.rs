@@ -0,0 +1,167 @@
+//! Which links are rendered and the attributes they get, registered in
+//! [Options::link_policy](crate::Options).
+//!
+//! Destinations that are never allowed, eg. `javascript:`, are refused
+//! before the policy is asked.
+
+use std::collections::BTreeSet;
+
+use crate::{
+	html,
+	resolve,
+};
+
+/// Attributes added to the `<a>` tag of a link.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkAttributes {
+	/// Browsing context the link opens in, eg. `_blank` for a new tab
+	pub target: Option<String>,
+	/// Relationship to the linked page, eg. `noopener noreferrer nofollow`
+	pub rel:    Option<String>,
+}
+
+/// Schemes that links, autolinks and images may use, and the attributes of
+/// links within and outside of the site. Everything is allowed and no
+/// attributes are added by default.
+///
+/// Relative links and those to an internal host are internal. Other links
+/// with a host are external. Links without a host, eg. `mailto:`, are
+/// neither and get no attributes.
+///
+/// ### Example
+///
+/// ```rust
+/// use gfm::LinkAttributes;
+/// use gfm::LinkPolicy;
+/// use gfm::Options;
+/// use gfm::Parser;
+///
+/// let options = Options {
+/// 	link_policy: LinkPolicy::default()
+/// 		.schemes(&["http", "https", "mailto"])
+/// 		.internal_host("docs.x.y")
+/// 		.external(LinkAttributes {
+/// 			target: Some("_blank".to_string()),
+/// 			rel:    Some("noopener noreferrer nofollow".to_string()),
+/// 		}),
+/// 	..Default::default()
+/// };
+/// let html = Parser::render_with_options(
+/// 	"[a](https://docs.x.y/a) [b](https://z/b) [c](ftp://z/c)",
+/// 	&options,
+/// );
+/// assert_eq!(
+/// 	html,
+/// 	"<p><a href=\"https://docs.x.y/a\">a</a> <a href=\"https://z/b\" \
+/// 	 target=\"_blank\" rel=\"noopener noreferrer nofollow\">b</a> c</p>\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkPolicy {
+	/// Allowed schemes in lowercase, or `None` to allow every scheme
+	schemes:        Option<BTreeSet<String>>,
+	/// Hosts in lowercase whose links are internal
+	internal_hosts: BTreeSet<String>,
+	internal:       LinkAttributes,
+	external:       LinkAttributes,
+}
+
+impl LinkPolicy {
+	/// Allows only the given schemes, eg. `https`. Relative urls are always
+	/// allowed. Can be called again to allow further schemes.
+	pub fn schemes(mut self, schemes: &[&str]) -> Self {
+		self.schemes
+			.get_or_insert_with(BTreeSet::new)
+			.extend(schemes.iter().map(|s| s.to_ascii_lowercase()));
+		self
+	}
+
+	/// Treats links to `host`, eg. `docs.example.com`, as internal.
+	pub fn internal_host(mut self, host: &str) -> Self {
+		self.internal_hosts.insert(host.to_ascii_lowercase());
+		self
+	}
+
+	/// Sets the attributes of internal links.
+	pub fn internal(mut self, attributes: LinkAttributes) -> Self {
+		self.internal = attributes;
+		self
+	}
+
+	/// Sets the attributes of external links.
+	pub fn external(mut self, attributes: LinkAttributes) -> Self {
+		self.external = attributes;
+		self
+	}
+
+	/// Checks whether the scheme of a url is allowed.
+	pub(crate) fn allows(&self, url: &str) -> bool {
+		match (&self.schemes, resolve::scheme(url)) {
+			(Some(schemes), Some(scheme)) => {
+				schemes.contains(&scheme.to_ascii_lowercase())
+			}
+			// Protocol relative urls use the scheme of the page
+			(Some(schemes), None) if url.starts_with("//") => {
+				schemes.contains("https") || schemes.contains("http")
+			}
+			_ => true,
+		}
+	}
+
+	/// Renders the attributes of a link to `url`, each with a leading space.
+	pub(crate) fn attributes(&self, url: &str) -> String {
+		let attributes = match resolve::host(url) {
+			Some(host) if self.internal_hosts.contains(&host) => &self.internal,
+			Some(_) => &self.external,
+			None if resolve::scheme(url).is_none() => &self.internal,
+			None => return String::new(),
+		};
+		let mut rendered = String::new();
+		if let Some(target) = &attributes.target {
+			rendered
+				.push_str(&format!(" target=\"{}\"", html::attribute(target)));
+		}
+		if let Some(rel) = &attributes.rel {
+			rendered.push_str(&format!(" rel=\"{}\"", html::attribute(rel)));
+		}
+		rendered
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn schemes_are_allowed_case_insensitively() {
+		let policy = LinkPolicy::default().schemes(&["HTTPS", "mailto"]);
+		assert!(policy.allows("https://x.y"));
+		assert!(policy.allows("HTTPS://x.y"));
+		assert!(policy.allows("MailTo:a@x.y"));
+		assert!(policy.allows("a/b.html"));
+		assert!(policy.allows("//x.y/a"));
+		assert!(!policy.allows("http://x.y"));
+		assert!(!policy.allows("irc://x.y"));
+		assert!(LinkPolicy::default().allows("irc://x.y"));
+	}
+
+	#[test]
+	fn links_are_internal_or_external_by_host() {
+		let attributes = |target: &str| LinkAttributes {
+			target: Some(target.to_string()),
+			rel:    None,
+		};
+		let policy = LinkPolicy::default()
+			.internal_host("Docs.X.Y")
+			.internal(attributes("_self"))
+			.external(attributes("_blank"));
+		let target = |url| policy.attributes(url);
+		assert_eq!(target("https://docs.x.y/a"), " target=\"_self\"");
+		assert_eq!(target("https://user@DOCS.x.y:8080"), " target=\"_self\"");
+		assert_eq!(target("../a.html#b"), " target=\"_self\"");
+		assert_eq!(target("#b"), " target=\"_self\"");
+		assert_eq!(target("https://x.y/a"), " target=\"_blank\"");
+		assert_eq!(target("//docs.x.y.z/a"), " target=\"_blank\"");
+		assert_eq!(target("mailto:a@docs.x.y"), "");
+	}
+}
@@ -0,0 +1,372 @@
+//! Rewriting of the destinations of links, images and autolinks before they
+//! are rendered, registered in [Options::url_resolver](crate::Options).
+//!
+//! The resolved url is checked like any other destination, so a resolver
+//! cannot make the renderer write a `javascript:` link.
+
+use std::{
+	borrow::Cow,
+	fmt,
+	sync::Arc,
+};
+
+/// What a url is the destination of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UrlKind {
+	/// `[text](url)`
+	Link,
+	/// `![alt](url)`
+	Image,
+	/// `<https://x.y>` or `https://x.y`
+	Autolink,
+}
+
+/// Rewrites urls, eg. relative to where the document is published.
+///
+/// Closures that take the url and its kind implement it as well.
+///
+/// ### Example
+///
+/// ```rust
+/// use gfm::Options;
+/// use gfm::Parser;
+/// use gfm::UrlKind;
+/// use gfm::UrlResolver;
+///
+/// let options = Options {
+/// 	url_resolver: UrlResolver::new(|url: &str, kind| match kind {
+/// 		UrlKind::Image => Some(format!("https://cdn.x.y/{}", url)),
+/// 		_ => None,
+/// 	}),
+/// 	..Default::default()
+/// };
+/// let html = Parser::render_with_options("[a](b.md) ![c](d.png)", &options);
+/// assert_eq!(
+/// 	html,
+/// 	"<p><a href=\"b.md\">a</a> <img src=\"https://cdn.x.y/d.png\" \
+/// 	 alt=\"c\" referrerpolicy=\"no-referrer\"></p>\n"
+/// );
+/// ```
+pub trait ResolveUrl: Send + Sync {
+	/// Returns the url to render instead of `url`, which is the destination
+	/// as it is written, or `None` to keep it.
+	fn resolve(&self, url: &str, kind: UrlKind) -> Option<String>;
+}
+
+impl<F> ResolveUrl for F
+where
+	F: Fn(&str, UrlKind) -> Option<String> + Send + Sync,
+{
+	fn resolve(&self, url: &str, kind: UrlKind) -> Option<String> {
+		self(url, kind)
+	}
+}
+
+/// The resolver urls are rewritten with, none by default.
+#[derive(Clone, Default)]
+pub struct UrlResolver {
+	resolver: Option<Arc<dyn ResolveUrl>>,
+}
+
+impl UrlResolver {
+	pub fn new(resolver: impl ResolveUrl + 'static) -> Self {
+		UrlResolver {
+			resolver: Some(Arc::new(resolver)),
+		}
+	}
+
+	/// Returns the url to render, which is `url` unless it was rewritten.
+	pub(crate) fn resolve<'a>(
+		&self,
+		url: &'a str,
+		kind: UrlKind,
+	) -> Cow<'a, str> {
+		match self.resolver.as_ref().and_then(|r| r.resolve(url, kind)) {
+			Some(resolved) => Cow::Owned(resolved),
+			None => Cow::Borrowed(url),
+		}
+	}
+}
+
+impl fmt::Debug for UrlResolver {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("UrlResolver")
+			.field("resolver", &self.resolver.as_ref().map(|_| ".."))
+			.finish()
+	}
+}
+
+/// Resolvers are equal if they are the very same one.
+impl PartialEq for UrlResolver {
+	fn eq(&self, other: &Self) -> bool {
+		match (&self.resolver, &other.resolver) {
+			(Some(a), Some(b)) => Arc::ptr_eq(a, b),
+			(a, b) => a.is_none() && b.is_none(),
+		}
+	}
+}
+
+impl Eq for UrlResolver {}
+
+/// Rewrites urls for the place a document is published at, eg. a website
+/// built from the markdown of a repository.
+///
+/// Absolute urls into a repository are made relative to the document first,
+/// then links to
+/// markdown files are pointed at their html, and relative urls are finally
+/// resolved against the base url. Urls of other sites and links within the
+/// document, eg. `#usage`, are kept.
+///
+/// ### Example
+///
+/// ```rust
+/// use gfm::Options;
+/// use gfm::Parser;
+/// use gfm::UrlResolver;
+/// use gfm::UrlRewriter;
+///
+/// let rewriter = UrlRewriter::default()
+/// 	.base("https://docs.x.y/")
+/// 	.markdown_to_html(true)
+/// 	.repository("https://github.com/x/y/blob/main/")
+/// 	.document("docs/guide.md");
+/// let options = Options {
+/// 	url_resolver: UrlResolver::new(rewriter),
+/// 	..Default::default()
+/// };
+/// let html = Parser::render_with_options(
+/// 	"[a](https://github.com/x/y/blob/main/docs/a.md#b)",
+/// 	&options,
+/// );
+/// assert_eq!(html, "<p><a href=\"https://docs.x.y/a.html#b\">a</a></p>\n");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UrlRewriter {
+	base:             Option<String>,
+	markdown_to_html: bool,
+	repositories:     Vec<String>,
+	directory:        String,
+}
+
+impl UrlRewriter {
+	/// Resolves relative urls against `url`. Urls starting with `/` are
+	/// resolved against its origin.
+	pub fn base(mut self, url: &str) -> Self {
+		self.base = Some(url.to_string());
+		self
+	}
+
+	/// Points relative links to `.md` files at the `.html` file of the same
+	/// name. Images are kept.
+	pub fn markdown_to_html(mut self, enabled: bool) -> Self {
+		self.markdown_to_html = enabled;
+		self
+	}
+
+	/// Makes absolute urls starting with `url`, eg.
+	/// `https://github.com/owner/repo/blob/main/`, relative to it.
+	pub fn repository(mut self, url: &str) -> Self {
+		let url = url.trim_end_matches('/');
+		self.repositories.push(format!("{}/", url));
+		self
+	}
+
+	/// Path of the document in the repository, eg. `docs/guide.md`, whose
+	/// directory urls into the repository are made relative to. Without it,
+	/// the document is taken to be at the root of the repository.
+	pub fn document(mut self, path: &str) -> Self {
+		let path = path.trim_start_matches("./");
+		self.directory = match path.rfind('/') {
+			Some(i) => path[..i].to_string(),
+			None => String::new(),
+		};
+		self
+	}
+}
+
+impl ResolveUrl for UrlRewriter {
+	fn resolve(&self, url: &str, kind: UrlKind) -> Option<String> {
+		let mut resolved = Cow::Borrowed(url);
+		if let Some(path) = self.repositories.iter().find_map(|repository| {
+			match url.strip_prefix(repository.as_str()) {
+				Some(path) => Some(path),
+				None if format!("{}/", url) == *repository => Some(""),
+				None => None,
+			}
+		}) {
+			resolved = Cow::Owned(relative_to(&self.directory, path));
+		}
+		if !is_relative(&resolved) || resolved.starts_with('#') {
+			return (resolved != url).then(|| resolved.into_owned());
+		}
+
+		if self.markdown_to_html && kind != UrlKind::Image {
+			let end = resolved.find(['?', '#']).unwrap_or(resolved.len());
+			let path = &resolved[..end];
+			if path.len() > 3 &&
+				path.as_bytes()[path.len() - 3..]
+					.eq_ignore_ascii_case(b".md")
+			{
+				resolved = Cow::Owned(format!(
+					"{}.html{}",
+					&path[..path.len() - 3],
+					&resolved[end..]
+				));
+			}
+		}
+
+		if let Some(base) = self.base.as_deref() {
+			resolved = Cow::Owned(match resolved.strip_prefix('/') {
+				Some(path) => format!("{}/{}", origin(base), path),
+				None => format!(
+					"{}/{}",
+					base.trim_end_matches('/'),
+					resolved.trim_start_matches("./")
+				),
+			});
+		}
+		(resolved != url).then(|| resolved.into_owned())
+	}
+}
+
+/// Path from `directory` to `path`, both relative to the root of a
+/// repository, eg. `../b/c.md` from `a` to `b/c.md`.
+fn relative_to(directory: &str, path: &str) -> String {
+	let end = path.find(['?', '#']).unwrap_or(path.len());
+	let (path, suffix) = path.split_at(end);
+	let from: Vec<&str> =
+		directory.split('/').filter(|c| !c.is_empty()).collect();
+	let to: Vec<&str> = path.split('/').collect();
+	let common = from
+		.iter()
+		.zip(to[..to.len() - 1].iter())
+		.take_while(|(a, b)| a == b)
+		.count();
+	let relative = format!(
+		"{}{}",
+		"../".repeat(from.len() - common),
+		to[common..].join("/")
+	);
+	match relative.is_empty() {
+		true => format!("./{}", suffix),
+		false => relative + suffix,
+	}
+}
+
+/// Checks whether a url has neither a scheme nor a host, eg. `docs/a.md`.
+fn is_relative(url: &str) -> bool {
+	!url.starts_with("//") && scheme(url).is_none()
+}
+
+/// Scheme of a url, eg. `https` of `https://x.y`.
+pub(crate) fn scheme(url: &str) -> Option<&str> {
+	let end = url.find(['/', '?', '#']).unwrap_or(url.len());
+	let (scheme, _) = url[..end].split_once(':')?;
+	(scheme.starts_with(|c: char| c.is_ascii_alphabetic()) &&
+		scheme
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c)))
+	.then_some(scheme)
+}
+
+/// Host of a url in lowercase, without the user and port, eg. `x.y` of
+/// `https://user@X.y:8080/a`.
+pub(crate) fn host(url: &str) -> Option<String> {
+	let after_scheme = match scheme(url) {
+		Some(scheme) => &url[scheme.len() + 1..],
+		None => url,
+	};
+	let authority = after_scheme.strip_prefix("//")?;
+	let end = authority.find(['/', '?', '#']).unwrap_or(authority.len());
+	let authority = &authority[..end];
+	let host = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+	let host = match host.rsplit_once(':') {
+		Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+		_ => host,
+	};
+	(!host.is_empty()).then(|| host.to_ascii_lowercase())
+}
+
+/// Scheme and host of an absolute url, eg. `https://x.y` of
+/// `https://x.y/a/b`.
+fn origin(url: &str) -> &str {
+	let host_start = url.find("://").map_or(0, |i| i + 3);
+	match url[host_start..].find('/') {
+		Some(i) => &url[..host_start + i],
+		None => url.trim_end_matches('/'),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn resolve(rewriter: &UrlRewriter, url: &str) -> String {
+		rewriter
+			.resolve(url, UrlKind::Link)
+			.unwrap_or_else(|| url.to_string())
+	}
+
+	#[test]
+	fn relative_urls_are_resolved_against_the_base() {
+		let rewriter = UrlRewriter::default().base("https://x.y/docs/");
+		assert_eq!(resolve(&rewriter, "a/b.md"), "https://x.y/docs/a/b.md");
+		assert_eq!(resolve(&rewriter, "./a"), "https://x.y/docs/a");
+		assert_eq!(resolve(&rewriter, "/a"), "https://x.y/a");
+		assert_eq!(resolve(&rewriter, "#a"), "#a");
+		assert_eq!(resolve(&rewriter, "https://z/a"), "https://z/a");
+		assert_eq!(resolve(&rewriter, "//z/a"), "//z/a");
+		assert_eq!(resolve(&rewriter, "mailto:a@z"), "mailto:a@z");
+	}
+
+	#[test]
+	fn markdown_links_point_at_html() {
+		let rewriter = UrlRewriter::default().markdown_to_html(true);
+		assert_eq!(resolve(&rewriter, "a/B.MD#c"), "a/B.html#c");
+		assert_eq!(resolve(&rewriter, "a.md?b=c.md"), "a.html?b=c.md");
+		assert_eq!(resolve(&rewriter, "a.mdx"), "a.mdx");
+		assert_eq!(resolve(&rewriter, "a€x"), "a€x");
+		assert_eq!(resolve(&rewriter, "€.md"), "€.html");
+		assert_eq!(resolve(&rewriter, "https://z/a.md"), "https://z/a.md");
+		assert_eq!(rewriter.resolve("a.md", UrlKind::Image), None);
+	}
+
+	#[test]
+	fn repository_urls_become_relative() {
+		let rewriter = UrlRewriter::default()
+			.repository("https://github.com/x/y/blob/main")
+			.markdown_to_html(true);
+		assert_eq!(
+			resolve(&rewriter, "https://github.com/x/y/blob/main/a.md"),
+			"a.html"
+		);
+		assert_eq!(
+			resolve(&rewriter, "https://github.com/x/y/blob/main"),
+			"./"
+		);
+		assert_eq!(
+			resolve(&rewriter, "https://github.com/x/z/blob/main/a.md"),
+			"https://github.com/x/z/blob/main/a.md"
+		);
+	}
+
+	#[test]
+	fn repository_urls_are_relative_to_the_document() {
+		let rewriter = UrlRewriter::default()
+			.repository("https://github.com/o/r/blob/main/")
+			.markdown_to_html(true)
+			.document("docs/guide.md");
+		let resolve = |path: &str| {
+			resolve(
+				&rewriter,
+				&format!("https://github.com/o/r/blob/main/{}", path),
+			)
+		};
+		assert_eq!(resolve("docs/a.md"), "a.html");
+		assert_eq!(resolve("docs/api/b.md#c"), "api/b.html#c");
+		assert_eq!(resolve("README.md"), "../README.html");
+		assert_eq!(resolve("src/d.rs"), "../src/d.rs");
+		assert_eq!(resolve("docs/"), "./");
+		assert_eq!(resolve(""), "../");
+	}
+}
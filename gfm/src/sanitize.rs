@@ -0,0 +1,343 @@
+//! Allowlist of the raw html that is kept when markdown is rendered.
+
+use std::collections::{
+	BTreeMap,
+	BTreeSet,
+};
+
+use crate::{
+	html::{
+		self,
+		Scan,
+		Tag,
+	},
+	policy::LinkPolicy,
+	token::{
+		percent_encode,
+		validate_link,
+	},
+};
+
+/// Tags that GFM always escapes, because they change how the html after
+/// them is parsed.
+///
+/// See <https://github.github.com/gfm/#disallowed-raw-html-extension->.
+const TAGFILTER: [&str; 9] = [
+	"title",
+	"textarea",
+	"style",
+	"xmp",
+	"iframe",
+	"noembed",
+	"noframes",
+	"script",
+	"plaintext",
+];
+
+/// Attributes whose value is a url, which has to be allowed by the same
+/// rules as the destination of a link.
+const URL_ATTRIBUTES: [&str; 8] = [
+	"action",
+	"background",
+	"cite",
+	"formaction",
+	"href",
+	"longdesc",
+	"poster",
+	"src",
+];
+
+/// Tags allowed by default, with the attributes allowed on them. They are
+/// what GitHub keeps in rendered markdown.
+const DEFAULT_TAGS: [(&str, &[&str]); 57] = [
+	("a", &["href", "name"]),
+	("abbr", &[]),
+	("b", &[]),
+	("bdo", &[]),
+	("blockquote", &["cite"]),
+	("br", &[]),
+	("caption", &[]),
+	("cite", &[]),
+	("code", &[]),
+	("dd", &[]),
+	("del", &["cite", "datetime"]),
+	("details", &["open"]),
+	("dfn", &[]),
+	("div", &[]),
+	("dl", &[]),
+	("dt", &[]),
+	("em", &[]),
+	("figcaption", &[]),
+	("figure", &[]),
+	("h1", &[]),
+	("h2", &[]),
+	("h3", &[]),
+	("h4", &[]),
+	("h5", &[]),
+	("h6", &[]),
+	("hr", &[]),
+	("i", &[]),
+	("img", &["src", "longdesc"]),
+	("ins", &["cite", "datetime"]),
+	("kbd", &[]),
+	("li", &[]),
+	("mark", &[]),
+	("ol", &["start", "type"]),
+	("p", &[]),
+	("picture", &[]),
+	("pre", &[]),
+	("q", &["cite"]),
+	("rp", &[]),
+	("rt", &[]),
+	("ruby", &[]),
+	("s", &[]),
+	("samp", &[]),
+	("small", &[]),
+	("span", &[]),
+	("strike", &[]),
+	("strong", &[]),
+	("sub", &[]),
+	("summary", &[]),
+	("sup", &[]),
+	("table", &[]),
+	("tbody", &[]),
+	("td", &["colspan", "rowspan"]),
+	("tfoot", &[]),
+	("th", &["colspan", "rowspan"]),
+	("thead", &[]),
+	("tr", &[]),
+	("ul", &[]),
+];
+
+/// Attributes allowed on every tag by default.
+const DEFAULT_GLOBAL_ATTRIBUTES: [&str; 10] = [
+	"align",
+	"alt",
+	"dir",
+	"height",
+	"lang",
+	"role",
+	"title",
+	"valign",
+	"width",
+	"aria-label",
+];
+
+/// Allowlist of the raw html that is kept when markdown is rendered.
+///
+/// Tags that are not allowed are escaped, so they show up as text.
+/// Attributes that are not allowed are removed from the tags that are.
+/// Comments, processing instructions and declarations are always removed,
+/// and so are event handlers, eg. `onclick`, and urls that would not be
+/// allowed as the destination of a link, eg. `javascript:`. The tags of
+/// GFM's tagfilter, eg. `<script>`, are always escaped.
+///
+/// ### Example
+///
+/// ```rust
+/// use gfm::Options;
+/// use gfm::Parser;
+/// use gfm::Sanitizer;
+///
+/// let options = Options {
+/// 	sanitizer: Sanitizer::empty().allow("kbd", &[]).allow("a", &["href"]),
+/// 	..Default::default()
+/// };
+/// let html = Parser::render_with_options(
+/// 	"<kbd>^C</kbd> <a href=\"x\" onclick=\"y\"><b>z</b></a>",
+/// 	&options,
+/// );
+/// assert_eq!(
+/// 	html,
+/// 	"<p><kbd>^C</kbd> <a href=\"x\">&lt;b&gt;z&lt;/b&gt;</a></p>\n"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sanitizer {
+	/// Allowed tags with the attributes allowed on them
+	tags:       BTreeMap<String, BTreeSet<String>>,
+	/// Attributes allowed on every allowed tag
+	attributes: BTreeSet<String>,
+}
+
+impl Default for Sanitizer {
+	/// Allows the tags and attributes GitHub keeps in rendered markdown.
+	fn default() -> Self {
+		let mut sanitizer = Sanitizer::empty();
+		for (tag, attributes) in DEFAULT_TAGS.iter() {
+			sanitizer = sanitizer.allow(tag, attributes);
+		}
+		sanitizer.allow_attribute(&DEFAULT_GLOBAL_ATTRIBUTES)
+	}
+}
+
+impl Sanitizer {
+	/// Allows no raw html at all, which is escaped.
+	pub fn empty() -> Self {
+		Sanitizer {
+			tags:       BTreeMap::new(),
+			attributes: BTreeSet::new(),
+		}
+	}
+
+	/// Allows a tag, or further attributes on a tag that is already allowed.
+	pub fn allow(mut self, tag: &str, attributes: &[&str]) -> Self {
+		self.tags
+			.entry(tag.to_ascii_lowercase())
+			.or_default()
+			.extend(attributes.iter().map(|a| a.to_ascii_lowercase()));
+		self
+	}
+
+	/// Allows attributes on every allowed tag.
+	pub fn allow_attribute(mut self, attributes: &[&str]) -> Self {
+		self.attributes
+			.extend(attributes.iter().map(|a| a.to_ascii_lowercase()));
+		self
+	}
+
+	/// Escapes a tag that is allowed, eg. by default.
+	pub fn deny(mut self, tag: &str) -> Self {
+		self.tags.remove(&tag.to_ascii_lowercase());
+		self
+	}
+
+	/// Checks whether a tag is kept, along with its allowed attributes.
+	pub fn allows(&self, tag: &str) -> bool {
+		self.allowed(tag).is_some()
+	}
+
+	/// Removes everything from raw html that is not allowed.
+	///
+	/// ### Example
+	///
+	/// ```rust
+	/// let sanitizer = gfm::Sanitizer::default();
+	/// assert_eq!(
+	/// 	sanitizer.sanitize("<img src=x onerror=alert(1)><script>"),
+	/// 	"<img src=\"x\">&lt;script&gt;"
+	/// );
+	/// ```
+	pub fn sanitize(&self, source: &str) -> String {
+		self.sanitize_links(source, &LinkPolicy::default())
+	}
+
+	/// Removes everything from raw html that is not allowed, including urls
+	/// whose scheme `policy` does not allow. Links get the attributes of the
+	/// policy instead of their own `target` and `rel`.
+	pub(crate) fn sanitize_links(
+		&self,
+		source: &str,
+		policy: &LinkPolicy,
+	) -> String {
+		let mut sanitized = String::with_capacity(source.len());
+		let mut scan = Scan::default();
+		let mut text_start = 0;
+		let mut i = 0;
+		while let Some(offset) = source[i..].find('<') {
+			let start = i + offset;
+			let Some((tag, end)) = html::tag(source, start, &mut scan) else {
+				i = start + 1;
+				continue;
+			};
+			sanitized.push_str(&html::raw(&source[text_start..start]));
+			match self.tag(&tag, policy) {
+				Some(tag) => sanitized.push_str(&tag),
+				None if matches!(tag, Tag::Other) => {}
+				None => sanitized.push_str(&html::text(&source[start..end])),
+			}
+			text_start = end;
+			i = end;
+		}
+		sanitized.push_str(&html::raw(&source[text_start..]));
+		sanitized
+	}
+
+	/// Writes an allowed tag again with only its allowed attributes, followed
+	/// by the attributes the policy gives a link.
+	fn tag(&self, tag: &Tag<'_>, policy: &LinkPolicy) -> Option<String> {
+		match tag {
+			Tag::Open {
+				name,
+				attributes,
+				self_closing,
+			} => {
+				let allowed = self.allowed(name)?;
+				let is_allowed = |name: &String| {
+					(allowed.contains(name) || self.attributes.contains(name)) &&
+						!name.starts_with("on")
+				};
+				let link_attributes = match name.eq_ignore_ascii_case("a") {
+					true => attributes
+						.iter()
+						.filter(|a| a.name.eq_ignore_ascii_case("href"))
+						.find(|a| is_allowed(&a.name.to_ascii_lowercase()))
+						.and_then(|a| url(a.value.unwrap_or(""), policy))
+						.map(|href| policy.attributes(&href))
+						.unwrap_or_default(),
+					false => String::new(),
+				};
+				let mut html = format!("<{}", name.to_ascii_lowercase());
+				for attribute in attributes.iter() {
+					let name = attribute.name.to_ascii_lowercase();
+					// The policy replaces the `target` and `rel` of links
+					let replaced = !link_attributes.is_empty() &&
+						(name == "target" || name == "rel");
+					if !is_allowed(&name) || replaced {
+						continue;
+					}
+					let value = attribute.value.unwrap_or("");
+					if URL_ATTRIBUTES.contains(&name.as_str()) {
+						let Some(url) = url(value, policy) else {
+							continue;
+						};
+						html.push_str(&format!(" {}=\"{}\"", name, url));
+					} else if attribute.value.is_none() {
+						html.push_str(&format!(" {}", name));
+					} else {
+						html.push_str(&format!(
+							" {}=\"{}\"",
+							name,
+							html::raw(value).replace('\'', "&#39;")
+						));
+					}
+				}
+				html.push_str(&link_attributes);
+				html.push_str(if *self_closing { " />" } else { ">" });
+				Some(html)
+			}
+			Tag::Close(name) => {
+				self.allowed(name)?;
+				Some(format!("</{}>", name.to_ascii_lowercase()))
+			}
+			Tag::Other => None,
+		}
+	}
+
+	/// Returns the attributes allowed on a tag, if it is allowed.
+	fn allowed(&self, tag: &str) -> Option<&BTreeSet<String>> {
+		let tag = tag.to_ascii_lowercase();
+		if TAGFILTER.contains(&tag.as_str()) {
+			return None;
+		}
+		self.tags.get(&tag)
+	}
+}
+
+/// Decodes the value of a url attribute, checks it like the destination of
+/// a link, including its scheme against `policy`, and returns it escaped
+/// like every other attribute, but otherwise as it is written. Control
+/// characters and whitespace, which browsers skip in a scheme, are not
+/// taken into account by the check.
+fn url(value: &str, policy: &LinkPolicy) -> Option<String> {
+	let decoded = html::decode(value)?;
+	let stripped: String = decoded
+		.chars()
+		.filter(|c| !(c.is_ascii_control() || c.is_whitespace()))
+		.collect();
+	validate_link(&percent_encode(&stripped)).ok()?;
+	if !policy.allows(&stripped) {
+		return None;
+	}
+	Some(html::raw(value).replace('\'', "&#39;"))
+}
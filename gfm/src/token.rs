@@ -1,4 +1,7 @@
 use core::fmt;
+use std::borrow::Cow;
+
+use crate::Image;
 
 static COMMONMARK_SCHEME_ASCII: [char; 65] = [
 	//https://spec.commonmark.org/0.30/#scheme
@@ -11,58 +14,36 @@ static COMMONMARK_SCHEME_ASCII: [char; 65] = [
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum Token<'a> {
-	/// String: Body of unstructured text
-	Plaintext(String),
-	/// u8: Header level (1..=6). str: Header text. Option<str>: html label
-	Header(usize, String, Option<String>),
-	/// str: Text for list entry
-	UnorderedListEntry(Vec<Token<'a>>),
-	/// str: Text for list entry
-	OrderedListEntry(String),
-	/// str: Text to be italicized
-	Italic(String),
-	/// str: Text to be bolded
-	Bold(String),
-	/// str: Text to be bolded and italicized
-	BoldItalic(String),
+	/// Cow<str>: Body of unstructured text, borrowed from the source unless
+	/// it had to be rewritten
+	Plaintext(Cow<'a, str>),
+	/// Vec<Token>: Tokens to be emphasized. eg. <em>tokens</em>
+	Emphasis(Vec<Token<'a>>),
+	/// Vec<Token>: Tokens to be strongly emphasized. eg.
+	/// <strong>tokens</strong>
+	Strong(Vec<Token<'a>>),
+	/// DelimiterRun: Run of `*` or `_`. Resolved into [Token::Emphasis] and
+	/// [Token::Strong] after lexing, never rendered
+	Delimiter(DelimiterRun),
 	/// Corresponds to a </br> html tag
 	LineBreak,
-	/// Corresponds to a newline character
-	Newline,
-	/// Corresponds to a <hr /> html tag
-	HorizontalRule,
-	/// Used for control flow. Not directly rendered
-	Tab,
-	/// Used for control flow. Not directly rendered
-	DoubleTab,
+	/// Line ending inside a paragraph, rendered as a newline
+	SoftBreak,
 	/// str: Text to be struck through
-	Strikethrough(String),
-	/// str: Text to be placed within an inline code tag. eg. <code>str</code>
-	Code(String),
-	/// First str: Text to be placed within a multi-line code tag. Second str:
-	/// Language
-	CodeBlock(String, String),
-	/// u8: Block quote level. str: Block quote text
-	BlockQuote(u8, String),
-	/// str: Link. Option<str>: Title for link.
-	Image(String, Option<String>),
-	/// str: Link. First Option<str>: Title for link. Second Option<str>: Hover
-	/// text
-	Link(ValidURL<'a>, Option<String>, Option<String>),
-	/// str: Summary. Vec<Token>: Tokens to be rendered in the collapsable
-	/// section
-	Detail(String, Vec<Token<'a>>),
-	/// Tuple of Vec<(Alignment, str)>: Which defines the table header and
-	/// Vec<Vec<(Alignment, Vec<Token>)>> which defines the rows
-	Table(
-		Vec<(Alignment, String)>,
-		Vec<Vec<(Alignment, Vec<Token<'a>>)>>,
-	),
-	/// TaskBox: Boolean state of the checked or unchecked box. str: List item
-	/// text
-	TaskListItem(TaskBox, String),
-	/// First str: Reference id. Second str: Reference text
-	Footnote(String, String),
+	Strikethrough(&'a str),
+	/// Cow<str>: Text to be placed within an inline code tag. eg.
+	/// <code>str</code>
+	Code(Cow<'a, str>),
+	/// Image: Source, alternate text, title and size of the image
+	Image(Image),
+	/// str: Link. First Option<Vec<Token>>: Content of the link. Second
+	/// Option<str>: Hover text
+	Link(ValidURL<'a>, Option<Vec<Token<'a>>>, Option<&'a str>),
+	/// str: Name of the syntax extension. String: Value it parsed.
+	/// Vec<Token>: Content
+	Custom(&'a str, String, Vec<Token<'a>>),
+	/// str: Raw html tag, comment or declaration, eg. `<kbd>`
+	Html(&'a str),
 }
 
 impl fmt::Display for Token<'_> {
@@ -79,16 +60,110 @@ impl fmt::Display for Token<'_> {
 }
 
 impl Token<'_> {
-	pub(crate) fn is_usable_in_table(&self) -> bool {
+	/// Number of emphasis and links nested in each other, starting with this
+	/// token.
+	pub(crate) fn depth(&self) -> usize {
 		match self {
-			Token::Code(_) => true,
-			Token::Link(_, _, _) => true,
-			Token::Bold(_) => true,
-			Token::Italic(_) => true,
-			Token::BoldItalic(_) => true,
-			Token::Plaintext(_) => true,
-			_ => false,
+			Token::Emphasis(inner) |
+			Token::Strong(inner) |
+			Token::Link(_, Some(inner), _) |
+			Token::Custom(_, _, inner) => {
+				1 + inner.iter().map(Token::depth).max().unwrap_or(0)
+			}
+			_ => 0,
+		}
+	}
+
+	/// Concatenates the text of inline tokens without any markup, eg. for the
+	/// alt text of an image.
+	pub(crate) fn plain_text(tokens: &[Token<'_>]) -> String {
+		let mut text = String::new();
+		for token in tokens.iter() {
+			match token {
+				Token::Plaintext(t) | Token::Code(t) => text.push_str(t),
+				Token::Strikethrough(t) => text.push_str(t),
+				Token::Emphasis(inner) | Token::Strong(inner) => {
+					text.push_str(&Self::plain_text(inner))
+				}
+				Token::Link(_, Some(inner), _) | Token::Custom(_, _, inner) => {
+					text.push_str(&Self::plain_text(inner))
+				}
+				Token::Link(l, None, _) => text.push_str(&l.fmt_unsafe()),
+				Token::Image(Image { alt: Some(alt), .. }) => {
+					text.push_str(alt)
+				}
+				Token::LineBreak | Token::SoftBreak => text.push('\n'),
+				_ => {}
+			}
 		}
+		text
+	}
+}
+
+/// A run of `*` or `_` characters that may open or close emphasis.
+///
+/// See <https://spec.commonmark.org/0.29/#delimiter-run>.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct DelimiterRun {
+	/// Either `*` or `_`
+	pub(crate) ch:        char,
+	/// Length of the whole run, used for the rule of 3
+	pub(crate) length:    usize,
+	/// Delimiters of the run that are not matched yet
+	pub(crate) count:     usize,
+	pub(crate) can_open:  bool,
+	pub(crate) can_close: bool,
+}
+
+impl DelimiterRun {
+	/// Creates a run of `length` times `ch`, surrounded by `prev` and `next`.
+	/// The start and end of the source count as whitespace.
+	pub(crate) fn new(
+		ch: char,
+		length: usize,
+		prev: Option<char>,
+		next: Option<char>,
+	) -> Self {
+		let prev_whitespace = prev.is_none_or(char::is_whitespace);
+		let next_whitespace = next.is_none_or(char::is_whitespace);
+		let prev_punctuation = prev.is_some_and(is_punctuation);
+		let next_punctuation = next.is_some_and(is_punctuation);
+
+		let left_flanking = !next_whitespace &&
+			(!next_punctuation || prev_whitespace || prev_punctuation);
+		let right_flanking = !prev_whitespace &&
+			(!prev_punctuation || next_whitespace || next_punctuation);
+
+		let (can_open, can_close) = match ch {
+			'_' => (
+				left_flanking && (!right_flanking || prev_punctuation),
+				right_flanking && (!left_flanking || next_punctuation),
+			),
+			_ => (left_flanking, right_flanking),
+		};
+
+		DelimiterRun {
+			ch,
+			length,
+			count: length,
+			can_open,
+			can_close,
+		}
+	}
+
+	/// The unmatched delimiters as plain text.
+	pub(crate) fn literal(&self) -> String {
+		std::iter::repeat_n(self.ch, self.count).collect()
+	}
+}
+
+/// ASCII punctuation and, as an approximation of the Unicode punctuation
+/// classes, every other non-alphanumeric visible character.
+pub(crate) fn is_punctuation(c: char) -> bool {
+	if c.is_ascii() {
+		c.is_ascii_punctuation()
+	} else {
+		!(c.is_alphanumeric() || c.is_whitespace() || c.is_control())
 	}
 }
 
@@ -100,22 +175,49 @@ pub struct ValidURL<'a> {
 
 impl ValidURL<'_> {
 	pub(crate) fn fmt_unsafe(&self) -> String {
-		let amp_replace_content = self.content.replace('&', "&amp;");
+		self.text().replace('&', "&amp;")
+	}
+
+	/// Text of the link if it is written as an autolink, eg. without the
+	/// `mailto:` of an email address. Urls without a scheme, eg. relative
+	/// paths, are kept as written instead of getting an `http:` prefix.
+	pub(crate) fn text(&self) -> String {
+		match &self.scheme {
+			None => self.content.to_string(),
+			Some(Scheme::Email(_)) | Some(Scheme::Www) => {
+				self.content.to_string()
+			}
+			Some(s) => format!("{}:{}", s, self.content),
+		}
+	}
+
+	/// The destination along with its scheme, which is validated to the same
+	/// url again.
+	pub(crate) fn destination(&self) -> String {
 		match &self.scheme {
-			None => format!("http:{}", amp_replace_content),
-			Some(Scheme::Email(_s)) => amp_replace_content.to_string(),
-			Some(s) => format!("{}:{}", s, amp_replace_content),
+			None => self.content.to_string(),
+			Some(Scheme::Www) => format!("http://{}", self.content),
+			Some(s) => format!("{}:{}", s, self.content),
 		}
 	}
 }
 
+/// The destination as it is written into an attribute, percent encoded and
+/// escaped. Like [ValidURL::text], a missing scheme is not filled in.
 impl fmt::Display for ValidURL<'_> {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		match &self.scheme {
 			None => {
 				write!(
 					f,
-					"http:{}",
+					"{}",
+					percent_encode(self.content).replace('&', "&amp;")
+				)
+			}
+			Some(Scheme::Www) => {
+				write!(
+					f,
+					"http://{}",
 					percent_encode(self.content).replace('&', "&amp;")
 				)
 			}
@@ -137,6 +239,8 @@ pub(crate) enum Scheme<'a> {
 	Email(&'a str),
 	Irc(&'a str),
 	Other(&'a str),
+	/// Extended `www.` autolink without an explicit scheme
+	Www,
 }
 
 impl fmt::Display for Scheme<'_> {
@@ -146,49 +250,24 @@ impl fmt::Display for Scheme<'_> {
 			Scheme::Email(s) => write!(f, "{}", s),
 			Scheme::Irc(s) => write!(f, "{}", s),
 			Scheme::Other(s) => write!(f, "{}", s),
+			Scheme::Www => write!(f, "http"),
 		}
 	}
 }
 
 pub(crate) fn percent_encode(source: &str) -> String {
-	source
-		.replace('%', "%25")
-		.replace('#', "%23")
-		.replace('[', "%5B")
-		.replace(']', "%5D")
-		.replace('!', "%21")
-		.replace('$', "%24")
-		.replace("'", "%27")
-		.replace('(', "%28")
-		.replace(')', "%29")
-		.replace('*', "%2A")
-		.replace(' ', "%20")
-		.replace('\\', "%5C")
-}
-
-/// Holds the possible states of a taskbox in a task list
-#[derive(Debug, PartialEq, Eq)]
-pub enum TaskBox {
-	Checked,
-	Unchecked,
-}
-
-/// Holds the alignment states for the table token
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub enum Alignment {
-	Left,
-	Right,
-	Center,
-}
+	// Same set of characters `cmark` leaves untouched in link destinations.
+	const SAFE: &[u8] = b"-_.+!*(),%#@?=;:/$~&";
 
-impl fmt::Display for Alignment {
-	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		match self {
-			Alignment::Left => write!(f, "left"),
-			Alignment::Right => write!(f, "right"),
-			Alignment::Center => write!(f, "center"),
+	let mut encoded = String::with_capacity(source.len());
+	for b in source.bytes() {
+		if b.is_ascii_alphanumeric() || SAFE.contains(&b) {
+			encoded.push(b as char);
+		} else {
+			encoded.push_str(&format!("%{:02X}", b));
 		}
 	}
+	encoded
 }
 
 pub(crate) fn validate_link(
@@ -199,7 +278,9 @@ pub(crate) fn validate_link(
 		return Err(SanitizationError { content: source });
 	}
 	let (scheme, path) = source.split_at(source.find(':').unwrap_or(0));
-	if scheme.to_lowercase() == "javascript" || !scheme.is_ascii() {
+	if matches!(scheme.to_lowercase().as_str(), "javascript" | "vbscript") ||
+		!scheme.is_ascii()
+	{
 		return Err(SanitizationError { content: source });
 	}
 	if scheme.to_lowercase() == "data" && !path.starts_with(":image/") {
@@ -287,7 +368,7 @@ pub(crate) fn validate_link(
 				.unwrap_or(""),
 			scheme:  Some(Scheme::Other(s)),
 		}),
-		None => Ok(ValidURL {
+		None | Some(Scheme::Www) => Ok(ValidURL {
 			content: source,
 			scheme:  None,
 		}),
@@ -0,0 +1,261 @@
+//! Traversal of a [Document], to inspect it with a [Visitor] or to rewrite
+//! it with a [VisitorMut] before it is rendered.
+//!
+//! Every method of the traits visits the children of its node by default.
+//! An implementation that overrides one of them does its work before and
+//! after calling the matching `walk_*` function, or leaves the children out
+//! by not calling it. Transformations compose by visiting the document with
+//! one visitor after another.
+
+use crate::ast::{
+	Block,
+	Custom,
+	Document,
+	Inline,
+};
+
+/// Visits the nodes of a document, parents before their children.
+///
+/// ### Example
+///
+/// ```rust
+/// use gfm::Inline;
+/// use gfm::Options;
+/// use gfm::Parser;
+/// use gfm::Visitor;
+///
+/// /// Collects the destination of every link.
+/// #[derive(Default)]
+/// struct Links(Vec<String>);
+///
+/// impl Visitor for Links {
+/// 	fn visit_inline(&mut self, inline: &Inline) {
+/// 		match inline {
+/// 			Inline::Link(url, _, _) | Inline::Autolink(url, _) => {
+/// 				self.0.push(url.clone())
+/// 			}
+/// 			_ => {}
+/// 		}
+/// 		gfm::walk_inline(self, inline);
+/// 	}
+/// }
+///
+/// let source = "- [foo](https://a.b)\n\n> see *<https://c.d>*";
+/// let mut links = Links::default();
+/// Parser::parse(source, &Options::default()).visit(&mut links);
+/// assert_eq!(links.0, ["https://a.b", "https://c.d"]);
+/// ```
+pub trait Visitor {
+	fn visit_block(&mut self, block: &Block) {
+		walk_block(self, block);
+	}
+
+	fn visit_inline(&mut self, inline: &Inline) {
+		walk_inline(self, inline);
+	}
+}
+
+/// Visits the nodes of a document, parents before their children, and
+/// allows them to be changed, removed or added.
+///
+/// ### Example
+///
+/// ```rust
+/// use gfm::Inline;
+/// use gfm::Options;
+/// use gfm::Parser;
+/// use gfm::VisitorMut;
+///
+/// /// Removes every image.
+/// struct StripImages;
+///
+/// impl VisitorMut for StripImages {
+/// 	fn visit_inlines_mut(&mut self, inlines: &mut Vec<Inline>) {
+/// 		inlines.retain(|i| !matches!(i, Inline::Image(..)));
+/// 		gfm::walk_inlines_mut(self, inlines);
+/// 	}
+/// }
+///
+/// let options = Options::default();
+/// let mut document = Parser::parse("a ![b](c.png) *d ![e](f.png)*", &options);
+/// document.visit_mut(&mut StripImages);
+/// let html = Parser::render_document(&document, &options);
+/// assert_eq!(html, "<p>a  <em>d </em></p>\n");
+/// ```
+pub trait VisitorMut {
+	/// Visits the blocks of a document or of a container block, eg. a block
+	/// quote or list item.
+	fn visit_blocks_mut(&mut self, blocks: &mut Vec<Block>) {
+		walk_blocks_mut(self, blocks);
+	}
+
+	fn visit_block_mut(&mut self, block: &mut Block) {
+		walk_block_mut(self, block);
+	}
+
+	/// Visits the inline content of a leaf block, eg. a paragraph or table
+	/// cell, or of an inline, eg. an emphasis or link.
+	fn visit_inlines_mut(&mut self, inlines: &mut Vec<Inline>) {
+		walk_inlines_mut(self, inlines);
+	}
+
+	fn visit_inline_mut(&mut self, inline: &mut Inline) {
+		walk_inline_mut(self, inline);
+	}
+}
+
+impl Document {
+	/// Visits every block and inline of the document.
+	pub fn visit<V: Visitor + ?Sized>(&self, visitor: &mut V) {
+		for block in self.blocks.iter() {
+			visitor.visit_block(block);
+		}
+	}
+
+	/// Visits every block and inline of the document, which may be changed
+	/// along the way.
+	pub fn visit_mut<V: VisitorMut + ?Sized>(&mut self, visitor: &mut V) {
+		visitor.visit_blocks_mut(&mut self.blocks);
+	}
+}
+
+/// Visits the children of a block.
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, block: &Block) {
+	match block {
+		Block::Paragraph(inlines) |
+		Block::Header(_, inlines, _) |
+		Block::Custom(Custom {
+			children: inlines, ..
+		}) => {
+			for inline in inlines.iter() {
+				visitor.visit_inline(inline);
+			}
+		}
+		Block::BlockQuote(blocks) | Block::Footnote(_, blocks) => {
+			for block in blocks.iter() {
+				visitor.visit_block(block);
+			}
+		}
+		Block::List(_, _, items) => {
+			for block in items.iter().flat_map(|item| item.children.iter()) {
+				visitor.visit_block(block);
+			}
+		}
+		Block::Table(_, headings, rows) => {
+			for inline in headings.iter().chain(rows.iter().flatten()).flatten()
+			{
+				visitor.visit_inline(inline);
+			}
+		}
+		Block::Detail(summary, blocks) => {
+			for inline in summary.iter() {
+				visitor.visit_inline(inline);
+			}
+			for block in blocks.iter() {
+				visitor.visit_block(block);
+			}
+		}
+		Block::HorizontalRule | Block::CodeBlock(..) | Block::Html(_) => {}
+	}
+}
+
+/// Visits the children of an inline.
+pub fn walk_inline<V: Visitor + ?Sized>(visitor: &mut V, inline: &Inline) {
+	match inline {
+		Inline::Emphasis(inner) |
+		Inline::Strong(inner) |
+		Inline::Link(_, inner, _) |
+		Inline::Custom(Custom {
+			children: inner, ..
+		}) => {
+			for inline in inner.iter() {
+				visitor.visit_inline(inline);
+			}
+		}
+		Inline::Text(_) |
+		Inline::Strikethrough(_) |
+		Inline::Code(_) |
+		Inline::Autolink(..) |
+		Inline::Image(..) |
+		Inline::FootnoteReference(_) |
+		Inline::LineBreak |
+		Inline::SoftBreak |
+		Inline::Html(_) => {}
+	}
+}
+
+/// Visits every block of a sequence.
+pub fn walk_blocks_mut<V: VisitorMut + ?Sized>(
+	visitor: &mut V,
+	blocks: &mut [Block],
+) {
+	for block in blocks.iter_mut() {
+		visitor.visit_block_mut(block);
+	}
+}
+
+/// Visits the children of a block.
+pub fn walk_block_mut<V: VisitorMut + ?Sized>(
+	visitor: &mut V,
+	block: &mut Block,
+) {
+	match block {
+		Block::Paragraph(inlines) |
+		Block::Header(_, inlines, _) |
+		Block::Custom(Custom {
+			children: inlines, ..
+		}) => visitor.visit_inlines_mut(inlines),
+		Block::BlockQuote(blocks) | Block::Footnote(_, blocks) => {
+			visitor.visit_blocks_mut(blocks)
+		}
+		Block::List(_, _, items) => {
+			for item in items.iter_mut() {
+				visitor.visit_blocks_mut(&mut item.children);
+			}
+		}
+		Block::Table(_, headings, rows) => {
+			for cell in headings.iter_mut().chain(rows.iter_mut().flatten()) {
+				visitor.visit_inlines_mut(cell);
+			}
+		}
+		Block::Detail(summary, blocks) => {
+			visitor.visit_inlines_mut(summary);
+			visitor.visit_blocks_mut(blocks);
+		}
+		Block::HorizontalRule | Block::CodeBlock(..) | Block::Html(_) => {}
+	}
+}
+
+/// Visits every inline of a sequence.
+pub fn walk_inlines_mut<V: VisitorMut + ?Sized>(
+	visitor: &mut V,
+	inlines: &mut [Inline],
+) {
+	for inline in inlines.iter_mut() {
+		visitor.visit_inline_mut(inline);
+	}
+}
+
+/// Visits the children of an inline.
+pub fn walk_inline_mut<V: VisitorMut + ?Sized>(
+	visitor: &mut V,
+	inline: &mut Inline,
+) {
+	match inline {
+		Inline::Emphasis(inner) |
+		Inline::Strong(inner) |
+		Inline::Link(_, inner, _) |
+		Inline::Custom(Custom {
+			children: inner, ..
+		}) => visitor.visit_inlines_mut(inner),
+		Inline::Text(_) |
+		Inline::Strikethrough(_) |
+		Inline::Code(_) |
+		Inline::Autolink(..) |
+		Inline::Image(..) |
+		Inline::FootnoteReference(_) |
+		Inline::LineBreak |
+		Inline::SoftBreak |
+		Inline::Html(_) => {}
+	}
+}
@@ -0,0 +1,166 @@
+//! Markup that is kept as text is reported with its position in the source.
+
+use gfm::{
+	Diagnostic,
+	DiagnosticKind,
+	Error,
+	Options,
+	ParseErrorAction,
+	Parser,
+	Severity,
+};
+
+fn diagnostics(source: &str) -> Vec<Diagnostic> {
+	Parser::render_with_diagnostics(source, &Options::default()).1
+}
+
+/// Returns the kind, line, column and spanned text of every diagnostic.
+fn located(source: &str) -> Vec<(DiagnosticKind, usize, usize, &str)> {
+	diagnostics(source)
+		.into_iter()
+		.map(|d| {
+			let (line, column) = d.line_column(source);
+			(d.kind, line, column, &source[d.span])
+		})
+		.collect()
+}
+
+#[test]
+fn valid_markup_has_no_diagnostics() {
+	assert!(diagnostics(
+		"# foo\n\n*bar* ~~baz~~ `qux` [a](b) <http://c.d> ![e](f.png)\n"
+	)
+	.is_empty());
+}
+
+#[test]
+fn unclosed_inline_markup() {
+	assert_eq!(located("foo ``bar baz"), vec![(
+		DiagnosticKind::UnclosedCodeSpan,
+		1,
+		5,
+		"``"
+	)]);
+	assert_eq!(located("~~foo"), vec![(
+		DiagnosticKind::UnclosedStrikethrough,
+		1,
+		1,
+		"~~"
+	)]);
+	assert_eq!(located("[foo](bar"), vec![(
+		DiagnosticKind::MalformedLink,
+		1,
+		1,
+		"[foo](bar"
+	)]);
+	assert_eq!(located("<1foo>"), vec![(
+		DiagnosticKind::InvalidAutolink,
+		1,
+		1,
+		"<1foo>"
+	)]);
+}
+
+#[test]
+fn unsafe_links() {
+	assert_eq!(located("a [b](javascript:alert(1))"), vec![(
+		DiagnosticKind::UnsafeLink,
+		1,
+		3,
+		"[b](javascript:alert(1)"
+	)]);
+}
+
+#[test]
+fn spans_point_into_nested_blocks() {
+	let source = "> - foo\n>   bar `baz\n\n| a | `b |\n| - | - |\n| c | ~~d |";
+	assert_eq!(located(source), vec![
+		(DiagnosticKind::UnclosedCodeSpan, 2, 9, "`"),
+		(DiagnosticKind::UnclosedCodeSpan, 4, 7, "`"),
+		(DiagnosticKind::UnclosedStrikethrough, 6, 7, "~~"),
+	]);
+}
+
+#[test]
+fn spans_point_into_headings_and_task_items() {
+	let source = "## foo `bar\n\nbaz\n===\n\n- [ ]  qux [a](b";
+	assert_eq!(located(source), vec![
+		(DiagnosticKind::UnclosedCodeSpan, 1, 8, "`"),
+		(DiagnosticKind::MalformedLink, 6, 12, "[a](b"),
+	]);
+}
+
+#[test]
+fn spans_point_into_link_text() {
+	let source = "[foo `bar](baz)";
+	assert_eq!(located(source), vec![(
+		DiagnosticKind::UnclosedCodeSpan,
+		1,
+		6,
+		"`"
+	)]);
+}
+
+#[test]
+fn nesting_too_deep_is_an_error() {
+	let options = Options {
+		max_nesting: 2,
+		..Default::default()
+	};
+	let source = "foo\n> > > bar";
+	let (_, diagnostics) = Parser::render_with_diagnostics(source, &options);
+	assert_eq!(diagnostics.len(), 1);
+	assert_eq!(diagnostics[0].kind, DiagnosticKind::NestingTooDeep);
+	assert_eq!(diagnostics[0].severity, Severity::Error);
+	assert_eq!(&source[diagnostics[0].span.clone()], "> > > bar");
+}
+
+fn with_action(on_parse_error: ParseErrorAction) -> Options {
+	Options {
+		on_parse_error,
+		..Default::default()
+	}
+}
+
+#[test]
+fn serialize_keeps_invalid_markup_as_text() {
+	let options = with_action(ParseErrorAction::Serialize);
+	assert_eq!(
+		Parser::try_render("a `b ~~c", &options),
+		Ok("<p>a `b ~~c</p>\n".to_string())
+	);
+}
+
+#[test]
+fn skip_drops_invalid_markup() {
+	let options = with_action(ParseErrorAction::Skip);
+	let render = |source| Parser::try_render(source, &options);
+	assert_eq!(render("a `b ~~c"), Ok("<p>a b c</p>\n".to_string()));
+	assert_eq!(render("a [b](c"), Ok("<p>a </p>\n".to_string()));
+	assert_eq!(render("a <1b> c"), Ok("<p>a  c</p>\n".to_string()));
+	assert!(render("[a `b](c)").unwrap().contains(">a b</a>"));
+	// Brackets that are not a link are not reported, so they are kept
+	assert_eq!(render("[a] b"), Ok("<p>[a] b</p>\n".to_string()));
+}
+
+#[test]
+fn abort_fails_on_the_first_invalid_markup() {
+	let options = with_action(ParseErrorAction::Abort);
+	match Parser::try_render("foo\n\nbar `baz ~~qux", &options) {
+		Err(Error::InvalidMarkup {
+			line,
+			column,
+			diagnostic,
+		}) => {
+			assert_eq!((line, column), (3, 5));
+			assert_eq!(diagnostic.kind, DiagnosticKind::UnclosedCodeSpan);
+		}
+		result => panic!("expected invalid markup, got {:?}", result),
+	}
+	assert!(Parser::try_render("foo *bar*", &options).is_ok());
+	// Only `try_render` can fail
+	assert_eq!(
+		Parser::render_with_options("`foo", &options),
+		"<p>`foo</p>\n"
+	);
+}
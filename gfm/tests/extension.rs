@@ -0,0 +1,201 @@
+//! Syntax that is not part of GFM can be registered as an extension.
+
+use gfm::{
+	BlockSyntax,
+	Custom,
+	Extensions,
+	Inline,
+	InlineSyntax,
+	Match,
+	Options,
+	Parser,
+	Visitor,
+};
+
+/// Marks text wrapped in `==`.
+struct Highlight;
+
+impl InlineSyntax for Highlight {
+	fn name(&self) -> &str {
+		"highlight"
+	}
+
+	fn trigger(&self) -> char {
+		'='
+	}
+
+	fn parse(&self, input: &str) -> Option<Match> {
+		let end = input.strip_prefix("==")?.find("==")? + 2;
+		(end > 2).then(|| Match {
+			length: end + 2,
+			content: Some(2..end),
+			..Default::default()
+		})
+	}
+
+	fn render(&self, _: &Custom, content: &str) -> String {
+		format!("<mark>{}</mark>", content)
+	}
+}
+
+/// Links to another page as `[[Page]]` or `[[Page|text]]`.
+struct WikiLink;
+
+impl InlineSyntax for WikiLink {
+	fn name(&self) -> &str {
+		"wikilink"
+	}
+
+	fn trigger(&self) -> char {
+		'['
+	}
+
+	fn parse(&self, input: &str) -> Option<Match> {
+		let rest = input.strip_prefix("[[")?;
+		let inner = &rest[..rest.find("]]")?];
+		let (page, content) = match inner.split_once('|') {
+			Some((page, _)) => (page, 3 + page.len()..2 + inner.len()),
+			None => (inner, 2..2 + inner.len()),
+		};
+		Some(Match {
+			length:  inner.len() + 4,
+			value:   page.trim().to_string(),
+			content: Some(content),
+		})
+	}
+
+	fn render(&self, node: &Custom, content: &str) -> String {
+		format!(
+			"<a href=\"/wiki/{}\">{}</a>",
+			gfm::escape(&node.value.replace(' ', "_")),
+			content
+		)
+	}
+}
+
+/// Embeds a video as `{{< youtube id >}}` on a line of its own.
+struct Shortcode;
+
+impl BlockSyntax for Shortcode {
+	fn name(&self) -> &str {
+		"shortcode"
+	}
+
+	fn trigger(&self) -> char {
+		'{'
+	}
+
+	fn parse(&self, line: &str) -> Option<Match> {
+		let inner = line.strip_prefix("{{<")?.strip_suffix(">}}")?;
+		Some(Match {
+			length: line.len(),
+			value: inner.trim().to_string(),
+			..Default::default()
+		})
+	}
+
+	fn render(&self, node: &Custom, _: &str) -> String {
+		match node.value.split_once(' ') {
+			Some(("youtube", id)) => format!(
+				"<iframe src=\"https://www.youtube.com/embed/{}\"></iframe>\n",
+				gfm::escape(id)
+			),
+			_ => String::new(),
+		}
+	}
+}
+
+fn options() -> Options {
+	Options {
+		extensions: Extensions::default()
+			.inline(Highlight)
+			.inline(WikiLink)
+			.block(Shortcode),
+		..Default::default()
+	}
+}
+
+fn render(source: &str) -> String {
+	Parser::render_with_options(source, &options())
+}
+
+#[test]
+fn inline_syntax_is_rendered() {
+	assert_eq!(
+		render("a ==b *c*== d"),
+		"<p>a <mark>b <em>c</em></mark> d</p>\n"
+	);
+	assert_eq!(
+		render("# ==a==\n\n| ==b== |\n| - |"),
+		concat!(
+			"<h1><mark>a</mark></h1>\n",
+			"<table>\n<thead>\n<tr>\n<th><mark>b</mark></th>\n</tr>\n",
+			"</thead>\n</table>\n"
+		)
+	);
+}
+
+#[test]
+fn inline_syntax_takes_precedence_over_gfm() {
+	assert_eq!(
+		render("see [[Main Page]] or [[Help|*the* help]]"),
+		concat!(
+			"<p>see <a href=\"/wiki/Main_Page\">Main Page</a> or ",
+			"<a href=\"/wiki/Help\"><em>the</em> help</a></p>\n"
+		)
+	);
+}
+
+#[test]
+fn gfm_is_lexed_where_a_syntax_does_not_match() {
+	assert_eq!(
+		render("[a](http://b.c) [[d == e"),
+		"<p><a href=\"http://b.c\">a</a> [[d == e</p>\n"
+	);
+	assert_eq!(Parser::render("a ==b== [[c]]"), "<p>a ==b== [[c]]</p>\n");
+}
+
+#[test]
+fn block_syntax_takes_up_a_line() {
+	assert_eq!(
+		render("a\n{{< youtube x\"y >}}\n\n> {{< youtube z >}} b"),
+		concat!(
+			"<p>a</p>\n",
+			"<iframe src=\"https://www.youtube.com/embed/x&quot;y\">",
+			"</iframe>\n",
+			"<blockquote>\n<p>{{&lt; youtube z &gt;}} b</p>\n</blockquote>\n"
+		)
+	);
+}
+
+#[test]
+fn nodes_of_syntax_extensions_are_visited() {
+	/// Collects the pages wikilinks point to.
+	#[derive(Default)]
+	struct Pages(Vec<String>);
+
+	impl Visitor for Pages {
+		fn visit_inline(&mut self, inline: &Inline) {
+			if let Inline::Custom(node) = inline {
+				if node.name == "wikilink" {
+					self.0.push(node.value.clone());
+				}
+			}
+			gfm::walk_inline(self, inline);
+		}
+	}
+
+	let mut pages = Pages::default();
+	Parser::parse("- ==[[a]]==\n\n  [[b|c]]", &options()).visit(&mut pages);
+	assert_eq!(pages.0, ["a", "b"]);
+}
+
+#[test]
+fn nodes_of_unregistered_syntax_are_rendered_as_content() {
+	let document =
+		Parser::parse("==*a*== [[b|c]]\n\n{{< youtube d >}}", &options());
+	assert_eq!(
+		Parser::render_document(&document, &Options::default()),
+		"<p><em>a</em> c</p>\n<p>youtube d</p>\n"
+	);
+}
@@ -1,8 +1,19 @@
-use gfm::Parser;
+use gfm::{
+	Options,
+	Parser,
+};
+
+fn render(source: &str) -> String {
+	let options = Options {
+		autolinks: true,
+		..Default::default()
+	};
+	Parser::render_with_options(source, &options)
+}
 
 #[test]
 fn gfm_test_622_www_autolink() {
-	let test_html = Parser::render("www.commonmark.org");
+	let test_html = render("www.commonmark.org");
 	let reference_html =
 		"<p><a href=\"http://www.commonmark.org\">www.commonmark.org</a></p>\n";
 	assert_eq!(test_html, reference_html);
@@ -11,42 +22,44 @@ fn gfm_test_622_www_autolink() {
 #[test]
 fn gfm_test_623_www_autolink_with_path() {
 	let test_html =
-		Parser::render("Visit www.commonmark.org/help for more information.");
+		render("Visit www.commonmark.org/help for more information.");
 	let reference_html = "<p>Visit <a href=\"http://www.commonmark.org/help\">www.commonmark.org/help</a> for more information.</p>\n";
 	assert_eq!(test_html, reference_html);
 }
 
 #[test]
 fn gfm_test_624_trailing_punctuation() {
-	let test_html = Parser::render(
-		"Visit www.commonmark.org.\n\nVisit www.commonmark.org/a.b.",
-	);
+	let test_html =
+		render("Visit www.commonmark.org.\n\nVisit www.commonmark.org/a.b.");
 	let reference_html = "<p>Visit <a href=\"http://www.commonmark.org\">www.commonmark.org</a>.</p>\n<p>Visit <a href=\"http://www.commonmark.org/a.b\">www.commonmark.org/a.b</a>.</p>\n";
 	assert_eq!(test_html, reference_html);
 }
 
 #[test]
 fn gfm_test_625_parentheses_balancing() {
-	let test_html = Parser::render(
-		"www.google.com/search?q=Markup+(business)\n\nwww.google.com/search?\
-		 q=Markup+(business)))\n\n(www.google.com/search?q=Markup+(business))\\
-		 \
-		 n\n(www.google.com/search?q=Markup+(business)",
-	);
+	let test_html = render(concat!(
+		"www.google.com/search?q=Markup+(business)\n",
+		"\n",
+		"www.google.com/search?q=Markup+(business)))\n",
+		"\n",
+		"(www.google.com/search?q=Markup+(business))\n",
+		"\n",
+		"(www.google.com/search?q=Markup+(business)\n",
+	));
 	let reference_html = "<p><a href=\"http://www.google.com/search?q=Markup+(business)\">www.google.com/search?q=Markup+(business)</a></p>\n<p><a href=\"http://www.google.com/search?q=Markup+(business)\">www.google.com/search?q=Markup+(business)</a>))</p>\n<p>(<a href=\"http://www.google.com/search?q=Markup+(business)\">www.google.com/search?q=Markup+(business)</a>)</p>\n<p>(<a href=\"http://www.google.com/search?q=Markup+(business)\">www.google.com/search?q=Markup+(business)</a></p>\n";
 	assert_eq!(test_html, reference_html);
 }
 
 #[test]
 fn gfm_test_626_parentheses_interior() {
-	let test_html = Parser::render("www.google.com/search?q=(business))+ok");
+	let test_html = render("www.google.com/search?q=(business))+ok");
 	let reference_html = "<p><a href=\"http://www.google.com/search?q=(business))+ok\">www.google.com/search?q=(business))+ok</a></p>\n";
 	assert_eq!(test_html, reference_html);
 }
 
 #[test]
 fn gfm_test_627_entity_reference_in_autolink() {
-	let test_html = Parser::render(
+	let test_html = render(
 		"www.google.com/search?q=commonmark&hl=en\n\nwww.google.com/search?\
 		 q=commonmark&hl;",
 	);
@@ -56,7 +69,7 @@ fn gfm_test_627_entity_reference_in_autolink() {
 
 #[test]
 fn gfm_test_628_autolink_with_lt() {
-	let test_html = Parser::render("www.commonmark.org/he<lp");
+	let test_html = render("www.commonmark.org/he<lp");
 	let reference_html =
         "<p><a href=\"http://www.commonmark.org/he\">www.commonmark.org/he</a>&lt;lp</p>\n";
 	assert_eq!(test_html, reference_html);
@@ -64,7 +77,7 @@ fn gfm_test_628_autolink_with_lt() {
 
 #[test]
 fn gfm_test_629_http_https_autolink() {
-	let test_html = Parser::render(
+	let test_html = render(
         "http://commonmark.org\n\n(Visit https://encrypted.google.com/search?q=Markup+(business))",
     );
 	let reference_html = "<p><a href=\"http://commonmark.org\">http://commonmark.org</a></p>\n<p>(Visit <a href=\"https://encrypted.google.com/search?q=Markup+(business)\">https://encrypted.google.com/search?q=Markup+(business)</a>)</p>\n";
@@ -73,7 +86,7 @@ fn gfm_test_629_http_https_autolink() {
 
 #[test]
 fn gfm_test_630_email_autolink() {
-	let test_html = Parser::render("foo@bar.baz");
+	let test_html = render("foo@bar.baz");
 	let reference_html =
 		"<p><a href=\"mailto:foo@bar.baz\">foo@bar.baz</a></p>\n";
 	assert_eq!(test_html, reference_html);
@@ -81,7 +94,7 @@ fn gfm_test_630_email_autolink() {
 
 #[test]
 fn gfm_test_631_email_plus_symbol() {
-	let test_html = Parser::render(
+	let test_html = render(
 		"hello@mail+xyz.example isn't valid, but hello+xyz@mail.example is.",
 	);
 	let reference_html = "<p>hello@mail+xyz.example isn't valid, but <a \
@@ -92,9 +105,8 @@ fn gfm_test_631_email_plus_symbol() {
 
 #[test]
 fn gfm_test_632_email_special_characters() {
-	let test_html = Parser::render(
-		"a.b-c_d@a.b\n\na.b-c_d@a.b.\n\na.b-c_d@a.b-\n\na.b-c_d@a.b_",
-	);
+	let test_html =
+		render("a.b-c_d@a.b\n\na.b-c_d@a.b.\n\na.b-c_d@a.b-\n\na.b-c_d@a.b_");
 	let reference_html = "<p><a href=\"mailto:a.b-c_d@a.b\">a.b-c_d@a.b</a></\
 	                      p>\n<p><a href=\"mailto:a.b-c_d@a.b\">a.b-c_d@a.b</\
 	                      a>.</p>\n<p>a.b-c_d@a.b-</p>\n<p>a.b-c_d@a.b_</p>\n";
@@ -103,10 +115,17 @@ fn gfm_test_632_email_special_characters() {
 
 #[test]
 fn gfm_test_633_protocol_autolinks() {
-	let test_html = Parser::render(
-		"mailto:foo@bar.baz\n\nmailto:a.b-c_d@a.b\n\nmailto:a.b-c_d@a.b.\n\\
-		 nxmpp:foo@bar.baz\n\nxmpp:foo@bar.baz.",
-	);
+	let test_html = render(concat!(
+		"mailto:foo@bar.baz\n",
+		"\n",
+		"mailto:a.b-c_d@a.b\n",
+		"\n",
+		"mailto:a.b-c_d@a.b.\n",
+		"\n",
+		"xmpp:foo@bar.baz\n",
+		"\n",
+		"xmpp:foo@bar.baz.\n",
+	));
 	let reference_html =
 		"<p><a href=\"mailto:foo@bar.baz\">mailto:foo@bar.baz</a></p>\n<p><a \
 		 href=\"mailto:a.b-c_d@a.b\">mailto:a.b-c_d@a.b</a></p>\n<p><a \
@@ -118,7 +137,7 @@ fn gfm_test_633_protocol_autolinks() {
 
 #[test]
 fn gfm_test_634_xmpp_autolink_with_resource() {
-	let test_html = Parser::render(
+	let test_html = render(
 		"xmpp:foo@bar.baz/txt\n\nxmpp:foo@bar.baz/txt@bin\n\nxmpp:foo@bar.baz/\
 		 txt@bin.com",
 	);
@@ -133,7 +152,7 @@ fn gfm_test_634_xmpp_autolink_with_resource() {
 
 #[test]
 fn gfm_test_635_xmpp_autolink_trailing_slash() {
-	let test_html = Parser::render("xmpp:foo@bar.baz/txt/bin");
+	let test_html = render("xmpp:foo@bar.baz/txt/bin");
 	let reference_html = "<p><a href=\"xmpp:foo@bar.baz/txt\">xmpp:foo@bar.\
 	                      baz/txt</a>/bin</p>\n";
 	assert_eq!(test_html, reference_html);
@@ -1,4 +1,7 @@
-use gfm::Parser;
+use gfm::{
+	Options,
+	Parser,
+};
 
 #[test]
 fn gfm_test_soft_line_breaks_673() {
@@ -13,3 +16,14 @@ fn gfm_test_soft_line_breaks_674() {
 	let expected_html = "<p>foo\nbaz</p>\n";
 	assert_eq!(test_html, expected_html);
 }
+
+#[test]
+fn soft_line_breaks_rendered_as_hard_breaks_with_breaks_option() {
+	let options = Options {
+		breaks: true,
+		..Default::default()
+	};
+	let test_html = Parser::render_with_options("foo \n baz\n*bar*", &options);
+	let expected_html = "<p>foo<br />\nbaz<br />\n<em>bar</em></p>\n";
+	assert_eq!(test_html, expected_html);
+}
@@ -3,21 +3,21 @@ use gfm::Parser;
 #[test]
 fn gfm_test_1_characters_and_lines_valid_document() {
 	let test_html = Parser::render("\tfoo\tbaz\t\tbim");
-	let reference_html = "<pre><code>foo\tbaz\t\tbim\n</code></pre>";
+	let reference_html = "<pre><code>foo\tbaz\t\tbim\n</code></pre>\n";
 	assert_eq!(test_html, reference_html);
 }
 
 #[test]
 fn gfm_test_2_characters_and_lines_indented_code_block() {
 	let test_html = Parser::render("  \tfoo\tbaz\t\tbim");
-	let reference_html = "<pre><code>foo\tbaz\t\tbim\n</code></pre>";
+	let reference_html = "<pre><code>foo\tbaz\t\tbim\n</code></pre>\n";
 	assert_eq!(test_html, reference_html);
 }
 
 #[test]
 fn gfm_test_3_unicode_in_code_block() {
 	let test_html = Parser::render("    a\ta\n    ὐ\ta");
-	let reference_html = "<pre><code>a\ta\nὐ\ta\n</code></pre>";
+	let reference_html = "<pre><code>a\ta\nὐ\ta\n</code></pre>\n";
 	assert_eq!(test_html, reference_html);
 }
 
@@ -55,7 +55,7 @@ fn gfm_test_7_list_item_with_tab_indentation() {
 #[test]
 fn gfm_test_8_blank_line_in_code_block() {
 	let test_html = Parser::render("    foo\n\tbar");
-	let reference_html = "<pre><code>foo\nbar\n</code></pre>";
+	let reference_html = "<pre><code>foo\nbar\n</code></pre>\n";
 	assert_eq!(test_html, reference_html);
 }
 
@@ -70,13 +70,13 @@ fn gfm_test_9_nested_list_with_indentation() {
 #[test]
 fn gfm_test_10_atx_heading_with_tab() {
 	let test_html = Parser::render("#\tFoo");
-	let reference_html = "<h1>Foo</h1>";
+	let reference_html = "<h1>Foo</h1>\n";
 	assert_eq!(test_html, reference_html);
 }
 
 #[test]
 fn gfm_test_11_hr_with_tabs() {
 	let test_html = Parser::render("*\t*\t*\t");
-	let reference_html = "<hr />";
+	let reference_html = "<hr />\n";
 	assert_eq!(test_html, reference_html);
 }
@@ -0,0 +1,130 @@
+//! Images are rendered with their title and size, lazily and as figures.
+
+use std::fs;
+
+use gfm::{
+	ImageOptions,
+	Options,
+	Parser,
+};
+
+fn render(source: &str, images: ImageOptions) -> String {
+	let options = Options {
+		images,
+		..Default::default()
+	};
+	Parser::render_with_options(source, &options)
+}
+
+#[test]
+fn images_have_their_title_and_size() {
+	assert_eq!(
+		Parser::render("![a](a.png)"),
+		"<p><img src=\"a.png\" alt=\"a\" referrerpolicy=\"no-referrer\"></p>\n"
+	);
+	assert_eq!(
+		Parser::render("![a](a.png 'b'){width=300 height=\"200px\"} c"),
+		concat!(
+			"<p><img src=\"a.png\" alt=\"a\" title=\"b\" width=\"300\" ",
+			"height=\"200\" referrerpolicy=\"no-referrer\"> c</p>\n"
+		)
+	);
+	assert_eq!(
+		Parser::render("![a](https://x.y/a.png)"),
+		concat!(
+			"<p><img src=\"https://x.y/a.png\" alt=\"a\" ",
+			"referrerpolicy=\"no-referrer\"></p>\n"
+		)
+	);
+	// Braces that are not a size are kept as text
+	assert_eq!(
+		Parser::render("![a](a.png){width=50%} {height=2}"),
+		concat!(
+			"<p><img src=\"a.png\" alt=\"a\" referrerpolicy=\"no-referrer\">",
+			"{width=50%} {height=2}</p>\n"
+		)
+	);
+	assert_eq!(
+		Parser::render("![a](javascript:b){width=3}"),
+		"<p><img src=\"data:,\" alt=\"a\" width=\"3\"></p>\n"
+	);
+}
+
+#[test]
+fn images_load_lazily() {
+	let images = ImageOptions {
+		lazy: true,
+		..Default::default()
+	};
+	assert_eq!(
+		render("![a](a.png)", images),
+		concat!(
+			"<p><img src=\"a.png\" alt=\"a\" loading=\"lazy\" ",
+			"decoding=\"async\" referrerpolicy=\"no-referrer\"></p>\n"
+		)
+	);
+}
+
+#[test]
+fn images_with_a_title_are_figures() {
+	let figures = || ImageOptions {
+		figures: true,
+		..Default::default()
+	};
+	assert_eq!(
+		render("![a](a.png \"<b> & c\")", figures()),
+		concat!(
+			"<figure>\n<img src=\"a.png\" alt=\"a\" title=\"&lt;b&gt; &amp; ",
+			"c\" referrerpolicy=\"no-referrer\">\n",
+			"<figcaption>&lt;b&gt; &amp; c</figcaption>\n</figure>\n"
+		)
+	);
+	// Only images that are a paragraph of their own
+	assert_eq!(
+		render("![a](a.png \"b\") c\n\n![d](d.png)", figures()),
+		concat!(
+			"<p><img src=\"a.png\" alt=\"a\" title=\"b\" ",
+			"referrerpolicy=\"no-referrer\"> c</p>\n",
+			"<p><img src=\"d.png\" alt=\"d\" referrerpolicy=\"no-referrer\">",
+			"</p>\n"
+		)
+	);
+}
+
+#[test]
+fn sizes_of_local_images_are_detected() {
+	let root =
+		std::env::temp_dir().join(format!("gfm-images-{}", std::process::id()));
+	fs::create_dir_all(root.join("img")).unwrap();
+	let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+	png.extend([0, 0, 1, 44, 0, 0, 0, 200]);
+	fs::write(root.join("img/a.png"), png).unwrap();
+	fs::write(root.join("b.svg"), "<svg viewBox=\"0 0 40 30\"></svg>").unwrap();
+
+	let images = ImageOptions {
+		local_root: Some(root.clone()),
+		..Default::default()
+	};
+	let sizes = render(
+		concat!(
+			"![a](img/a.png) ![a](./img/a.png?v=2){width=150} ",
+			"![b](b.svg){height=60} ![c](c.png) ![a](../img/a.png)"
+		),
+		images,
+	);
+	fs::remove_dir_all(&root).unwrap();
+	assert_eq!(
+		sizes,
+		concat!(
+			"<p><img src=\"img/a.png\" alt=\"a\" width=\"300\" height=\"200\" ",
+			"referrerpolicy=\"no-referrer\"> ",
+			"<img src=\"./img/a.png?v=2\" alt=\"a\" width=\"150\" ",
+			"height=\"100\" referrerpolicy=\"no-referrer\"> ",
+			"<img src=\"b.svg\" alt=\"b\" width=\"80\" height=\"60\" ",
+			"referrerpolicy=\"no-referrer\"> ",
+			"<img src=\"c.png\" alt=\"c\" referrerpolicy=\"no-referrer\"> ",
+			"<img src=\"../img/a.png\" alt=\"a\" ",
+			"referrerpolicy=\"no-referrer\"></p>\n"
+		)
+	);
+}
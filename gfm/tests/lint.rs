@@ -0,0 +1,198 @@
+//! Style rules and the comments that disable them.
+
+use gfm::{
+	LintOptions,
+	Parser,
+	Rule,
+};
+
+/// Returns the rule, line, column and spanned text of every lint.
+fn lints_with<'a>(
+	source: &'a str,
+	options: &LintOptions,
+) -> Vec<(Rule, usize, usize, &'a str)> {
+	Parser::lint(source, options)
+		.into_iter()
+		.map(|l| {
+			let (line, column) = l.line_column(source);
+			(l.rule, line, column, &source[l.span])
+		})
+		.collect()
+}
+
+fn lints(source: &str) -> Vec<(Rule, usize, usize, &str)> {
+	lints_with(source, &LintOptions::default())
+}
+
+#[test]
+fn clean_document_has_no_lints() {
+	let source = "# Title\n\n## Section\n\nSome *text* with a [link](https://a.b) \
+	              and <https://c.d>.\n\n- one\n- two\n\n![alt](x.png)  \nend\n";
+	assert_eq!(lints(source), vec![]);
+}
+
+#[test]
+fn heading_increment() {
+	assert_eq!(lints("# a\n\n### b\n\n#### c\n\n# d\n\n### e"), vec![
+		(Rule::HeadingIncrement, 3, 5, "b"),
+		(Rule::HeadingIncrement, 9, 5, "e"),
+	]);
+}
+
+#[test]
+fn duplicate_headings() {
+	assert_eq!(lints("# a\n\n## b\n\nb\n-\n\n## c"), vec![(
+		Rule::NoDuplicateHeading,
+		5,
+		1,
+		"b"
+	)]);
+}
+
+#[test]
+fn bare_urls() {
+	assert_eq!(
+		lints("see https://a.b/c and <https://d.e>\n\n> mail foo@bar.org"),
+		vec![
+			(Rule::NoBareUrls, 1, 5, "https://a.b/c"),
+			(Rule::NoBareUrls, 3, 8, "foo@bar.org"),
+		]
+	);
+}
+
+#[test]
+fn trailing_spaces() {
+	assert_eq!(lints("a \nb  \nc   \n\t\n"), vec![
+		(Rule::NoTrailingSpaces, 1, 2, " "),
+		(Rule::NoTrailingSpaces, 3, 2, "   "),
+		(Rule::NoTrailingSpaces, 4, 1, "\t"),
+	]);
+	let strict = LintOptions {
+		br_spaces: 0,
+		..Default::default()
+	};
+	assert_eq!(lints_with("b  \n", &strict), vec![(
+		Rule::NoTrailingSpaces,
+		1,
+		2,
+		"  "
+	)]);
+}
+
+#[test]
+fn list_markers() {
+	assert_eq!(lints("- a\n- b\n\n* c\n\n  + d"), vec![
+		(Rule::UlStyle, 4, 1, "*"),
+		(Rule::UlStyle, 6, 3, "+"),
+	]);
+	let asterisks = LintOptions {
+		list_marker: Some('*'),
+		..Default::default()
+	};
+	assert_eq!(lints_with("- a\n\n1. b\n\n* c", &asterisks), vec![(
+		Rule::UlStyle,
+		1,
+		1,
+		"-"
+	)]);
+}
+
+#[test]
+fn line_length() {
+	let options = LintOptions {
+		line_length: 10,
+		..Default::default()
+	};
+	assert_eq!(
+		lints_with(
+			"short\nthis line is too long\n<https://example.org/long>",
+			&options
+		),
+		vec![(Rule::LineLength, 2, 11, "is too long")]
+	);
+}
+
+#[test]
+fn images_without_alt_text() {
+	assert_eq!(lints("![](a.png) ![ ](b.png) ![c](c.png)"), vec![
+		(Rule::NoAltText, 1, 1, "![](a.png)"),
+		(Rule::NoAltText, 1, 12, "![ ](b.png)"),
+	]);
+	assert_eq!(lints("| a |\n| - |\n| [![](b.png)](c) |"), vec![(
+		Rule::NoAltText,
+		3,
+		3,
+		"[![](b.png)](c)"
+	)]);
+}
+
+#[test]
+fn empty_links() {
+	assert_eq!(lints("[a]() [b](#) [](c) [d](e)"), vec![
+		(Rule::NoEmptyLinks, 1, 1, "[a]()"),
+		(Rule::NoEmptyLinks, 1, 7, "[b](#)"),
+		(Rule::NoEmptyLinks, 1, 14, "[](c)"),
+	]);
+}
+
+#[test]
+fn disabled_rules_are_not_checked() {
+	let options = LintOptions {
+		disabled: vec![Rule::NoTrailingSpaces, Rule::NoBareUrls],
+		..Default::default()
+	};
+	assert_eq!(lints_with("a \nhttps://a.b\n\n[c]()", &options), vec![(
+		Rule::NoEmptyLinks,
+		4,
+		1,
+		"[c]()"
+	)]);
+}
+
+#[test]
+fn comments_disable_rules() {
+	let source = [
+		"<!-- smd-lint-disable no-bare-urls -->",
+		"https://a.b",
+		"<!-- smd-lint-enable -->",
+		"https://c.d",
+		"",
+		"<!-- smd-lint-disable-next-line -->",
+		"https://e.f [g]()",
+		"https://h.i <!-- smd-lint-disable-line MD034 -->",
+		"https://j.k",
+	]
+	.join("\n");
+	assert_eq!(lints(&source), vec![
+		(Rule::NoBareUrls, 4, 1, "https://c.d"),
+		(Rule::NoBareUrls, 9, 1, "https://j.k"),
+	]);
+}
+
+#[test]
+fn unclosed_comments_do_not_disable_rules() {
+	let source = "x <!--> https://a.b\ny <!---> https://c.d\n";
+	assert_eq!(lints(source), vec![
+		(Rule::NoBareUrls, 1, 9, "https://a.b"),
+		(Rule::NoBareUrls, 2, 10, "https://c.d"),
+	]);
+}
+
+#[test]
+fn comments_are_not_rendered() {
+	assert_eq!(
+		Parser::render("a\n<!-- smd-lint-disable -->\nb\n\n<!--\nc\n\n-->\nd"),
+		"<p>a</p>\n<p>b</p>\n<p>d</p>\n"
+	);
+}
+
+#[test]
+fn rules_parse_from_names_and_codes() {
+	assert_eq!("line-length".parse(), Ok(Rule::LineLength));
+	assert_eq!("md045".parse(), Ok(Rule::NoAltText));
+	assert!("no-such-rule".parse::<Rule>().is_err());
+	for rule in Rule::ALL {
+		assert_eq!(rule.name().parse(), Ok(rule));
+		assert_eq!(rule.code().parse(), Ok(rule));
+	}
+}
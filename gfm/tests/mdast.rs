@@ -0,0 +1,311 @@
+//! Documents are exchanged as mdast JSON.
+#![cfg(feature = "serde")]
+
+use gfm::{
+	Block,
+	Custom,
+	Document,
+	Inline,
+	Options,
+	Parser,
+};
+use serde_json::json;
+
+fn parse(source: &str) -> Document {
+	Parser::parse(source, &Options::default())
+}
+
+#[test]
+fn documents_serialize_to_mdast() {
+	let document = parse(
+		"# Foo {#bar}\n\nsome *em*\nnext  \nline\n\n```rust ignore\nx\n```",
+	);
+	assert_eq!(
+		serde_json::to_value(&document).unwrap(),
+		json!({
+			"type": "root",
+			"children": [
+				{
+					"type": "heading",
+					"depth": 1,
+					"children": [{ "type": "text", "value": "Foo" }],
+					"data": { "hProperties": { "id": "bar" } }
+				},
+				{
+					"type": "paragraph",
+					"children": [
+						{ "type": "text", "value": "some " },
+						{
+							"type": "emphasis",
+							"children": [{ "type": "text", "value": "em" }]
+						},
+						{ "type": "text", "value": "\nnext" },
+						{ "type": "break" },
+						{ "type": "text", "value": "line" }
+					]
+				},
+				{ "type": "code", "lang": "rust", "meta": "ignore", "value": "x" }
+			]
+		})
+	);
+}
+
+#[test]
+fn lists_tables_and_footnotes_serialize_to_mdast() {
+	let document = parse(
+		"3. [ ] a\n\n   b\n\n| c | d |\n| :- | - |\n| e[^1] |\n\n[^1]: f",
+	);
+	assert_eq!(
+		serde_json::to_value(&document).unwrap()["children"],
+		json!([
+			{
+				"type": "list",
+				"ordered": true,
+				"start": 3,
+				"spread": true,
+				"children": [{
+					"type": "listItem",
+					"checked": false,
+					"spread": true,
+					"children": [
+						{ "type": "paragraph", "children": [{ "type": "text", "value": "a" }] },
+						{ "type": "paragraph", "children": [{ "type": "text", "value": "b" }] }
+					]
+				}]
+			},
+			{
+				"type": "table",
+				"align": ["left", null],
+				"children": [
+					{
+						"type": "tableRow",
+						"children": [
+							{ "type": "tableCell", "children": [{ "type": "text", "value": "c" }] },
+							{ "type": "tableCell", "children": [{ "type": "text", "value": "d" }] }
+						]
+					},
+					{
+						"type": "tableRow",
+						"children": [
+							{
+								"type": "tableCell",
+								"children": [
+									{ "type": "text", "value": "e" },
+									{ "type": "footnoteReference", "identifier": "1", "label": "1" }
+								]
+							},
+							{ "type": "tableCell", "children": [] }
+						]
+					}
+				]
+			},
+			{
+				"type": "footnoteDefinition",
+				"identifier": "1",
+				"label": "1",
+				"children": [{ "type": "paragraph", "children": [{ "type": "text", "value": "f" }] }]
+			}
+		])
+	);
+}
+
+#[test]
+fn documents_survive_a_round_trip() {
+	let options = Options::default();
+	let source = concat!(
+		"# a {#b}\n\n",
+		"> - c **d** ~~e~~ `f`\n>   g\n\n",
+		"1. [h](i) ![j](k.png \"u\"){width=30} <http://l.m>\n\n",
+		"***\n\n",
+		"```rust\nn\n```\n\n",
+		"| o | p |\n| -: | :-: |\n| q |\n\n",
+		"<details>\n<summary>r</summary>\n\ns[^1]\n</details>\n\n",
+		"[^1]: t",
+	);
+	let document = Parser::parse(source, &options);
+	let json = serde_json::to_string(&document).unwrap();
+	let imported: Document = serde_json::from_str(&json).unwrap();
+	assert_eq!(
+		Parser::render_document(&imported, &options),
+		Parser::render_document(&document, &options)
+	);
+	assert_eq!(serde_json::to_string(&imported).unwrap(), json);
+}
+
+#[test]
+fn documents_of_remark_are_rendered() {
+	// As printed by `remark().parse()`, including positions
+	let json = json!({
+		"type": "root",
+		"children": [
+			{
+				"type": "heading",
+				"depth": 2,
+				"children": [{
+					"type": "text",
+					"value": "Hi",
+					"position": {
+						"start": { "line": 1, "column": 4, "offset": 3 },
+						"end": { "line": 1, "column": 6, "offset": 5 }
+					}
+				}],
+				"position": {
+					"start": { "line": 1, "column": 1, "offset": 0 },
+					"end": { "line": 1, "column": 6, "offset": 5 }
+				}
+			},
+			{
+				"type": "paragraph",
+				"children": [
+					{ "type": "link", "url": "https://a.b", "title": null, "children": [
+						{ "type": "text", "value": "x" }
+					]},
+					{ "type": "html", "value": "<b>" }
+				]
+			},
+			{ "type": "list", "ordered": false, "start": null, "spread": false, "children": [
+				{ "type": "listItem", "spread": false, "checked": null, "children": [
+					{ "type": "paragraph", "children": [{ "type": "text", "value": "y" }] }
+				]}
+			]}
+		]
+	});
+	let document: Document = serde_json::from_value(json).unwrap();
+	assert_eq!(
+		Parser::render_document(&document, &Options::default()),
+		concat!(
+			"<h2>Hi</h2>\n",
+			"<p><a href=\"https://a.b\">x</a><b></p>\n",
+			"<ul>\n<li>y</li>\n</ul>\n"
+		)
+	);
+}
+
+#[test]
+fn references_of_remark_are_resolved() {
+	// As printed by `remark().parse()` for
+	// "[a *b*][Ref] ![c][ref] [d][e]\n\n[ref]: https://a.b \"T\"\n"
+	let json = json!({
+		"type": "root",
+		"children": [
+			{
+				"type": "paragraph",
+				"children": [
+					{
+						"type": "linkReference",
+						"children": [
+							{ "type": "text", "value": "a " },
+							{ "type": "emphasis", "children": [
+								{ "type": "text", "value": "b" }
+							]}
+						],
+						"label": "Ref",
+						"identifier": "ref",
+						"referenceType": "full"
+					},
+					{ "type": "text", "value": " " },
+					{
+						"type": "imageReference",
+						"alt": "c",
+						"label": "ref",
+						"identifier": "ref",
+						"referenceType": "full"
+					},
+					{ "type": "text", "value": " " },
+					{
+						"type": "linkReference",
+						"children": [{ "type": "text", "value": "d" }],
+						"label": "e",
+						"identifier": "e",
+						"referenceType": "full"
+					}
+				]
+			},
+			{
+				"type": "definition",
+				"identifier": "ref",
+				"label": "ref",
+				"title": "T",
+				"url": "https://a.b"
+			}
+		]
+	});
+	let document: Document = serde_json::from_value(json).unwrap();
+	assert_eq!(
+		Parser::render_document(&document, &Options::default()),
+		concat!(
+			"<p><a href=\"https://a.b\" title=\"T\">a <em>b</em></a> ",
+			"<img src=\"https://a.b\" alt=\"c\" title=\"T\" ",
+			"referrerpolicy=\"no-referrer\"> d</p>\n"
+		)
+	);
+}
+
+#[test]
+fn invalid_documents_are_refused() {
+	let error = |json: serde_json::Value| {
+		serde_json::from_value::<Document>(json)
+			.unwrap_err()
+			.to_string()
+	};
+	assert_eq!(
+		error(json!({ "type": "paragraph", "children": [] })),
+		"expected a `root` node, found `paragraph`"
+	);
+	assert_eq!(
+		error(json!({ "type": "root", "children": [
+			{ "type": "heading", "depth": 7, "children": [] }
+		]})),
+		"heading depth 7 is not 1 to 6"
+	);
+	assert_eq!(
+		error(json!({ "type": "root", "children": [
+			{ "type": "paragraph", "children": [{ "type": "thematicBreak" }] }
+		]})),
+		"`thematicBreak` is not allowed as inline content"
+	);
+	assert!(
+		error(json!({ "type": "root", "children": [{ "type": "yaml" }] }))
+			.contains("unknown variant `yaml`")
+	);
+}
+
+#[test]
+fn nodes_of_syntax_extensions_are_directives() {
+	let document = Document {
+		blocks: vec![
+			Block::Paragraph(vec![Inline::Custom(Custom {
+				name:     "wikilink".to_string(),
+				value:    "Main Page".to_string(),
+				children: vec![Inline::Text("home".to_string())],
+			})]),
+			Block::Custom(Custom {
+				name:     "shortcode".to_string(),
+				value:    String::new(),
+				children: Vec::new(),
+			}),
+		],
+	};
+	let json = serde_json::to_value(&document).unwrap();
+	assert_eq!(
+		json["children"],
+		json!([
+			{
+				"type": "paragraph",
+				"children": [{
+					"type": "textDirective",
+					"name": "wikilink",
+					"attributes": { "value": "Main Page" },
+					"children": [{ "type": "text", "value": "home" }]
+				}]
+			},
+			{
+				"type": "leafDirective",
+				"name": "shortcode",
+				"attributes": {},
+				"children": []
+			}
+		])
+	);
+	assert_eq!(serde_json::from_value::<Document>(json).unwrap(), document);
+}
@@ -0,0 +1,241 @@
+//! Inputs that are known to make markdown parsers crash or take quadratic
+//! time. Every test renders a large input and fails if that takes too long,
+//! which a linear parser is far below even in debug builds.
+
+use std::time::{
+	Duration,
+	Instant,
+};
+
+use gfm::{
+	Error,
+	Options,
+	ParseErrorAction,
+	Parser,
+};
+
+const N: usize = 50_000;
+
+fn assert_renders_quickly(source: &str) -> String {
+	let start = Instant::now();
+	let html = Parser::render(source);
+	let elapsed = start.elapsed();
+	assert!(
+		elapsed < Duration::from_secs(10),
+		"rendering {} bytes took {:?}",
+		source.len(),
+		elapsed
+	);
+	html
+}
+
+#[test]
+fn nested_block_quotes() {
+	let html = assert_renders_quickly(&format!("{}a", "> ".repeat(N)));
+	assert_eq!(html.matches("<blockquote>").count(), 64);
+}
+
+#[test]
+fn nested_lists() {
+	assert_renders_quickly(&format!("{}a", "- ".repeat(N)));
+	assert_renders_quickly(&format!("{}a", "1. ".repeat(N)));
+	let indented: String = (0..2_000)
+		.map(|i| format!("{}- a\n", "  ".repeat(i)))
+		.collect();
+	assert_renders_quickly(&indented);
+}
+
+#[test]
+fn nested_details() {
+	assert_renders_quickly(&format!(
+		"{}a\n{}",
+		"<details>\n".repeat(N),
+		"</details>\n".repeat(N)
+	));
+}
+
+#[test]
+fn nested_footnotes() {
+	assert_renders_quickly(&format!("{}a", "[^a]: ".repeat(N)));
+}
+
+#[test]
+fn blank_lines_in_nested_lists() {
+	assert_renders_quickly(&format!("{}a{}", "- ".repeat(N), "\n".repeat(N)));
+}
+
+#[test]
+fn emphasis_runs() {
+	assert_renders_quickly(&"*".repeat(N));
+	assert_renders_quickly(&"*a ".repeat(N));
+	assert_renders_quickly(&"a* ".repeat(N));
+	assert_renders_quickly(&"*a **a ".repeat(N));
+	assert_renders_quickly(&"_a __a ".repeat(N));
+	assert_renders_quickly(&"**a *".repeat(N));
+}
+
+#[test]
+fn nested_emphasis() {
+	let html = assert_renders_quickly(&format!(
+		"{}a{}",
+		"*a ".repeat(N),
+		" a*".repeat(N)
+	));
+	assert_eq!(html.matches("<em>").count(), 64);
+}
+
+#[test]
+fn brackets() {
+	assert_renders_quickly(&"[".repeat(N));
+	assert_renders_quickly(&"]".repeat(N));
+	assert_renders_quickly(&"[a](".repeat(N));
+	assert_renders_quickly(&"![".repeat(N));
+	assert_renders_quickly(&"[]".repeat(N));
+}
+
+#[test]
+fn nested_links() {
+	assert_renders_quickly(&format!("{}a{}", "[".repeat(N), "](b)".repeat(N)));
+	assert_renders_quickly(&format!("{}a{}", "![".repeat(N), "](b)".repeat(N)));
+}
+
+#[test]
+fn backticks() {
+	let source: String =
+		(1..320).map(|i| format!("{}a", "`".repeat(i))).collect();
+	assert_renders_quickly(&source);
+	assert_renders_quickly(&"`a``".repeat(N));
+}
+
+#[test]
+fn angle_brackets() {
+	assert_renders_quickly(&format!("{}>", "<".repeat(N)));
+	assert_renders_quickly(&"<a".repeat(N));
+}
+
+#[test]
+fn autolink_candidates() {
+	assert_renders_quickly(&format!("{}@", "a_".repeat(N)));
+	assert_renders_quickly(&"www._".repeat(N));
+	assert_renders_quickly(&"http://a_".repeat(N));
+}
+
+#[test]
+fn spaces() {
+	assert_renders_quickly(&format!("a{}b", " ".repeat(N)));
+	assert_renders_quickly(&format!("a{}\nb", " ".repeat(N)));
+}
+
+#[test]
+fn escapes_and_strikethrough() {
+	assert_renders_quickly(&"\\".repeat(N));
+	assert_renders_quickly(&"~~a".repeat(N));
+}
+
+#[test]
+fn unclosed_raw_html() {
+	for markup in ["<a b=\"", "<a b='", "<!--", "<?", "<![CDATA[", "<!A"] {
+		assert_renders_quickly(&format!("a {}", markup.repeat(N)));
+		assert_renders_quickly(&format!("{}\n", markup).repeat(N));
+	}
+	assert_renders_quickly(&format!("<div>\n{}", "<a b\n".repeat(N)));
+}
+
+#[test]
+fn table_with_many_missing_cells() {
+	let source = format!(
+		"{}\n{}\n{}",
+		"|a".repeat(1_000),
+		"|-".repeat(1_000),
+		"a\n".repeat(N)
+	);
+	let html = assert_renders_quickly(&source);
+	assert!(html.len() < 64 * 1024 * 1024);
+}
+
+/// Renders random documents made of characters with a meaning in markdown.
+#[test]
+fn random_input_does_not_panic() {
+	const ALPHABET: &[&str] = &[
+		"*",
+		"_",
+		"`",
+		"~",
+		"[",
+		"]",
+		"(",
+		")",
+		"!",
+		"<",
+		">",
+		"#",
+		"-",
+		"+",
+		"|",
+		":",
+		"\\",
+		"\n",
+		" ",
+		"\t",
+		"1.",
+		"a",
+		"é",
+		"@",
+		"www.",
+		"http://",
+		"[^",
+		"<details>",
+		"</details>",
+		"```",
+		"    ",
+	];
+	let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+	let mut random = move || {
+		state ^= state << 13;
+		state ^= state >> 7;
+		state ^= state << 17;
+		state as usize
+	};
+
+	let skip = Options {
+		on_parse_error: ParseErrorAction::Skip,
+		..Default::default()
+	};
+	for _ in 0..5_000 {
+		let len = random() % 64;
+		let source: String = (0..len)
+			.map(|_| ALPHABET[random() % ALPHABET.len()])
+			.collect();
+		Parser::render(&source);
+		Parser::render_with_options(&source, &skip);
+	}
+}
+
+#[test]
+fn input_larger_than_the_limit_is_refused() {
+	let options = Options {
+		max_input_size: 8,
+		..Default::default()
+	};
+	assert_eq!(
+		Parser::try_render("some more than eight bytes", &options),
+		Err(Error::InputTooLarge {
+			size:  26,
+			limit: 8,
+		})
+	);
+	assert!(Parser::try_render("eight b.", &options).is_ok());
+}
+
+#[test]
+fn nesting_deeper_than_the_limit_is_refused() {
+	let options = Options {
+		max_nesting: 4,
+		..Default::default()
+	};
+	assert_eq!(
+		Parser::try_render("- a\n\n> > - b\n> > > > > c", &options),
+		Err(Error::NestingTooDeep { line: 4, limit: 4 })
+	);
+	assert!(Parser::try_render("> > - b\n> > > > c", &options).is_ok());
+}
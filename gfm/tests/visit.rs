@@ -0,0 +1,206 @@
+//! Documents can be inspected and rewritten before they are rendered.
+
+use gfm::{
+	Block,
+	Inline,
+	Options,
+	Parser,
+	Visitor,
+	VisitorMut,
+};
+
+fn transform(source: &str, visitor: &mut impl VisitorMut) -> String {
+	let options = Options::default();
+	let mut document = Parser::parse(source, &options);
+	document.visit_mut(visitor);
+	Parser::render_document(&document, &options)
+}
+
+/// Rewrites the destination of every link and autolink.
+struct RewriteLinks;
+
+impl VisitorMut for RewriteLinks {
+	fn visit_inline_mut(&mut self, inline: &mut Inline) {
+		match inline {
+			Inline::Link(url, _, _) | Inline::Autolink(url, _) => {
+				*url = url.replace("http://old.org", "https://new.org")
+			}
+			_ => {}
+		}
+		gfm::walk_inline_mut(self, inline);
+	}
+}
+
+/// Demotes every heading by one level.
+struct DemoteHeadings;
+
+impl VisitorMut for DemoteHeadings {
+	fn visit_block_mut(&mut self, block: &mut Block) {
+		if let Block::Header(level, _, _) = block {
+			*level = (*level + 1).min(6);
+		}
+		gfm::walk_block_mut(self, block);
+	}
+}
+
+/// Removes every image.
+struct StripImages;
+
+impl VisitorMut for StripImages {
+	fn visit_inlines_mut(&mut self, inlines: &mut Vec<Inline>) {
+		inlines.retain(|i| !matches!(i, Inline::Image(..)));
+		gfm::walk_inlines_mut(self, inlines);
+	}
+}
+
+/// Gives every heading without an id one made of its text.
+struct InjectAnchors;
+
+impl VisitorMut for InjectAnchors {
+	fn visit_block_mut(&mut self, block: &mut Block) {
+		if let Block::Header(_, inlines, id @ None) = block {
+			let text: String = inlines
+				.iter()
+				.filter_map(|i| match i {
+					Inline::Text(t) => Some(t.as_str()),
+					_ => None,
+				})
+				.collect();
+			*id = Some(text.to_lowercase().replace(' ', "-"));
+		}
+		gfm::walk_block_mut(self, block);
+	}
+}
+
+#[test]
+fn rendering_a_parsed_document_is_rendering_the_source() {
+	let options = Options::default();
+	for source in [
+		"# foo\n\n> - bar *baz*\n>   1. [qux](http://a.b)\n\n```rust\nx\n```",
+		"| a | b |\n| - | :-: |\n| `c` | www.d.org |\n\n- [x] e[^1]\n\n[^1]: f",
+		"<details>\n<summary>g ~~h~~</summary>\n\ni  \nj \
+		 ![k](l.png)\n</details>",
+	] {
+		let document = Parser::parse(source, &options);
+		assert_eq!(
+			Parser::render_document(&document, &options),
+			Parser::render_with_options(source, &options)
+		);
+	}
+}
+
+#[test]
+fn links_are_rewritten() {
+	assert_eq!(
+		transform("[a](http://old.org/x) <http://old.org/y>", &mut RewriteLinks),
+		"<p><a href=\"https://new.org/x\">a</a> <a \
+		 href=\"https://new.org/y\">http://old.org/y</a></p>\n"
+	);
+}
+
+#[test]
+fn headings_are_demoted() {
+	assert_eq!(
+		transform("# a\n\n> ###### b", &mut DemoteHeadings),
+		"<h2>a</h2>\n<blockquote>\n<h6>b</h6>\n</blockquote>\n"
+	);
+}
+
+#[test]
+fn images_are_stripped_everywhere() {
+	assert_eq!(
+		transform(
+			"![a](a.png)\n\n- b **![c](c.png)**\n\n| ![d](d.png) |\n| - |",
+			&mut StripImages
+		),
+		concat!(
+			"<p></p>\n",
+			"<ul>\n<li>b <strong></strong></li>\n</ul>\n",
+			"<table>\n<thead>\n<tr>\n<th></th>\n</tr>\n</thead>\n</table>\n"
+		)
+	);
+}
+
+#[test]
+fn anchors_are_injected() {
+	assert_eq!(
+		transform("# Foo Bar\n\n## Baz {#qux}", &mut InjectAnchors),
+		"<h1 id=\"foo-bar\">Foo Bar</h1>\n<h2 id=\"qux\">Baz</h2>\n"
+	);
+}
+
+#[test]
+fn transformations_compose() {
+	let options = Options::default();
+	let mut document = Parser::parse("# a\n\n![b](b.png)", &options);
+	document.visit_mut(&mut DemoteHeadings);
+	document.visit_mut(&mut InjectAnchors);
+	document.visit_mut(&mut StripImages);
+	assert_eq!(
+		Parser::render_document(&document, &options),
+		"<h2 id=\"a\">a</h2>\n<p></p>\n"
+	);
+}
+
+#[test]
+fn disallowed_destinations_are_not_linked() {
+	struct Inject;
+
+	impl VisitorMut for Inject {
+		fn visit_inline_mut(&mut self, inline: &mut Inline) {
+			match inline {
+				Inline::Link(url, _, _) => *url = "javascript:alert(1)".into(),
+				Inline::Autolink(url, text) => {
+					*url = "javascript:alert(2)".into();
+					*text = "<script>".into();
+				}
+				_ => {}
+			}
+		}
+	}
+
+	assert_eq!(
+		transform("[a](b) <http://c.d>", &mut Inject),
+		"<p>a &lt;script&gt;</p>\n"
+	);
+}
+
+#[test]
+fn nodes_are_entered_and_exited() {
+	/// Records the nesting of block quotes.
+	#[derive(Default)]
+	struct Depths {
+		depth: usize,
+		seen:  Vec<(usize, String)>,
+	}
+
+	impl Visitor for Depths {
+		fn visit_block(&mut self, block: &Block) {
+			let quote = matches!(block, Block::BlockQuote(_));
+			if quote {
+				self.depth += 1;
+			}
+			gfm::walk_block(self, block);
+			if quote {
+				self.depth -= 1;
+			}
+		}
+
+		fn visit_inline(&mut self, inline: &Inline) {
+			if let Inline::Text(t) = inline {
+				self.seen.push((self.depth, t.clone()));
+			}
+			gfm::walk_inline(self, inline);
+		}
+	}
+
+	let mut depths = Depths::default();
+	Parser::parse("a\n> b\n> > *c*\n\nd", &Options::default())
+		.visit(&mut depths);
+	assert_eq!(depths.seen, vec![
+		(0, "a".to_string()),
+		(1, "b".to_string()),
+		(2, "c".to_string()),
+		(0, "d".to_string()),
+	]);
+}
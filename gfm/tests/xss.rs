@@ -0,0 +1,313 @@
+//! Markdown and raw html that tries to run scripts in the rendered page.
+//! None of it may come out as an element or attribute that runs code.
+
+use gfm::{
+	LinkAttributes,
+	LinkPolicy,
+	Options,
+	Parser,
+	Sanitizer,
+};
+
+const PAYLOADS: &[&str] = &[
+	// Raw html
+	"<script>alert(1)</script>",
+	"<SCRIPT SRC=//x.y/a.js></SCRIPT>",
+	"<script\n>alert(1)</script\n>",
+	"<img src=x onerror=alert(1)>",
+	"<img src=x ONERROR=alert(1)>",
+	"<img src=\"x\" onerror = 'alert(1)'>",
+	"<img/src=x/onerror=alert(1)>",
+	"<svg onload=alert(1)>",
+	"<svg><script>alert(1)</script></svg>",
+	"<body onload=alert(1)>",
+	"<iframe src=javascript:alert(1)></iframe>",
+	"<iframe srcdoc=\"<script>alert(1)</script>\"></iframe>",
+	"<object data=javascript:alert(1)>",
+	"<embed src=javascript:alert(1)>",
+	"<a href=\"javascript:alert(1)\">a</a>",
+	"<a href='JaVaScRiPt:alert(1)'>a</a>",
+	"<a href=\" javascript:alert(1)\">a</a>",
+	"<a href=\"java&#x09;script:alert(1)\">a</a>",
+	"<a href=\"jav&#x61;script&colon;alert(1)\">a</a>",
+	"<a href=\"&#106;&#97;&#118;&#97;&#115;&#99;&#114;&#105;&#112;&#116;&#58;\
+	 alert(1)\">a</a>",
+	"<a href=\"javascript&NewLine;:alert(1)\">a</a>",
+	"<a href=\"&Jscr;avascript:alert(1)\">a</a>",
+	"<a href=\"vbscript:msgbox(1)\">a</a>",
+	"<a href=\"data:text/html,<script>alert(1)</script>\">a</a>",
+	"<a href=x onclick=alert(1)>a</a>",
+	"<a href=\"x\" style=\"background:url(javascript:alert(1))\">a</a>",
+	"<div style=\"width:expression(alert(1))\">a</div>",
+	"<form action=javascript:alert(1)><button>a</button></form>",
+	"<button formaction=javascript:alert(1)>a</button>",
+	"<math><a xlink:href=javascript:alert(1)>a</a></math>",
+	"<details open ontoggle=alert(1)>",
+	"<input autofocus onfocus=alert(1)>",
+	"<meta http-equiv=refresh content=\"0;url=javascript:alert(1)\">",
+	"<base href=javascript:alert(1)//>",
+	"<link rel=import href=//x.y/a.html>",
+	"<style>@import 'javascript:alert(1)';</style>",
+	"<textarea><script>alert(1)</script></textarea>",
+	"<title><script>alert(1)</script></title>",
+	"<noscript><p title=\"</noscript><img src=x onerror=alert(1)>\">",
+	"<!-- --><script>alert(1)</script><!-- -->",
+	"<!--<img src=x onerror=alert(1)>-->",
+	"<![CDATA[<script>alert(1)</script>]]>",
+	"<?xml <script>alert(1)</script> ?>",
+	"<!DOCTYPE html><script>alert(1)</script>",
+	"<a href=\"x\"\nonclick=alert(1)>a</a>",
+	"<p>\n<img src=x onerror=alert(1)>\n</p>",
+	"<div>\n\n<script>alert(1)</script>\n\n</div>",
+	"<table><tr><td onmouseover=alert(1)>a</td></tr></table>",
+	"<img src=\"x\" alt=\"\" onerror=\"alert(1)\" />",
+	"<img src=\"x` `<script>alert(1)</script>\"` `>",
+	"<img \"\"\"><script>alert(1)</script>\">",
+	"<a href=\"x\" title='\"><script>alert(1)</script>'>a</a>",
+	// Markdown
+	"[a](javascript:alert(1))",
+	"[a](JAVASCRIPT:alert(1))",
+	"[a](javascript&#58;alert(1))",
+	"[a](<javascript:alert(1)>)",
+	"[a](vbscript:msgbox(1))",
+	"[a](data:text/html;base64,PHNjcmlwdD5hbGVydCgxKTwvc2NyaXB0Pg==)",
+	"[a](x \"\\\"onmouseover=alert(1) \\\"\")",
+	"[a](x 'a\" onmouseover=\"alert(1)')",
+	"[a\"onclick=\"alert(1)](x)",
+	"![a](javascript:alert(1))",
+	"![a\" onerror=\"alert(1)](x)",
+	"![a](x\"onerror=\"alert(1))",
+	"![a](<x\" onerror=\"alert(1)>)",
+	"<javascript:alert(1)>",
+	"<vbscript:msgbox(1)>",
+	"<x\" onmouseover=\"alert(1)>",
+	"www.x.y/\"onmouseover=\"alert(1)",
+	"https://x.y/<script>alert(1)</script>",
+	"[a]: javascript:alert(1)\n\n[a]",
+	"# a\" onclick=\"alert(1) {#b\"onclick=\"alert(1)}",
+	"```js\" onclick=\"alert(1)\nalert(1)\n```",
+	"`<script>alert(1)</script>`",
+	"~~<script>alert(1)</script>~~",
+	"a[^b\"onclick=\"alert(1)]\n\n[^b\"onclick=\"alert(1)]: c",
+	"| <img src=x onerror=alert(1)> |\n| - |",
+	"- [ ] <img src=x onerror=alert(1)>",
+	"> <script>alert(1)</script>",
+	"<details>\n<summary><img src=x onerror=alert(1)></summary>\n</details>",
+	"\\<script>alert(1)\\</script>",
+	"&lt;script&gt;alert(1)&lt;/script&gt;",
+];
+
+/// Tags that may be written by the renderer itself, besides those the
+/// default sanitizer allows.
+const RENDERED_TAGS: &[&str] = &["input", "section"];
+
+/// Finds every tag in the rendered html, along with its attributes as
+/// written.
+fn tags(html: &str) -> Vec<(String, String)> {
+	let mut tags = Vec::new();
+	let mut rest = html;
+	while let Some(start) = rest.find('<') {
+		rest = &rest[start + 1..];
+		let end = rest.find('>').expect("tags are closed");
+		let tag = rest[..end].trim_start_matches('/');
+		let (name, attributes) = tag.split_once(' ').unwrap_or((tag, ""));
+		tags.push((name.to_string(), attributes.to_string()));
+		rest = &rest[end + 1..];
+	}
+	tags
+}
+
+/// Splits the attributes of a tag written by the renderer or sanitizer,
+/// whose values are always double quoted.
+fn attributes(written: &str) -> Vec<(String, String)> {
+	let mut attributes = Vec::new();
+	let mut rest = written.trim_end_matches('/').trim();
+	while !rest.is_empty() {
+		let end = rest.find([' ', '=']).unwrap_or(rest.len());
+		let name = rest[..end].to_string();
+		rest = &rest[end..];
+		let value = match rest.strip_prefix("=\"") {
+			Some(quoted) => {
+				let end = quoted.find('"').expect("values are quoted");
+				rest = &quoted[end + 1..];
+				quoted[..end].to_string()
+			}
+			None => String::new(),
+		};
+		attributes.push((name, value));
+		rest = rest.trim_start();
+	}
+	attributes
+}
+
+fn assert_is_safe(source: &str, html: &str) {
+	let allowed = Sanitizer::default();
+	for (name, written) in tags(html) {
+		let name = name.to_ascii_lowercase();
+		assert!(
+			!matches!(
+				name.as_str(),
+				"script" |
+					"style" | "iframe" |
+					"object" | "embed" |
+					"svg" | "math" | "form" |
+					"meta" | "base" | "link" |
+					"textarea" | "title" |
+					"body"
+			),
+			"<{}> in {:?} rendered from {:?}",
+			name,
+			html,
+			source
+		);
+		assert!(
+			allowed.allows(&name) ||
+				RENDERED_TAGS.contains(&name.as_str()) ||
+				name.starts_with('!'),
+			"<{}> in {:?} rendered from {:?}",
+			name,
+			html,
+			source
+		);
+		for (attribute, value) in attributes(&written) {
+			assert!(
+				!attribute.to_ascii_lowercase().starts_with("on") &&
+					attribute != "style",
+				"{} in {:?} rendered from {:?}",
+				attribute,
+				html,
+				source
+			);
+			let value = value.to_ascii_lowercase();
+			assert!(
+				!(value.starts_with("javascript:") ||
+					value.starts_with("vbscript:") ||
+					value.starts_with("data:text")),
+				"{}=\"{}\" in {:?} rendered from {:?}",
+				attribute,
+				value,
+				html,
+				source
+			);
+		}
+	}
+}
+
+#[test]
+fn payloads_are_rendered_safely() {
+	for payload in PAYLOADS.iter() {
+		assert_is_safe(payload, &Parser::render(payload));
+		// Inside of inline content and of containers
+		let inline = format!("a {} b", payload);
+		assert_is_safe(&inline, &Parser::render(&inline));
+		let nested = format!("> - {}", payload.replace('\n', "\n>   "));
+		assert_is_safe(&nested, &Parser::render(&nested));
+	}
+}
+
+#[test]
+fn payloads_are_escaped_without_raw_html() {
+	let options = Options {
+		sanitizer: Sanitizer::empty(),
+		..Default::default()
+	};
+	for payload in PAYLOADS.iter() {
+		let html = Parser::render_with_options(payload, &options);
+		assert_is_safe(payload, &html);
+	}
+}
+
+#[test]
+fn allowed_html_is_kept() {
+	assert_eq!(
+		Parser::render("<kbd>Ctrl</kbd>+<kbd>C</kbd>"),
+		"<p><kbd>Ctrl</kbd>+<kbd>C</kbd></p>\n"
+	);
+	assert_eq!(
+		Parser::render(
+			"<div align=\"center\">\n<img src=\"a.png\" width=10>\n</div>"
+		),
+		"<div align=\"center\">\n<img src=\"a.png\" width=\"10\">\n</div>\n"
+	);
+	assert_eq!(
+		Parser::render("<a href=\"https://x.y/?a=1&amp;b=2\">c</a>"),
+		"<p><a href=\"https://x.y/?a=1&amp;b=2\">c</a></p>\n"
+	);
+}
+
+#[test]
+fn tagfilter_is_always_applied() {
+	let options = Options {
+		sanitizer: Sanitizer::default().allow("script", &[]),
+		..Default::default()
+	};
+	assert_eq!(
+		Parser::render_with_options("<script>alert(1)</script>", &options),
+		"&lt;script&gt;alert(1)&lt;/script&gt;\n"
+	);
+}
+
+#[test]
+fn raw_html_follows_the_link_policy() {
+	let options = Options {
+		sanitizer: Sanitizer::default().allow("a", &["target", "rel"]),
+		link_policy: LinkPolicy::default()
+			.schemes(&["https", "mailto"])
+			.external(LinkAttributes {
+				target: Some("_blank".to_string()),
+				rel:    Some("noopener".to_string()),
+			}),
+		..Default::default()
+	};
+	let render = |source| Parser::render_with_options(source, &options);
+	assert_eq!(
+		render("<a href=\"ftp://z/c\">raw</a>"),
+		"<p><a>raw</a></p>\n"
+	);
+	assert_eq!(render("<a href=\"http://z/c\">"), "<a>\n");
+	assert_eq!(
+		render("<img src=\"http://z/i.png\" alt=\"i\">"),
+		"<img alt=\"i\">\n"
+	);
+	assert_eq!(
+		render("<a href=\"https://z/c\" target=\"_top\" rel=\"x\">c</a>"),
+		"<p><a href=\"https://z/c\" target=\"_blank\" \
+		 rel=\"noopener\">c</a></p>\n"
+	);
+	assert_eq!(
+		render("<a href=\"mailto:a@z\" target=\"_top\">a</a>"),
+		"<p><a href=\"mailto:a@z\" target=\"_top\">a</a></p>\n"
+	);
+}
+
+#[test]
+fn refused_links_are_rendered_as_their_text() {
+	let options = Options {
+		link_policy: LinkPolicy::default().schemes(&["https"]),
+		..Default::default()
+	};
+	let render = |source| Parser::render_with_options(source, &options);
+	assert_eq!(
+		render("a [b *c*](javascript:d) e"),
+		"<p>a b <em>c</em> e</p>\n"
+	);
+	assert_eq!(render("a [b *c*](http://d) e"), "<p>a b <em>c</em> e</p>\n");
+	assert_eq!(render("[b](javascript:d \"t\")"), "<p>b</p>\n");
+}
+
+#[test]
+fn text_is_escaped_only_where_needed() {
+	assert_eq!(
+		Parser::render("a [b] {c} | (d) ~e 'f' \\g"),
+		"<p>a [b] {c} | (d) ~e 'f' \\g</p>\n"
+	);
+	assert_eq!(
+		Parser::render("[a](https://b \"c 'd' <e>\")"),
+		"<p><a href=\"https://b\" title=\"c &#39;d&#39; \
+		 &lt;e&gt;\">a</a></p>\n"
+	);
+	assert_eq!(
+		Parser::render("# a & \"b\" {#c&d}"),
+		"<h1 id=\"c&amp;d\">a &amp; &quot;b&quot;</h1>\n"
+	);
+}
@@ -0,0 +1,269 @@
+use base64::{
+	engine::general_purpose::STANDARD,
+	Engine,
+};
+use log::{
+	debug,
+	warn,
+};
+use std::{
+	fs,
+	path::{
+		Component,
+		Path,
+	},
+};
+
+/// Embeds the local images of a document, and the fonts and images its
+/// stylesheets refer to, as `data:` uris. Paths are resolved against
+/// `base_dir`, files outside of it or that cannot be read are left as they
+/// are.
+///
+/// Warns if the document is larger than `size_warning` bytes afterwards.
+pub fn embed_resources(
+	html: &str,
+	base_dir: &Path,
+	size_warning: u64,
+) -> String {
+	let html = embed_images(html, base_dir);
+	let html = embed_stylesheet_urls(&html, base_dir);
+	if html.len() as u64 > size_warning {
+		warn!(
+			"self-contained document is {} KiB, more than the {} KiB it \
+			 should be at most",
+			html.len() / 1024,
+			size_warning / 1024
+		);
+	}
+	html
+}
+
+/// Replaces the `src` of `<img>` tags that point at local files.
+fn embed_images(html: &str, base_dir: &Path) -> String {
+	let mut embedded = String::with_capacity(html.len());
+	let mut rest = html;
+	while let Some(start) = rest.find("<img ") {
+		let end = rest[start..].find('>').map_or(rest.len(), |i| start + i);
+		let tag = &rest[start..end];
+		let source = tag.find(" src=\"").and_then(|i| {
+			let value_start = start + i + 6;
+			let value_end = value_start + rest[value_start..end].find('"')?;
+			Some(value_start..value_end)
+		});
+		match source {
+			Some(source) => {
+				embedded.push_str(&rest[..source.start]);
+				let value = &rest[source.clone()];
+				match data_uri(&unescape(value), base_dir) {
+					Some(uri) => embedded.push_str(&uri),
+					None => embedded.push_str(value),
+				}
+				rest = &rest[source.end..];
+			}
+			None => {
+				embedded.push_str(&rest[..end]);
+				rest = &rest[end..];
+			}
+		}
+	}
+	embedded.push_str(rest);
+	embedded
+}
+
+/// Replaces the `url()`s of `<style>` elements that point at local files,
+/// eg. fonts.
+fn embed_stylesheet_urls(html: &str, base_dir: &Path) -> String {
+	let mut embedded = String::with_capacity(html.len());
+	let mut rest = html;
+	while let Some(start) = rest.find("<style>") {
+		let start = start + "<style>".len();
+		let end = rest[start..]
+			.find("</style>")
+			.map_or(rest.len(), |i| start + i);
+		embedded.push_str(&rest[..start]);
+		embedded.push_str(&embed_css_urls(&rest[start..end], base_dir));
+		rest = &rest[end..];
+	}
+	embedded.push_str(rest);
+	embedded
+}
+
+/// Replaces the `url()`s of a stylesheet that point at local files, eg.
+/// fonts, which are resolved against `base_dir` and have to be inside it.
+pub fn embed_css_urls(css: &str, base_dir: &Path) -> String {
+	let mut embedded = String::with_capacity(css.len());
+	let mut rest = css;
+	while let Some(start) = rest.find("url(") {
+		let start = start + "url(".len();
+		let Some(end) = rest[start..].find(')').map(|i| start + i) else {
+			break;
+		};
+		let url = rest[start..end].trim().trim_matches(['"', '\'']);
+		embedded.push_str(&rest[..start]);
+		match data_uri(url, base_dir) {
+			Some(uri) => embedded.push_str(&format!("\"{}\"", uri)),
+			None => embedded.push_str(&rest[start..end]),
+		}
+		rest = &rest[end..];
+	}
+	embedded.push_str(rest);
+	embedded
+}
+
+/// Reads a local image or font as a `data:` uri. Urls with a scheme or host,
+/// files outside of `base_dir` and files of other types are not embedded.
+fn data_uri(url: &str, base_dir: &Path) -> Option<String> {
+	if url.starts_with("//") || url.starts_with('#') || has_scheme(url) {
+		return None;
+	}
+	let end = url.find(['?', '#']).unwrap_or(url.len());
+	let decoded = percent_decode(&url[..end]);
+	let path = Path::new(decoded.trim_start_matches('/'));
+	if !path
+		.components()
+		.all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+	{
+		warn!(
+			"Not embedding {}, it is outside of {}",
+			url,
+			base_dir.display()
+		);
+		return None;
+	}
+	let path = base_dir.join(path);
+	let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+	let mime = mime_type(&extension)?;
+	match fs::read(&path) {
+		Ok(bytes) => {
+			debug!("Embedding {} ({}B)", path.display(), bytes.len());
+			Some(format!("data:{};base64,{}", mime, STANDARD.encode(bytes)))
+		}
+		Err(e) => {
+			warn!("Cannot embed {}: {}", path.display(), e);
+			None
+		}
+	}
+}
+
+/// Media type of the images and fonts that are embedded.
+fn mime_type(extension: &str) -> Option<&'static str> {
+	Some(match extension {
+		"png" => "image/png",
+		"jpg" | "jpeg" => "image/jpeg",
+		"gif" => "image/gif",
+		"svg" => "image/svg+xml",
+		"webp" => "image/webp",
+		"avif" => "image/avif",
+		"ico" => "image/x-icon",
+		"woff" => "font/woff",
+		"woff2" => "font/woff2",
+		"ttf" => "font/ttf",
+		"otf" => "font/otf",
+		_ => return None,
+	})
+}
+
+/// Checks whether a url starts with a scheme, eg. `https:`.
+fn has_scheme(url: &str) -> bool {
+	let end = url.find(['/', '?', '#']).unwrap_or(url.len());
+	url[..end].split_once(':').is_some_and(|(scheme, _)| {
+		scheme.starts_with(|c: char| c.is_ascii_alphabetic()) &&
+			scheme
+				.chars()
+				.all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c))
+	})
+}
+
+/// Reverts the escaping of an attribute value written by the renderer.
+fn unescape(value: &str) -> String {
+	value
+		.replace("&quot;", "\"")
+		.replace("&#39;", "'")
+		.replace("&lt;", "<")
+		.replace("&gt;", ">")
+		.replace("&amp;", "&")
+}
+
+/// Decodes the `%20` escapes of a path.
+fn percent_decode(path: &str) -> String {
+	let bytes = path.as_bytes();
+	let mut decoded = Vec::with_capacity(bytes.len());
+	let mut i = 0;
+	while i < bytes.len() {
+		let hex = bytes
+			.get(i + 1..i + 3)
+			.and_then(|h| std::str::from_utf8(h).ok())
+			.and_then(|h| u8::from_str_radix(h, 16).ok());
+		match (bytes[i], hex) {
+			(b'%', Some(byte)) => {
+				decoded.push(byte);
+				i += 3;
+			}
+			(byte, _) => {
+				decoded.push(byte);
+				i += 1;
+			}
+		}
+	}
+	String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_utils::temp_dir;
+
+	#[test]
+	fn test_local_resources_are_embedded() {
+		let dir = temp_dir("bundle");
+		fs::create_dir_all(dir.join("a b")).unwrap();
+		fs::write(dir.join("a b/c.png"), b"png").unwrap();
+		fs::write(dir.join("d.woff2"), b"font").unwrap();
+		fs::write(dir.join("e.txt"), b"text").unwrap();
+
+		let html = concat!(
+			"<style>\n@font-face { src: url('d.woff2'); }\n</style>\n",
+			"<p><img src=\"a%20b/c.png?v=1\" alt=\"c\"> ",
+			"<img src=\"https://x.y/c.png\"> <img src=\"e.txt\"> ",
+			"<img src=\"missing.png\"></p>\n"
+		);
+		let embedded = embed_resources(html, &dir, u64::MAX);
+		fs::remove_dir_all(&dir).unwrap();
+		assert_eq!(
+			embedded,
+			concat!(
+				"<style>\n@font-face { src: ",
+				"url(\"data:font/woff2;base64,Zm9udA==\"); }\n</style>\n",
+				"<p><img src=\"data:image/png;base64,cG5n\" alt=\"c\"> ",
+				"<img src=\"https://x.y/c.png\"> <img src=\"e.txt\"> ",
+				"<img src=\"missing.png\"></p>\n"
+			)
+		);
+	}
+
+	#[test]
+	fn test_files_outside_of_the_base_dir_are_kept() {
+		let dir = temp_dir("bundle-outside");
+		fs::create_dir_all(dir.join("base/a")).unwrap();
+		fs::write(dir.join("secret.png"), b"png").unwrap();
+		fs::write(dir.join("base/b.png"), b"png").unwrap();
+
+		let base = dir.join("base");
+		let secret = dir.join("secret.png").display().to_string();
+		let embedded = ["b.png", "/b.png", "./a/../b.png"]
+			.map(|url| data_uri(url, &base).is_some());
+		let kept = ["../secret.png", "a/%2E%2E/../secret.png", &secret]
+			.map(|url| data_uri(url, &base).is_some());
+		fs::remove_dir_all(&dir).unwrap();
+		assert_eq!(embedded, [true, true, false]);
+		assert_eq!(kept, [false, false, false]);
+	}
+
+	#[test]
+	fn test_urls_with_a_scheme_are_kept() {
+		assert!(has_scheme("https://x.y/a.png"));
+		assert!(has_scheme("data:image/png;base64,"));
+		assert!(!has_scheme("a/b:c.png"));
+		assert!(!has_scheme("a.png"));
+	}
+}
@@ -1,6 +1,12 @@
 use crate::error::Error;
 use crate::fs;
 
+use crate::page::{
+	Margins,
+	Orientation,
+	PageSize,
+};
+use crate::template::Theme;
 use crate::{
 	error::Result,
 	DEFAULT_CONFIG,
@@ -9,11 +15,41 @@ use serde::{
 	Deserialize,
 	Serialize,
 };
+use std::{
+	fmt,
+	path::PathBuf,
+};
 
 #[derive(Debug, Deserialize, Serialize, Default)]
 pub struct Config {
 	/// Configuration values about gfm generation.
-	parse: ParseConfig,
+	pub parse: ParseConfig,
+
+	/// Rules checked by `smd lint`.
+	#[serde(default)]
+	pub lint: LintConfig,
+
+	/// How the destinations of links and images are rewritten, which are
+	/// allowed and the attributes links get.
+	#[serde(default)]
+	pub links: LinksConfig,
+
+	/// How images are loaded, sized and captioned.
+	#[serde(default)]
+	pub images: ImagesConfig,
+
+	/// How html documents are written.
+	#[serde(default)]
+	pub html: HtmlConfig,
+
+	/// Page setup of pdf documents.
+	#[serde(default)]
+	pub pdf: PdfConfig,
+
+	/// External commands the document tree is piped through, in order,
+	/// between parsing and rendering.
+	#[serde(default)]
+	pub filters: Vec<FilterConfig>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Default)]
@@ -39,6 +75,302 @@ pub enum ParseErrorAction {
 	Serialize,
 }
 
+/// Rewriting of link and image destinations for the place the rendered
+/// document is published at.
+#[derive(Debug, Deserialize, Serialize, Default, PartialEq)]
+#[serde(default)]
+pub struct LinksConfig {
+	/// Url relative links and images are resolved against.
+	pub base_url: Option<String>,
+
+	/// Whether relative links to `.md` files point at their `.html` file.
+	pub markdown_to_html: bool,
+
+	/// Absolute urls into the repository, eg.
+	/// `https://github.com/owner/repo/blob/main/`, which are made relative to
+	/// the input. The current directory is taken as the root of the
+	/// repository.
+	pub repository_urls: Vec<String>,
+
+	/// Schemes links and images may use, eg. `https`, or empty to allow
+	/// any scheme. Relative urls are always allowed.
+	pub allowed_schemes: Vec<String>,
+
+	/// Hosts whose links are internal, eg. `docs.example.com`. Relative links
+	/// are internal too, links to other hosts external.
+	pub internal_hosts: Vec<String>,
+
+	/// Attributes of internal links.
+	pub internal: LinkAttributesConfig,
+
+	/// Attributes of external links, eg. `target = "_blank"`.
+	pub external: LinkAttributesConfig,
+}
+
+/// Attributes added to the `<a>` tag of a link.
+#[derive(Debug, Deserialize, Serialize, Default, PartialEq)]
+#[serde(default)]
+pub struct LinkAttributesConfig {
+	/// Where the link opens, eg. `_blank` for a new tab.
+	pub target: Option<String>,
+
+	/// Relationship to the linked page, eg. `noopener noreferrer nofollow`.
+	pub rel: Option<String>,
+}
+
+/// How images are rendered.
+#[derive(Debug, Deserialize, Serialize, Default, PartialEq)]
+#[serde(default)]
+pub struct ImagesConfig {
+	/// Whether images are only loaded once they are about to be shown.
+	pub lazy_loading: bool,
+
+	/// Whether images with a title that are a paragraph of their own are
+	/// rendered as a figure, with the title as its caption.
+	pub figures: bool,
+
+	/// Whether the width and height of local images are read from their
+	/// files, which are looked up next to the markdown file.
+	pub detect_size: bool,
+}
+
+/// How html documents are written.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct HtmlConfig {
+	/// Whether html is written as a single file, with the stylesheet and
+	/// local images and fonts embedded.
+	pub self_contained: bool,
+
+	/// Size in KiB above which a self-contained document is warned about.
+	pub size_warning_kib: u64,
+
+	/// Colors of the built-in stylesheet, `auto`, `light`, `dark` or `none`.
+	pub theme: Theme,
+
+	/// Name of a theme package in the `themes` directory next to the
+	/// configuration file, whose colors, template and stylesheets are used
+	/// instead of the built-in ones.
+	pub theme_package: Option<String>,
+
+	/// Html file with `{{ title }}`, `{{ style }}`, `{{ toc }}`,
+	/// `{{ content }}` and `{{ meta.key }}` placeholders, used instead of
+	/// the built-in template.
+	pub template: Option<PathBuf>,
+
+	/// Whether the built-in print stylesheet is added, which sets the page
+	/// margins and keeps headings, code blocks and table rows from being
+	/// split across pages.
+	pub print_stylesheet: bool,
+
+	/// Stylesheets added after the theme, in order. Local fonts and images
+	/// they refer to are embedded.
+	pub stylesheets: Vec<PathBuf>,
+}
+
+impl Default for HtmlConfig {
+	fn default() -> Self {
+		HtmlConfig {
+			self_contained:   false,
+			size_warning_kib: 10 * 1024,
+			theme:            Theme::default(),
+			theme_package:    None,
+			template:         None,
+			print_stylesheet: true,
+			stylesheets:      Vec::new(),
+		}
+	}
+}
+
+/// Page setup of pdf documents.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[serde(default)]
+pub struct PdfConfig {
+	/// Size of the paper, `a3`, `a4`, `a5`, `letter`, `legal` or a width and
+	/// height, eg. `150mm x 200mm`.
+	pub page_size: PageSize,
+
+	/// `portrait` or `landscape`.
+	pub orientation: Orientation,
+
+	/// Margins of the pages, like the css `margin` property, eg. `18mm 16mm`.
+	/// Unless set, the margins of the theme package or the print stylesheet
+	/// are used.
+	pub margins: Option<Margins>,
+
+	/// How much the content is scaled, between 0.1 and 2.
+	pub scale: f64,
+
+	/// Whether background colors and images are printed.
+	pub print_background: bool,
+
+	/// Pages that are printed, eg. `1-5, 8`, or all of them.
+	pub page_ranges: Option<String>,
+
+	/// Html shown at the top of every page, with `{{ page }}`,
+	/// `{{ pages }}`, `{{ date }}`, `{{ title }}` and `{{ meta.key }}`
+	/// placeholders. It is styled by its own `style` attributes, not by the
+	/// stylesheet of the document.
+	pub header: Option<String>,
+
+	/// Html shown at the bottom of every page, like the header, eg.
+	/// `{{ page }} / {{ pages }}`.
+	pub footer: Option<String>,
+
+	/// Whether the first page, eg. a title page, has no header and footer.
+	/// Its top and bottom margins are left out for that.
+	pub plain_first_page: bool,
+}
+
+impl Default for PdfConfig {
+	fn default() -> Self {
+		PdfConfig {
+			page_size:        PageSize::default(),
+			orientation:      Orientation::default(),
+			margins:          None,
+			scale:            1.0,
+			print_background: true,
+			page_ranges:      None,
+			header:           None,
+			footer:           None,
+			plain_first_page: false,
+		}
+	}
+}
+
+/// An external command that reads the document tree as mdast JSON from its
+/// stdin and writes the transformed tree to its stdout.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct FilterConfig {
+	/// Program to run, looked up in `PATH` unless it is a path.
+	pub command: String,
+
+	/// Arguments passed to the program.
+	#[serde(default)]
+	pub args: Vec<String>,
+
+	/// Seconds the program may run before it is killed.
+	#[serde(default = "FilterConfig::default_timeout")]
+	pub timeout_secs: u64,
+}
+
+impl FilterConfig {
+	fn default_timeout() -> u64 {
+		30
+	}
+}
+
+impl fmt::Display for FilterConfig {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.command)?;
+		for arg in self.args.iter() {
+			write!(f, " {}", arg)?;
+		}
+		Ok(())
+	}
+}
+
+/// Per-rule configuration of the linter, named after the markdownlint rules.
+#[derive(Debug, Deserialize, Serialize, Default)]
+#[serde(default)]
+pub struct LintConfig {
+	/// Heading levels should only increment by one level at a time (MD001).
+	pub heading_increment:    RuleConfig,
+	/// Unordered lists should use a single marker style (MD004).
+	pub ul_style:             UlStyleConfig,
+	/// Lines should not end with spaces (MD009).
+	pub no_trailing_spaces:   TrailingSpacesConfig,
+	/// Lines should not be longer than a limit (MD013).
+	pub line_length:          LineLengthConfig,
+	/// Headings should not repeat the text of another heading (MD024).
+	pub no_duplicate_heading: RuleConfig,
+	/// URLs should be written as links or autolinks (MD034).
+	pub no_bare_urls:         RuleConfig,
+	/// Links should have text and a destination (MD042).
+	pub no_empty_links:       RuleConfig,
+	/// Images should have alternate text (MD045).
+	pub no_alt_text:          RuleConfig,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RuleConfig {
+	pub enabled: bool,
+}
+
+impl Default for RuleConfig {
+	fn default() -> Self {
+		RuleConfig { enabled: true }
+	}
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UlStyleConfig {
+	pub enabled: bool,
+	/// Marker that every unordered list should use.
+	pub style:   UlStyle,
+}
+
+impl Default for UlStyleConfig {
+	fn default() -> Self {
+		UlStyleConfig {
+			enabled: true,
+			style:   UlStyle::default(),
+		}
+	}
+}
+
+/// Marker style of unordered lists.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UlStyle {
+	/// Every list uses the marker of the first list in the document.
+	#[default]
+	Consistent,
+	/// `-`
+	Dash,
+	/// `*`
+	Asterisk,
+	/// `+`
+	Plus,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TrailingSpacesConfig {
+	pub enabled:   bool,
+	/// Number of trailing spaces allowed as a hard line break, 0 to allow
+	/// none.
+	pub br_spaces: usize,
+}
+
+impl Default for TrailingSpacesConfig {
+	fn default() -> Self {
+		TrailingSpacesConfig {
+			enabled:   true,
+			br_spaces: 2,
+		}
+	}
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LineLengthConfig {
+	pub enabled:     bool,
+	/// Maximum number of characters in a line.
+	pub line_length: usize,
+}
+
+impl Default for LineLengthConfig {
+	fn default() -> Self {
+		LineLengthConfig {
+			enabled:     true,
+			line_length: 80,
+		}
+	}
+}
+
 impl Config {
 	pub fn parse_from_str(content: &str) -> Result<Config> {
 		Ok(config::Config::builder()
@@ -131,6 +463,203 @@ mod tests {
 	// 	std::env::remove_var("SMD_PARSE__KEEP_TEMP_FILES");
 	// }
 
+	#[test]
+	fn test_parse_lint_rules() {
+		let toml_content = r#"
+            [parse]
+            on_parse_error = "skip"
+            keep_temp_files = false
+
+            [lint.line_length]
+            line_length = 120
+
+            [lint.ul_style]
+            style = "dash"
+
+            [lint.no_bare_urls]
+            enabled = false
+        "#;
+
+		let config = Config::parse_from_str(toml_content).unwrap();
+		assert_eq!(config.lint.line_length.line_length, 120);
+		assert!(config.lint.line_length.enabled);
+		assert_eq!(config.lint.ul_style.style, UlStyle::Dash);
+		assert!(!config.lint.no_bare_urls.enabled);
+		assert!(config.lint.no_alt_text.enabled);
+		assert_eq!(config.lint.no_trailing_spaces.br_spaces, 2);
+	}
+
+	#[test]
+	fn test_parse_filters() {
+		let toml_content = r#"
+            [parse]
+            on_parse_error = "skip"
+            keep_temp_files = false
+
+            [[filters]]
+            command = "python3"
+            args = ["filters/toc.py", "--depth", "2"]
+            timeout_secs = 5
+
+            [[filters]]
+            command = "./demote"
+        "#;
+
+		let config = Config::parse_from_str(toml_content).unwrap();
+		assert_eq!(config.filters, vec![
+			FilterConfig {
+				command:      "python3".to_string(),
+				args:         vec![
+					"filters/toc.py".to_string(),
+					"--depth".to_string(),
+					"2".to_string()
+				],
+				timeout_secs: 5,
+			},
+			FilterConfig {
+				command:      "./demote".to_string(),
+				args:         Vec::new(),
+				timeout_secs: 30,
+			},
+		]);
+		assert_eq!(
+			config.filters[0].to_string(),
+			"python3 filters/toc.py --depth 2"
+		);
+	}
+
+	#[test]
+	fn test_parse_links() {
+		let toml_content = r#"
+            [parse]
+            on_parse_error = "skip"
+            keep_temp_files = false
+
+            [links]
+            base_url = "https://docs.example.com/"
+            markdown_to_html = true
+            repository_urls = ["https://github.com/owner/repo/blob/main/"]
+            allowed_schemes = ["http", "https", "mailto"]
+            internal_hosts = ["docs.example.com"]
+
+            [links.external]
+            target = "_blank"
+            rel = "noopener noreferrer nofollow"
+        "#;
+
+		let config = Config::parse_from_str(toml_content).unwrap();
+		assert_eq!(config.links, LinksConfig {
+			base_url:         Some("https://docs.example.com/".to_string()),
+			markdown_to_html: true,
+			repository_urls:  vec![
+				"https://github.com/owner/repo/blob/main/".to_string()
+			],
+			allowed_schemes:  vec![
+				"http".to_string(),
+				"https".to_string(),
+				"mailto".to_string()
+			],
+			internal_hosts:   vec!["docs.example.com".to_string()],
+			internal:         LinkAttributesConfig::default(),
+			external:         LinkAttributesConfig {
+				target: Some("_blank".to_string()),
+				rel:    Some("noopener noreferrer nofollow".to_string()),
+			},
+		});
+		assert_eq!(
+			Config::default().links,
+			Config::parse_from_str(
+				"[parse]\non_parse_error = \"skip\"\nkeep_temp_files = false"
+			)
+			.unwrap()
+			.links
+		);
+	}
+
+	#[test]
+	fn test_parse_images() {
+		let toml_content = r#"
+            [parse]
+            on_parse_error = "skip"
+            keep_temp_files = false
+
+            [images]
+            lazy_loading = true
+            detect_size = true
+        "#;
+
+		let config = Config::parse_from_str(toml_content).unwrap();
+		assert_eq!(config.images, ImagesConfig {
+			lazy_loading: true,
+			figures:      false,
+			detect_size:  true,
+		});
+	}
+
+	#[test]
+	fn test_parse_html() {
+		let toml_content = r#"
+            [parse]
+            on_parse_error = "skip"
+            keep_temp_files = false
+
+            [html]
+            self_contained = true
+            theme = "dark"
+            theme_package = "report"
+            template = "/templates/page.html"
+            print_stylesheet = false
+            stylesheets = ["/styles/a.css", "b.css"]
+        "#;
+
+		let config = Config::parse_from_str(toml_content).unwrap();
+		assert_eq!(config.html, HtmlConfig {
+			self_contained:   true,
+			size_warning_kib: 10 * 1024,
+			theme:            Theme::Dark,
+			theme_package:    Some("report".to_string()),
+			template:         Some(PathBuf::from("/templates/page.html")),
+			print_stylesheet: false,
+			stylesheets:      vec![
+				PathBuf::from("/styles/a.css"),
+				PathBuf::from("b.css")
+			],
+		});
+	}
+
+	#[test]
+	fn test_parse_pdf() {
+		let toml_content = r#"
+            [parse]
+            on_parse_error = "skip"
+            keep_temp_files = false
+
+            [pdf]
+            page_size = "a4"
+            orientation = "landscape"
+            margins = "1in 2cm"
+            scale = 0.8
+            page_ranges = "1-3"
+            footer = "{{ page }}"
+            plain_first_page = true
+        "#;
+
+		let config = Config::parse_from_str(toml_content).unwrap();
+		assert_eq!(config.pdf, PdfConfig {
+			page_size:        PageSize::A4,
+			orientation:      Orientation::Landscape,
+			margins:          Some("1in 2cm".parse().unwrap()),
+			scale:            0.8,
+			print_background: true,
+			page_ranges:      Some("1-3".to_string()),
+			header:           None,
+			footer:           Some("{{ page }}".to_string()),
+			plain_first_page: true,
+		});
+		assert!(Config::parse_from_str("[parse]\n[pdf]\npage_size = \"b9\"")
+			.is_err());
+	}
+
 	#[test]
 	fn test_invalid_toml() {
 		let invalid_toml = r#"
@@ -7,26 +7,106 @@ use headless_chrome::types::PrintToPdfOptions;
 
 use crate::{
 	browser::BrowserSession,
-	error::Result,
+	config::PdfConfig,
+	error::{
+		Error,
+		Result,
+	},
 	fs::write_to_file,
+	page::{
+		Length,
+		Margins,
+		Orientation,
+		Unit,
+	},
 };
 
+/// Top or bottom margin of pages without configured margins that have a
+/// header or footer, which Chrome shows within the margin.
+const HEADER_FOOTER_MARGIN: Length = Length::new(1.5, Unit::Centimeter);
+
 /// Trait for converting html to other file formats.
 pub trait Convert {
-	fn from_html(html: &str) -> Result<Vec<u8>>;
+	/// Settings of the conversion, eg. the page setup.
+	type Options;
+
+	fn from_html(html: &str, options: &Self::Options) -> Result<Vec<u8>>;
 }
 
-pub fn convert_html<T>(html: &str) -> Result<Vec<u8>>
+pub fn convert_html<T>(html: &str, options: &T::Options) -> Result<Vec<u8>>
 where
 	T: Convert,
 {
-	T::from_html(html)
+	T::from_html(html, options)
 }
 
 pub struct PDFConverter;
 
+impl PDFConverter {
+	/// Maps the page setup onto the options of Chrome. Without margins the
+	/// pages have none, so that those of the stylesheet apply, except for
+	/// room for the header and footer.
+	pub fn print_options(config: &PdfConfig) -> Result<PrintToPdfOptions> {
+		if !(0.1..=2.0).contains(&config.scale) {
+			return Err(Error::ConfigValidatioinError(format!(
+				"the scale of pdf pages is {}, it has to be between 0.1 and 2",
+				config.scale
+			)));
+		}
+		let (width, height) = config.page_size.dimensions();
+		let margin = |side: fn(&Margins) -> Length, shown: bool| {
+			let default = match shown {
+				true => HEADER_FOOTER_MARGIN,
+				false => Length::new(0.0, Unit::Millimeter),
+			};
+			Some(config.margins.as_ref().map_or(default, side).inches())
+		};
+		// Chrome shows its own header or footer for the one that is not set
+		let display_header_footer =
+			config.header.is_some() || config.footer.is_some();
+		let template = |html: &Option<String>| {
+			display_header_footer.then(|| match html {
+				Some(html) => header_footer(html),
+				None => "<span></span>".to_string(),
+			})
+		};
+		Ok(PrintToPdfOptions {
+			landscape: Some(config.orientation == Orientation::Landscape),
+			print_background: Some(config.print_background),
+			scale: Some(config.scale),
+			paper_width: Some(width.inches()),
+			paper_height: Some(height.inches()),
+			margin_top: margin(|m| m.top, config.header.is_some()),
+			margin_bottom: margin(|m| m.bottom, config.footer.is_some()),
+			margin_left: margin(|m| m.left, false),
+			margin_right: margin(|m| m.right, false),
+			page_ranges: config.page_ranges.clone(),
+			display_header_footer: Some(display_header_footer),
+			header_template: template(&config.header),
+			footer_template: template(&config.footer),
+			..Default::default()
+		})
+	}
+}
+
+/// Wraps a header or footer in an element with a readable font size, as
+/// Chrome shows them tiny otherwise.
+fn header_footer(html: &str) -> String {
+	format!(
+		concat!(
+			"<div style=\"width: 100%; padding: 0 1cm; ",
+			"box-sizing: border-box; font-size: 9px; text-align: center;\">",
+			"{}</div>"
+		),
+		html
+	)
+}
+
 impl Convert for PDFConverter {
-	fn from_html(html: &str) -> Result<Vec<u8>> {
+	type Options = PdfConfig;
+
+	fn from_html(html: &str, options: &PdfConfig) -> Result<Vec<u8>> {
+		let options = Self::print_options(options)?;
 		let browser = BrowserSession::initialize()?;
 
 		let current_dir = env::current_dir()?;
@@ -36,16 +116,70 @@ impl Convert for PDFConverter {
 		let tab_id = browser.open_file(&tmpfile)?;
 		fs::remove_file(tmpfile)?;
 
-		let options = PrintToPdfOptions {
-			margin_top: Some(0.0),
-			margin_bottom: Some(0.0),
-			margin_left: Some(0.0),
-			margin_right: Some(0.0),
-			print_background: Some(true),
-			..Default::default()
-		};
 		let converted = browser.print_to_pdf(&tab_id, options)?;
 
 		Ok(converted)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_print_options() {
+		let config = PdfConfig {
+			page_size: "a4".parse().unwrap(),
+			orientation: Orientation::Landscape,
+			margins: Some("1in 0.5in".parse().unwrap()),
+			page_ranges: Some("2-".to_string()),
+			..Default::default()
+		};
+		let options = PDFConverter::print_options(&config).unwrap();
+		assert_eq!(options.landscape, Some(true));
+		assert_eq!(options.paper_width, Some(210.0 / 25.4));
+		assert_eq!(options.paper_height, Some(297.0 / 25.4));
+		assert_eq!(options.margin_top, Some(1.0));
+		assert_eq!(options.margin_left, Some(0.5));
+		assert_eq!(options.page_ranges.as_deref(), Some("2-"));
+		assert_eq!(options.print_background, Some(true));
+		assert_eq!(options.display_header_footer, Some(false));
+	}
+
+	#[test]
+	fn test_print_options_with_a_footer() {
+		let options = PDFConverter::print_options(&PdfConfig {
+			footer: Some("<b>1</b>".to_string()),
+			..Default::default()
+		})
+		.unwrap();
+		assert_eq!(options.display_header_footer, Some(true));
+		assert_eq!(options.header_template.as_deref(), Some("<span></span>"));
+		assert_eq!(options.margin_top, Some(0.0));
+		assert_eq!(options.margin_bottom, Some(1.5 / 2.54));
+		assert!(options
+			.footer_template
+			.is_some_and(|footer| footer.ends_with("\"><b>1</b></div>")));
+
+		let options = PDFConverter::print_options(&PdfConfig {
+			margins: Some("1in".parse().unwrap()),
+			header: Some("<b>1</b>".to_string()),
+			..Default::default()
+		})
+		.unwrap();
+		assert_eq!(options.margin_top, Some(1.0));
+	}
+
+	#[test]
+	fn test_print_options_without_margins() {
+		let options =
+			PDFConverter::print_options(&PdfConfig::default()).unwrap();
+		assert_eq!(options.margin_bottom, Some(0.0));
+		assert_eq!(options.paper_width, Some(8.5));
+		let scaled = PDFConverter::print_options(&PdfConfig {
+			scale: 3.0,
+			..Default::default()
+		});
+		assert!(scaled.is_err());
+	}
+}
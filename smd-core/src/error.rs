@@ -36,6 +36,19 @@ pub enum Error {
 	#[error("Headless-chrome error: `{0}`")]
 	BrowserError(String),
 
+	/// When the markdown cannot be rendered, eg. because it exceeds the
+	/// parser limits.
+	#[error("Parse error: `{0}`")]
+	ParseError(String),
+
+	/// When an external filter cannot be run, fails or exceeds its timeout.
+	#[error("Filter error: `{0}`")]
+	FilterError(String),
+
+	/// When a theme cannot be found, read or created.
+	#[error("Theme error: `{0}`")]
+	ThemeError(String),
+
 	#[error("Custom error: `{0}`")]
 	CustomError(String),
 }
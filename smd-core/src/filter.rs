@@ -0,0 +1,168 @@
+use log::{
+	debug,
+	info,
+};
+
+use crate::{
+	config::FilterConfig,
+	error::{
+		Error,
+		Result,
+	},
+};
+use std::{
+	io::{
+		Read,
+		Write,
+	},
+	process::{
+		Command,
+		Stdio,
+	},
+	sync::mpsc,
+	thread,
+	time::{
+		Duration,
+		Instant,
+	},
+};
+
+/// Interval at which a running filter is checked for its exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Pipes `input` through the command of a filter and returns what it wrote
+/// to its stdout. The command is killed once its timeout is exceeded, and a
+/// non-zero exit is reported together with what it wrote to its stderr.
+/// Processes it leaves behind that keep its output open count towards the
+/// timeout as well.
+pub fn run_filter(filter: &FilterConfig, input: &str) -> Result<String> {
+	debug!("Starting filter: {}", filter);
+	let mut child = Command::new(&filter.command)
+		.args(&filter.args)
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.map_err(|e| {
+			Error::FilterError(format!("cannot start \"{}\": {}", filter, e))
+		})?;
+
+	// Writing and reading happen on their own threads, so a filter that
+	// writes before it has read all of its input does not block. They are
+	// not joined, as processes the filter started may keep its pipes open;
+	// a filter may also exit without reading all of its input, which is fine
+	let mut stdin = child.stdin.take().expect("stdin is piped");
+	let input = input.to_string();
+	thread::spawn(move || stdin.write_all(input.as_bytes()));
+	let mut stdout = child.stdout.take().expect("stdout is piped");
+	let (stdout_sender, stdout_receiver) = mpsc::channel();
+	thread::spawn(move || {
+		let mut buffer = Vec::new();
+		let _ =
+			stdout_sender.send(stdout.read_to_end(&mut buffer).map(|_| buffer));
+	});
+	let mut stderr = child.stderr.take().expect("stderr is piped");
+	let (stderr_sender, stderr_receiver) = mpsc::channel();
+	thread::spawn(move || {
+		let mut buffer = String::new();
+		let _ = stderr_sender
+			.send(stderr.read_to_string(&mut buffer).map(|_| buffer));
+	});
+
+	let timed_out = || {
+		Error::FilterError(format!(
+			"\"{}\" timed out after {}s",
+			filter, filter.timeout_secs
+		))
+	};
+	let deadline = Instant::now() + Duration::from_secs(filter.timeout_secs);
+	let status = loop {
+		if let Some(status) = child.try_wait()? {
+			break status;
+		}
+		if Instant::now() >= deadline {
+			child.kill()?;
+			child.wait()?;
+			return Err(timed_out());
+		}
+		thread::sleep(POLL_INTERVAL);
+	};
+
+	let left = || deadline.saturating_duration_since(Instant::now());
+	let stdout = stdout_receiver
+		.recv_timeout(left())
+		.map_err(|_| timed_out())??;
+	let stderr = stderr_receiver
+		.recv_timeout(left())
+		.map_err(|_| timed_out())??;
+
+	if !status.success() {
+		let mut message = format!("\"{}\" failed with {}", filter, status);
+		if !stderr.trim().is_empty() {
+			message.push_str(": ");
+			message.push_str(stderr.trim());
+		}
+		return Err(Error::FilterError(message));
+	}
+
+	info!("Filter finished: {}", filter);
+	String::from_utf8(stdout).map_err(|_| {
+		Error::FilterError(format!("\"{}\" wrote invalid UTF-8", filter))
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn shell(script: &str, timeout_secs: u64) -> FilterConfig {
+		FilterConfig {
+			command: "sh".to_string(),
+			args: vec!["-c".to_string(), script.to_string()],
+			timeout_secs,
+		}
+	}
+
+	#[test]
+	fn test_output_is_returned() {
+		let output = run_filter(&shell("tr a-z A-Z", 5), "{\"a\": 1}").unwrap();
+		assert_eq!(output, "{\"A\": 1}");
+	}
+
+	#[test]
+	fn test_failure_reports_stderr() {
+		let filter = shell("echo oops >&2; exit 3", 5);
+		let error = run_filter(&filter, "{}").unwrap_err().to_string();
+		assert!(error.contains("exit status: 3"), "{}", error);
+		assert!(error.ends_with(": oops`"), "{}", error);
+	}
+
+	#[test]
+	fn test_timeout_kills_the_filter() {
+		let filter = shell("exec sleep 10", 1);
+		let started = Instant::now();
+		let error = run_filter(&filter, "{}").unwrap_err().to_string();
+		assert!(error.contains("timed out after 1s"), "{}", error);
+		assert!(started.elapsed() < Duration::from_secs(5));
+	}
+
+	#[test]
+	fn test_timeout_covers_processes_left_behind() {
+		let filter = shell("sleep 10 & cat", 1);
+		let started = Instant::now();
+		let error = run_filter(&filter, "{}").unwrap_err().to_string();
+		assert!(error.contains("timed out after 1s"), "{}", error);
+		assert!(started.elapsed() < Duration::from_secs(5));
+	}
+
+	#[test]
+	fn test_missing_command() {
+		let filter = FilterConfig {
+			command:      "smd-no-such-filter".to_string(),
+			args:         Vec::new(),
+			timeout_secs: 5,
+		};
+		let error = run_filter(&filter, "{}").unwrap_err().to_string();
+		assert!(error.contains("cannot start \"smd-no-such-filter\""));
+	}
+}
@@ -13,5 +13,27 @@ pub mod convert;
 /// Wrapper for the headless-chrome crate
 pub mod browser;
 
+/// External commands that transform the document tree.
+pub mod filter;
+
+/// Self-contained html documents with embedded resources.
+pub mod bundle;
+
+/// Templates and themes of html documents.
+pub mod template;
+
+/// Page setup of printed documents.
+pub mod page;
+
+/// Theme packages that are shared between documents.
+pub mod theme;
+
+/// Helpers shared by the tests of the modules.
+#[cfg(test)]
+mod test_utils;
+
 /// Default configuration file.
 pub const DEFAULT_CONFIG: &str = "smd/config.toml";
+
+/// Directory of the configuration that themes are kept in.
+pub const THEMES_DIR: &str = "smd/themes";
@@ -0,0 +1,363 @@
+use serde::{
+	Deserialize,
+	Serialize,
+};
+use std::{
+	fmt,
+	str::FromStr,
+};
+
+/// A css rule that leaves out the top and bottom margins of the first page,
+/// where the header and footer of pdf pages are shown.
+pub const PLAIN_FIRST_PAGE: &str =
+	"@page :first {\n\tmargin-top: 0;\n\tmargin-bottom: 0;\n}\n";
+
+/// Units of a [Length].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unit {
+	Millimeter,
+	Centimeter,
+	Inch,
+	/// 1/72 of an inch.
+	Point,
+	/// 1/96 of an inch, like in css.
+	Pixel,
+}
+
+impl Unit {
+	const ALL: [(Unit, &'static str); 5] = [
+		(Unit::Millimeter, "mm"),
+		(Unit::Centimeter, "cm"),
+		(Unit::Inch, "in"),
+		(Unit::Point, "pt"),
+		(Unit::Pixel, "px"),
+	];
+
+	fn suffix(self) -> &'static str {
+		Unit::ALL
+			.iter()
+			.find(|(u, _)| *u == self)
+			.map_or("", |(_, s)| s)
+	}
+
+	fn per_inch(self) -> f64 {
+		match self {
+			Unit::Millimeter => 25.4,
+			Unit::Centimeter => 2.54,
+			Unit::Inch => 1.0,
+			Unit::Point => 72.0,
+			Unit::Pixel => 96.0,
+		}
+	}
+}
+
+/// A length on paper, eg. `18mm` or `0.5in`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Length {
+	pub value: f64,
+	pub unit:  Unit,
+}
+
+impl Length {
+	pub const fn new(value: f64, unit: Unit) -> Self {
+		Length { value, unit }
+	}
+
+	/// The length in inches, which is what Chrome expects.
+	pub fn inches(self) -> f64 {
+		self.value / self.unit.per_inch()
+	}
+}
+
+impl fmt::Display for Length {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}{}", self.value, self.unit.suffix())
+	}
+}
+
+impl FromStr for Length {
+	type Err = String;
+
+	/// Parses a number followed by `mm`, `cm`, `in`, `pt` or `px`. Zero may
+	/// be written without a unit.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.trim();
+		if s == "0" {
+			return Ok(Length::new(0.0, Unit::Millimeter));
+		}
+		Unit::ALL
+			.iter()
+			.find_map(|&(unit, suffix)| {
+				let value = s.strip_suffix(suffix)?.trim().parse::<f64>().ok();
+				Some(Length::new(value?, unit))
+			})
+			.filter(|length| length.value.is_finite() && length.value >= 0.0)
+			.ok_or_else(|| format!("`{}` is not a length, eg. `18mm`", s))
+	}
+}
+
+/// Size of the paper of printed pages.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum PageSize {
+	A3,
+	A4,
+	A5,
+	/// 8.5 by 11 inches, which Chrome uses unless told otherwise.
+	#[default]
+	Letter,
+	/// 8.5 by 14 inches.
+	Legal,
+	/// Width and height, eg. `150mm x 200mm`.
+	Custom(Length, Length),
+}
+
+impl PageSize {
+	const NAMED: [(PageSize, &'static str); 5] = [
+		(PageSize::A3, "a3"),
+		(PageSize::A4, "a4"),
+		(PageSize::A5, "a5"),
+		(PageSize::Letter, "letter"),
+		(PageSize::Legal, "legal"),
+	];
+
+	/// Width and height of the paper in portrait orientation.
+	pub fn dimensions(self) -> (Length, Length) {
+		let mm = |w, h| {
+			(
+				Length::new(w, Unit::Millimeter),
+				Length::new(h, Unit::Millimeter),
+			)
+		};
+		let inch =
+			|w, h| (Length::new(w, Unit::Inch), Length::new(h, Unit::Inch));
+		match self {
+			PageSize::A3 => mm(297.0, 420.0),
+			PageSize::A4 => mm(210.0, 297.0),
+			PageSize::A5 => mm(148.0, 210.0),
+			PageSize::Letter => inch(8.5, 11.0),
+			PageSize::Legal => inch(8.5, 14.0),
+			PageSize::Custom(width, height) => (width, height),
+		}
+	}
+}
+
+impl fmt::Display for PageSize {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match PageSize::NAMED.iter().find(|(size, _)| size == self) {
+			Some((_, name)) => write!(f, "{}", name),
+			None => {
+				let (width, height) = self.dimensions();
+				write!(f, "{} x {}", width, height)
+			}
+		}
+	}
+}
+
+impl FromStr for PageSize {
+	type Err = String;
+
+	/// Parses the name of a paper size, ignoring case, or a width and height
+	/// separated by `x`. The `x` may also be the end of the unit `px`, so
+	/// every `x` is tried until both sides are lengths.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if let Some((size, _)) = PageSize::NAMED
+			.iter()
+			.find(|(_, name)| name.eq_ignore_ascii_case(s.trim()))
+		{
+			return Ok(*size);
+		}
+		let mut error =
+			format!("`{}` is not a page size, eg. `a4` or `150mm x 200mm`", s);
+		for (i, _) in s.match_indices('x') {
+			match (s[..i].parse(), s[i + 1..].parse()) {
+				(Ok(width), Ok(height)) => {
+					return Ok(PageSize::Custom(width, height))
+				}
+				(Err(e), _) | (_, Err(e)) => error = e,
+			}
+		}
+		Err(error)
+	}
+}
+
+impl TryFrom<String> for PageSize {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl From<PageSize> for String {
+	fn from(value: PageSize) -> Self {
+		value.to_string()
+	}
+}
+
+/// Whether pages are upright or on their side.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+	#[default]
+	Portrait,
+	Landscape,
+}
+
+impl FromStr for Orientation {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_ascii_lowercase().as_str() {
+			"portrait" => Ok(Orientation::Portrait),
+			"landscape" => Ok(Orientation::Landscape),
+			_ => Err(format!("`{}` is not `portrait` or `landscape`", s)),
+		}
+	}
+}
+
+/// Margins of printed pages, written like the css `margin` property: one
+/// length for every side, two for top and bottom and left and right, three
+/// for top, left and right and bottom, or four for top, right, bottom and
+/// left.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Margins {
+	pub top:    Length,
+	pub right:  Length,
+	pub bottom: Length,
+	pub left:   Length,
+}
+
+impl Margins {
+	/// A css rule that gives printed pages these margins.
+	pub fn page_rule(&self) -> String {
+		format!("@page {{\n\tmargin: {};\n}}\n", self)
+	}
+}
+
+impl fmt::Display for Margins {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let Margins {
+			top,
+			right,
+			bottom,
+			left,
+		} = self;
+		if right != left {
+			write!(f, "{} {} {} {}", top, right, bottom, left)
+		} else if top != bottom {
+			write!(f, "{} {} {}", top, right, bottom)
+		} else if top != right {
+			write!(f, "{} {}", top, right)
+		} else {
+			write!(f, "{}", top)
+		}
+	}
+}
+
+impl FromStr for Margins {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let lengths = s
+			.split_whitespace()
+			.map(str::parse)
+			.collect::<Result<Vec<Length>, _>>()?;
+		let [top, right, bottom, left] = match lengths[..] {
+			[all] => [all; 4],
+			[vertical, horizontal] => {
+				[vertical, horizontal, vertical, horizontal]
+			}
+			[top, horizontal, bottom] => [top, horizontal, bottom, horizontal],
+			[top, right, bottom, left] => [top, right, bottom, left],
+			_ => {
+				return Err(format!(
+					"`{}` are not margins, which are one to four lengths",
+					s
+				))
+			}
+		};
+		Ok(Margins {
+			top,
+			right,
+			bottom,
+			left,
+		})
+	}
+}
+
+impl TryFrom<String> for Margins {
+	type Error = String;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl From<Margins> for String {
+	fn from(value: Margins) -> Self {
+		value.to_string()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_lengths() {
+		assert_eq!("25.4mm".parse::<Length>().unwrap().inches(), 1.0);
+		assert_eq!("72pt".parse::<Length>().unwrap().inches(), 1.0);
+		assert_eq!("0".parse::<Length>().unwrap().inches(), 0.0);
+		assert_eq!("1.5 in".parse::<Length>().unwrap().to_string(), "1.5in");
+		for length in ["", "12", "-1mm", "amm", "1em"] {
+			assert!(length.parse::<Length>().is_err(), "{}", length);
+		}
+	}
+
+	#[test]
+	fn test_page_sizes() {
+		assert_eq!("A4".parse(), Ok(PageSize::A4));
+		let custom: PageSize = "150mm x 8in".parse().unwrap();
+		assert_eq!(
+			custom,
+			PageSize::Custom(
+				Length::new(150.0, Unit::Millimeter),
+				Length::new(8.0, Unit::Inch)
+			)
+		);
+		assert_eq!(custom.to_string(), "150mm x 8in");
+		let pixels: PageSize = "600px x 800px".parse().unwrap();
+		assert_eq!(
+			pixels,
+			PageSize::Custom(
+				Length::new(600.0, Unit::Pixel),
+				Length::new(800.0, Unit::Pixel)
+			)
+		);
+		assert_eq!(pixels.to_string(), "600px x 800px");
+		assert_eq!("600pxx800px".parse(), Ok(pixels));
+		assert_eq!(PageSize::Letter.to_string(), "letter");
+		assert!("b4".parse::<PageSize>().is_err());
+		assert!("1mm x".parse::<PageSize>().is_err());
+	}
+
+	#[test]
+	fn test_margins() {
+		for (margins, shortest) in [
+			("1in", "1in"),
+			("1in 1in 1in 1in", "1in"),
+			("18mm 16mm", "18mm 16mm"),
+			("1in 2in 3in", "1in 2in 3in"),
+			("1in 2in 3in 4in", "1in 2in 3in 4in"),
+		] {
+			let parsed: Margins = margins.parse().unwrap();
+			assert_eq!(parsed.to_string(), shortest);
+		}
+		let margins: Margins = "1in 2in 3in".parse().unwrap();
+		assert_eq!(margins.left, Length::new(2.0, Unit::Inch));
+		assert!("".parse::<Margins>().is_err());
+		assert!("1in 1in 1in 1in 1in".parse::<Margins>().is_err());
+	}
+}
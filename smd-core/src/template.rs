@@ -0,0 +1,320 @@
+use log::warn;
+use serde::{
+	Deserialize,
+	Serialize,
+};
+use std::{
+	collections::BTreeMap,
+	path::PathBuf,
+};
+
+use crate::{
+	error::Result,
+	fs,
+};
+
+/// Template html documents are written with, unless another one is
+/// configured.
+pub const DEFAULT_TEMPLATE: &str = include_str!("themes/default.html");
+
+const GITHUB: &str = include_str!("themes/github.css");
+const GITHUB_LIGHT: &str = include_str!("themes/github-light.css");
+const GITHUB_DARK: &str = include_str!("themes/github-dark.css");
+
+/// Margins of printed pages, and rules that keep headings with what follows
+/// them, code blocks and table rows from being split across pages and
+/// repeat table headers on every page.
+pub const PRINT_STYLESHEET: &str = include_str!("themes/print.css");
+
+/// Colors of the GitHub-like stylesheet of html documents.
+#[derive(Debug, Deserialize, Serialize, Default, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+	/// Light or dark, following the preference of the reader.
+	#[default]
+	Auto,
+	/// Dark text on a light background.
+	Light,
+	/// Light text on a dark background.
+	Dark,
+	/// No stylesheet at all.
+	None,
+}
+
+impl Theme {
+	/// Returns the stylesheet of the theme.
+	pub fn stylesheet(self) -> String {
+		match self {
+			Theme::Auto => format!(
+				"{}@media (prefers-color-scheme: dark) {{\n{}}}\n{}",
+				GITHUB_LIGHT, GITHUB_DARK, GITHUB
+			),
+			Theme::Light => format!("{}{}", GITHUB_LIGHT, GITHUB),
+			Theme::Dark => format!("{}{}", GITHUB_DARK, GITHUB),
+			Theme::None => String::new(),
+		}
+	}
+}
+
+/// What is filled into the placeholders of a template.
+#[derive(Debug)]
+pub struct Page<'a> {
+	/// `{{ title }}`, escaped.
+	pub title:   &'a str,
+	/// `{{ content }}`, the rendered markdown.
+	pub content: &'a str,
+	/// `{{ toc }}`, the table of contents as html.
+	pub toc:     &'a str,
+	/// `{{ style }}`, the stylesheet.
+	pub style:   &'a str,
+	/// `{{ meta.key }}`, the front matter of the markdown, escaped.
+	pub meta:    &'a BTreeMap<String, String>,
+}
+
+/// An html document with `{{ placeholder }}`s for the parts of a page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+	source: String,
+}
+
+impl Default for Template {
+	fn default() -> Self {
+		Template::new(DEFAULT_TEMPLATE)
+	}
+}
+
+impl Template {
+	pub fn new(source: &str) -> Self {
+		Template {
+			source: source.to_string(),
+		}
+	}
+
+	/// Reads a template from a file.
+	pub fn load(path: &PathBuf) -> Result<Self> {
+		Ok(Template::new(&fs::read_to_string(path)?))
+	}
+
+	/// Fills in the placeholders. Unknown placeholders are left out.
+	pub fn render(&self, page: &Page) -> String {
+		self.fill(|name| match name {
+			"title" => Some(escape(page.title)),
+			"content" => Some(page.content.to_string()),
+			"toc" => Some(page.toc.to_string()),
+			"style" => Some(page.style.replace("</", "<\\/")),
+			_ => meta(page.meta, name),
+		})
+	}
+
+	/// Fills in the placeholders of the header or footer of pdf pages:
+	/// `{{ page }}`, `{{ pages }}`, `{{ date }}`, `{{ title }}` and
+	/// `{{ meta.key }}`. The page numbers and the date are elements Chrome
+	/// fills in when printing.
+	pub fn render_header_footer(
+		&self,
+		title: &str,
+		meta_data: &BTreeMap<String, String>,
+	) -> String {
+		self.fill(|name| match name {
+			"page" => Some("<span class=\"pageNumber\"></span>".to_string()),
+			"pages" => Some("<span class=\"totalPages\"></span>".to_string()),
+			"date" => Some("<span class=\"date\"></span>".to_string()),
+			"title" => Some(escape(title)),
+			_ => meta(meta_data, name),
+		})
+	}
+
+	/// Replaces every `{{ placeholder }}` with its value. Unknown
+	/// placeholders are warned about and left out.
+	fn fill(&self, value: impl Fn(&str) -> Option<String>) -> String {
+		let mut rendered = String::with_capacity(self.source.len());
+		let mut rest = self.source.as_str();
+		while let Some(start) = rest.find("{{") {
+			let Some(end) = rest[start..].find("}}").map(|i| start + i) else {
+				break;
+			};
+			rendered.push_str(&rest[..start]);
+			let name = rest[start + 2..end].trim();
+			match value(name) {
+				Some(value) => rendered.push_str(&value),
+				None => warn!("Unknown placeholder `{{{{ {} }}}}`", name),
+			}
+			rest = &rest[end + 2..];
+		}
+		rendered.push_str(rest);
+		rendered
+	}
+}
+
+/// Value of a `{{ meta.key }}` placeholder, escaped, which is empty if the
+/// front matter has no such key.
+fn meta(meta: &BTreeMap<String, String>, name: &str) -> Option<String> {
+	let key = name.strip_prefix("meta.")?;
+	Some(escape(meta.get(key).map_or("", String::as_str)))
+}
+
+/// Splits the front matter off of markdown, which is either YAML between
+/// `---` lines or TOML between `+++` lines. Only its top level values are
+/// kept, as text.
+pub fn front_matter(source: &str) -> (BTreeMap<String, String>, &str) {
+	let mut meta = BTreeMap::new();
+	let Some((fence, rest)) = ["---", "+++"].iter().find_map(|fence| {
+		let rest = source.strip_prefix(fence)?;
+		let rest = rest.strip_prefix("\r\n").or(rest.strip_prefix('\n'))?;
+		Some((*fence, rest))
+	}) else {
+		return (meta, source);
+	};
+	let mut offset = 0;
+	let end = rest.split_inclusive('\n').find_map(|line| {
+		let start = offset;
+		offset += line.len();
+		let line = line.trim_end();
+		(line == fence || (fence == "---" && line == "...")).then_some(start)
+	});
+	let Some(end) = end else {
+		return (meta, source);
+	};
+	let (matter, body) = (&rest[..end], &rest[offset..]);
+
+	if fence == "+++" {
+		match matter.parse::<toml::Table>() {
+			Ok(table) => {
+				for (key, value) in table {
+					let value = match value {
+						toml::Value::String(s) => s,
+						toml::Value::Table(_) | toml::Value::Array(_) => {
+							continue
+						}
+						value => value.to_string(),
+					};
+					meta.insert(key, value);
+				}
+			}
+			Err(e) => warn!("Cannot parse the front matter: {}", e),
+		}
+		return (meta, body);
+	}
+	for line in matter.lines() {
+		// Nested values and list items are indented or start with `-`
+		if line.starts_with([' ', '\t', '-', '#']) {
+			continue;
+		}
+		let Some((key, value)) = line.split_once(':') else {
+			continue;
+		};
+		let value = value.trim();
+		let value = [('"', '"'), ('\'', '\'')]
+			.iter()
+			.find_map(|&(open, close)| {
+				value.strip_prefix(open)?.strip_suffix(close)
+			})
+			.unwrap_or(value);
+		if !value.is_empty() {
+			meta.insert(key.trim().to_string(), value.to_string());
+		}
+	}
+	(meta, body)
+}
+
+/// Escapes text for html.
+fn escape(text: &str) -> String {
+	text.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_placeholders_are_filled_in() {
+		let meta =
+			BTreeMap::from([("author".to_string(), "a & b".to_string())]);
+		let template = Template::new(
+			"<title>{{title}}</title>{{ style }}|{{ toc }}|{{ content }}|{{ \
+			 meta.author }}|{{ meta.date }}|{{ other }}",
+		);
+		let page = Page {
+			title:   "<T>",
+			content: "<p>c</p>",
+			toc:     "<nav></nav>",
+			style:   "p {}",
+			meta:    &meta,
+		};
+		assert_eq!(
+			template.render(&page),
+			"<title>&lt;T&gt;</title>p {}|<nav></nav>|<p>c</p>|a &amp; b||"
+		);
+	}
+
+	#[test]
+	fn test_header_footer_placeholders() {
+		let meta = BTreeMap::from([("version".to_string(), "1.2".to_string())]);
+		let footer = Template::new(
+			"{{ title }} {{ meta.version }}: {{ page }}/{{ pages }}, {{ date \
+			 }}",
+		);
+		assert_eq!(
+			footer.render_header_footer("A & B", &meta),
+			concat!(
+				"A &amp; B 1.2: <span class=\"pageNumber\"></span>/",
+				"<span class=\"totalPages\"></span>, ",
+				"<span class=\"date\"></span>"
+			)
+		);
+	}
+
+	#[test]
+	fn test_yaml_front_matter() {
+		let (meta, body) = front_matter(concat!(
+			"---\n",
+			"title: \"A: B\"\n",
+			"author: c\n",
+			"tags:\n",
+			"  - d\n",
+			"---\n",
+			"# e\n"
+		));
+		assert_eq!(body, "# e\n");
+		assert_eq!(
+			meta,
+			BTreeMap::from([
+				("title".to_string(), "A: B".to_string()),
+				("author".to_string(), "c".to_string()),
+			])
+		);
+	}
+
+	#[test]
+	fn test_toml_front_matter() {
+		let (meta, body) =
+			front_matter("+++\ntitle = \"a\"\nversion = 2\n+++\nb");
+		assert_eq!(body, "b");
+		assert_eq!(meta.get("title").unwrap(), "a");
+		assert_eq!(meta.get("version").unwrap(), "2");
+	}
+
+	#[test]
+	fn test_markdown_without_front_matter() {
+		let source = "---\n\na\n";
+		assert_eq!(front_matter(source), (BTreeMap::new(), source));
+		assert_eq!(front_matter("a\n---\n").1, "a\n---\n");
+	}
+
+	#[test]
+	fn test_print_stylesheet() {
+		assert!(PRINT_STYLESHEET.starts_with("@page {"));
+		assert!(PRINT_STYLESHEET.contains("display: table-header-group;"));
+	}
+
+	#[test]
+	fn test_themes() {
+		assert!(Theme::Auto.stylesheet().contains("prefers-color-scheme"));
+		assert!(Theme::Dark.stylesheet().contains("color-scheme: dark"));
+		assert!(!Theme::Light.stylesheet().contains("color-scheme: dark"));
+		assert_eq!(Theme::None.stylesheet(), "");
+	}
+}
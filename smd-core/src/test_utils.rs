@@ -0,0 +1,11 @@
+use std::{
+	env,
+	path::PathBuf,
+};
+
+/// Directory of a test in the temporary directory, unique to the process so
+/// that concurrent runs do not share it. Tests create and remove it
+/// themselves.
+pub(crate) fn temp_dir(test: &str) -> PathBuf {
+	env::temp_dir().join(format!("smd-{}-{}", test, std::process::id()))
+}
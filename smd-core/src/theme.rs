@@ -0,0 +1,273 @@
+use log::debug;
+use serde::{
+	Deserialize,
+	Serialize,
+};
+use std::path::{
+	Path,
+	PathBuf,
+};
+
+use crate::{
+	bundle,
+	error::{
+		Error,
+		Result,
+	},
+	fs,
+	page::Margins,
+	template::{
+		Template,
+		Theme,
+		DEFAULT_TEMPLATE,
+	},
+	THEMES_DIR,
+};
+
+/// Name of the file that describes a theme.
+pub const MANIFEST: &str = "theme.toml";
+
+const SCAFFOLD_MANIFEST: &str = include_str!("themes/scaffold/theme.toml");
+const SCAFFOLD_STYLESHEET: &str = include_str!("themes/scaffold/style.css");
+
+/// Contents of `theme.toml`. Paths are relative to the theme directory.
+#[derive(Debug, Deserialize, Serialize, Default, PartialEq)]
+#[serde(default)]
+pub struct Manifest {
+	/// What the theme looks like, shown by `smd theme list`.
+	pub description: String,
+
+	/// Colors of the built-in stylesheet the theme starts from, instead of
+	/// the configured ones.
+	pub colors: Option<Theme>,
+
+	/// Html file the document is rendered into.
+	pub template: Option<PathBuf>,
+
+	/// Stylesheets added after the built-in one, in order.
+	pub stylesheets: Vec<PathBuf>,
+
+	/// Stylesheet of code blocks, added after the other stylesheets.
+	pub highlight: Option<PathBuf>,
+
+	/// Defaults of pdf output.
+	pub pdf: PdfDefaults,
+}
+
+/// Defaults a theme sets for pdf output.
+#[derive(Debug, Deserialize, Serialize, Default, PartialEq)]
+#[serde(default)]
+pub struct PdfDefaults {
+	/// Margins of printed pages, like the css `margin` property, eg.
+	/// `18mm 16mm`.
+	pub margins: Option<Margins>,
+}
+
+/// A directory with a `theme.toml`, a template, stylesheets and the fonts
+/// and images they use, which gives documents a shared look.
+#[derive(Debug, PartialEq)]
+pub struct ThemePackage {
+	/// Name of the directory.
+	pub name:     String,
+	/// Where the theme is.
+	pub dir:      PathBuf,
+	pub manifest: Manifest,
+}
+
+/// Directory of the configuration that themes are looked up in.
+pub fn themes_dir() -> PathBuf {
+	dirs::config_dir().unwrap_or_default().join(THEMES_DIR)
+}
+
+impl ThemePackage {
+	/// Reads the theme called `name` in `themes`.
+	pub fn load(themes: &Path, name: &str) -> Result<Self> {
+		let dir = themes.join(validate_name(name)?);
+		let manifest = dir.join(MANIFEST);
+		if !manifest.exists() {
+			return Err(Error::ThemeError(format!(
+				"{} is not a theme, {} does not exist",
+				name,
+				manifest.display()
+			)));
+		}
+		let manifest = toml::from_str(&fs::read_to_string(&manifest)?)
+			.map_err(|e| Error::ThemeError(format!("{}: {}", name, e)))?;
+		Ok(ThemePackage {
+			name: name.to_string(),
+			dir,
+			manifest,
+		})
+	}
+
+	/// Reads every theme in `themes`, sorted by name. Directories without
+	/// a `theme.toml` are skipped.
+	pub fn list(themes: &Path) -> Result<Vec<Self>> {
+		if !themes.exists() {
+			return Ok(Vec::new());
+		}
+		let mut names: Vec<String> = std::fs::read_dir(themes)?
+			.filter_map(|entry| entry.ok())
+			.filter(|entry| entry.path().join(MANIFEST).exists())
+			.filter_map(|entry| entry.file_name().into_string().ok())
+			.collect();
+		names.sort();
+		names.iter().map(|name| Self::load(themes, name)).collect()
+	}
+
+	/// Writes a new theme called `name` to `themes`, with the default
+	/// template, an empty stylesheet and directories for fonts and images.
+	pub fn create(themes: &Path, name: &str) -> Result<Self> {
+		let dir = themes.join(validate_name(name)?);
+		if dir.exists() {
+			return Err(Error::ThemeError(format!(
+				"{} already exists",
+				dir.display()
+			)));
+		}
+		debug!("Creating theme {}", dir.display());
+		std::fs::create_dir_all(dir.join("fonts"))?;
+		std::fs::create_dir_all(dir.join("images"))?;
+		fs::write_to_file(&dir.join(MANIFEST), SCAFFOLD_MANIFEST)?;
+		fs::write_to_file(&dir.join("template.html"), DEFAULT_TEMPLATE)?;
+		fs::write_to_file(&dir.join("style.css"), SCAFFOLD_STYLESHEET)?;
+		Self::load(themes, name)
+	}
+
+	/// Template of the theme, or the default one.
+	pub fn template(&self) -> Result<Template> {
+		match &self.manifest.template {
+			Some(path) => Template::load(&self.dir.join(path)),
+			None => Ok(Template::default()),
+		}
+	}
+
+	/// Stylesheets of the theme, with the fonts and images they refer to
+	/// embedded. The built-in stylesheet is not included, and neither are the
+	/// margins of printed pages, which are part of the page setup.
+	pub fn stylesheet(&self) -> Result<String> {
+		let mut stylesheet = String::new();
+		let paths = self
+			.manifest
+			.stylesheets
+			.iter()
+			.chain(self.manifest.highlight.iter());
+		for path in paths {
+			let path = self.dir.join(path);
+			let css = fs::read_to_string(&path)?;
+			let dir = path.parent().unwrap_or(&self.dir);
+			stylesheet.push_str(&bundle::embed_css_urls(&css, dir));
+			if !stylesheet.ends_with('\n') {
+				stylesheet.push('\n');
+			}
+		}
+		Ok(stylesheet)
+	}
+
+	/// Files of the theme relative to its directory, sorted.
+	pub fn files(&self) -> Result<Vec<PathBuf>> {
+		let mut files = Vec::new();
+		let mut dirs = vec![self.dir.clone()];
+		while let Some(dir) = dirs.pop() {
+			for entry in std::fs::read_dir(&dir)? {
+				let path = entry?.path();
+				if path.is_dir() {
+					dirs.push(path);
+				} else if let Ok(file) = path.strip_prefix(&self.dir) {
+					files.push(file.to_path_buf());
+				}
+			}
+		}
+		files.sort();
+		Ok(files)
+	}
+}
+
+/// Checks that a theme name is a single directory name.
+fn validate_name(name: &str) -> Result<&str> {
+	let valid = !name.is_empty() &&
+		name != "." &&
+		name != ".." &&
+		!name.contains(['/', '\\']);
+	match valid {
+		true => Ok(name),
+		false => Err(Error::ThemeError(format!(
+			"{} is not a valid theme name",
+			name
+		))),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_utils::temp_dir;
+
+	#[test]
+	fn test_create_and_list_themes() {
+		let themes = temp_dir("themes");
+		let created = ThemePackage::create(&themes, "report").unwrap();
+		std::fs::create_dir_all(themes.join("not-a-theme")).unwrap();
+		let listed = ThemePackage::list(&themes).unwrap();
+		let files = created.files().unwrap();
+		let again = ThemePackage::create(&themes, "report");
+		let template = listed[0].template().unwrap();
+		std::fs::remove_dir_all(&themes).unwrap();
+
+		assert_eq!(listed, vec![created]);
+		assert_eq!(
+			files,
+			["style.css", "template.html", MANIFEST].map(PathBuf::from)
+		);
+		assert!(again.is_err());
+		assert_eq!(listed[0].manifest.colors, Some(Theme::Auto));
+		assert_eq!(template, Template::default());
+	}
+
+	#[test]
+	fn test_theme_stylesheet() {
+		let themes = temp_dir("theme-css");
+		let dir = themes.join("a");
+		std::fs::create_dir_all(dir.join("css/fonts")).unwrap();
+		std::fs::write(dir.join("css/fonts/b.woff2"), "b").unwrap();
+		std::fs::write(
+			dir.join("css/a.css"),
+			"@font-face { src: url(fonts/b.woff2); }",
+		)
+		.unwrap();
+		std::fs::write(dir.join("code.css"), "pre {}\n").unwrap();
+		std::fs::write(
+			dir.join(MANIFEST),
+			concat!(
+				"stylesheets = [\"css/a.css\"]\n",
+				"highlight = \"code.css\"\n",
+				"[pdf]\n",
+				"margins = \"1in\"\n"
+			),
+		)
+		.unwrap();
+		let theme = ThemePackage::load(&themes, "a").unwrap();
+		let stylesheet = theme.stylesheet();
+		std::fs::remove_dir_all(&themes).unwrap();
+
+		assert_eq!(theme.manifest.colors, None);
+		let margins = theme.manifest.pdf.margins.unwrap();
+		assert_eq!(margins.to_string(), "1in");
+		assert_eq!(
+			stylesheet.unwrap(),
+			concat!(
+				"@font-face { src: url(\"data:font/woff2;base64,Yg==\"); }\n",
+				"pre {}\n"
+			)
+		);
+	}
+
+	#[test]
+	fn test_theme_names() {
+		assert!(validate_name("a-b").is_ok());
+		for name in ["", "..", "a/b", "a\\b"] {
+			assert!(validate_name(name).is_err());
+		}
+		assert!(ThemePackage::load(&temp_dir("missing"), "a").is_err());
+	}
+}
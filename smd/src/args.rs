@@ -5,12 +5,19 @@ use clap::{
 	Subcommand,
 	ValueEnum,
 };
+use smd_core::page::{
+	Margins,
+	Orientation,
+	PageSize,
+};
 use std::path::PathBuf;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum OutputFileFormat {
 	Pdf,
 	Html,
+	/// The document tree as mdast JSON
+	Json,
 }
 
 /// Command-line arguments to parse.
@@ -72,7 +79,13 @@ pub struct Cli {
 #[derive(Debug, Subcommand, PartialEq)]
 pub enum Commands {
 	/// Parse and convert Markdown files.
-	Parse(ParseArgs),
+	Parse(Box<ParseArgs>),
+
+	/// Checks Markdown files against the configured style rules.
+	Lint(LintArgs),
+
+	/// Lists, creates and shows theme packages.
+	Theme(ThemeArgs),
 
 	/// Writes the default configuration to the systems configuration
 	/// diretctory
@@ -81,7 +94,8 @@ pub enum Commands {
 
 #[derive(Debug, Args, PartialEq)]
 pub struct ParseArgs {
-	/// Sets the markdown file to convert.
+	/// Sets the markdown file to convert, or a document tree as mdast JSON
+	/// if it ends in `.json`.
 	#[arg(
         short,
         long,
@@ -101,7 +115,144 @@ pub struct ParseArgs {
         default_value_t = OutputFileFormat::Pdf,
         help_heading = Some("OPTIONS"),
     )]
-	pub output: OutputFileFormat,
+	pub output:        OutputFileFormat,
+	/// Fails if the markdown contains markup that is kept as text, eg. an
+	/// unclosed code span.
+	#[arg(long, help_heading = Some("FLAGS"))]
+	pub deny_warnings: bool,
+
+	/// Resolves relative links and images against this url.
+	#[arg(long, value_name = "URL", help_heading = Some("OPTIONS"))]
+	pub base_url: Option<String>,
+
+	/// Points relative links to `.md` files at their `.html` file.
+	#[arg(long, help_heading = Some("FLAGS"))]
+	pub markdown_to_html: bool,
+
+	/// Makes absolute urls into this repository relative to the input, eg.
+	/// `https://github.com/owner/repo/blob/main/`, taking the current
+	/// directory as its root. May be given more than once.
+	#[arg(long, value_name = "URL", help_heading = Some("OPTIONS"))]
+	pub repository_url: Vec<String>,
+
+	/// Writes html as a complete document with its stylesheet, local images
+	/// and fonts embedded, so it can be shared as a single file.
+	#[arg(long, help_heading = Some("FLAGS"))]
+	pub self_contained: bool,
+
+	/// Uses the theme package of this name instead of the configured one.
+	#[arg(long, value_name = "NAME", help_heading = Some("OPTIONS"))]
+	pub theme: Option<String>,
+
+	/// Adds a stylesheet to html and pdf output, after the theme and the
+	/// configured ones. May be given more than once.
+	#[arg(
+        long,
+        value_name = "FILE",
+        value_parser = Cli::parse_dir,
+        help_heading = Some("OPTIONS"),
+    )]
+	pub css: Vec<PathBuf>,
+
+	/// Sets the paper size of pdf output, eg. `a4`, `letter` or
+	/// `150mm x 200mm`.
+	#[arg(long, value_name = "SIZE", help_heading = Some("OPTIONS"))]
+	pub page_size: Option<PageSize>,
+
+	/// Sets whether pdf pages are `portrait` or `landscape`.
+	#[arg(long, value_name = "ORIENTATION", help_heading = Some("OPTIONS"))]
+	pub orientation: Option<Orientation>,
+
+	/// Sets the margins of pdf pages like the css `margin` property, eg.
+	/// `18mm 16mm`.
+	#[arg(long, value_name = "MARGINS", help_heading = Some("OPTIONS"))]
+	pub margins: Option<Margins>,
+
+	/// Scales the content of pdf pages, between 0.1 and 2.
+	#[arg(long, value_name = "SCALE", help_heading = Some("OPTIONS"))]
+	pub scale: Option<f64>,
+
+	/// Leaves background colors and images out of pdf output.
+	#[arg(long, help_heading = Some("FLAGS"))]
+	pub no_background: bool,
+
+	/// Prints only these pages to pdf, eg. `1-5, 8`.
+	#[arg(long, value_name = "RANGES", help_heading = Some("OPTIONS"))]
+	pub page_ranges: Option<String>,
+
+	/// Sets the html at the top of pdf pages, with `{{ page }}`,
+	/// `{{ pages }}`, `{{ date }}`, `{{ title }}` and `{{ meta.key }}`
+	/// placeholders.
+	#[arg(long, value_name = "HTML", help_heading = Some("OPTIONS"))]
+	pub header: Option<String>,
+
+	/// Sets the html at the bottom of pdf pages, like the header.
+	#[arg(long, value_name = "HTML", help_heading = Some("OPTIONS"))]
+	pub footer: Option<String>,
+
+	/// Leaves the header and footer out of the first pdf page.
+	#[arg(long, help_heading = Some("FLAGS"))]
+	pub plain_first_page: bool,
+}
+
+/// How `smd lint` reports what it found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LintFormat {
+	/// Every lint with the line it points at.
+	Human,
+	/// A JSON array of lints, for editors and CI.
+	Json,
+}
+
+#[derive(Debug, Args, PartialEq)]
+pub struct LintArgs {
+	/// Sets the markdown files to check.
+	#[arg(
+        short,
+        long,
+        value_name = "PATH",
+        value_parser = Cli::parse_dir,
+        help_heading = Some("OPTIONS"),
+        required = true,
+        num_args = 1..,
+    )]
+	pub input: Vec<PathBuf>,
+
+	/// Sets how the lints are printed.
+	#[arg(
+        long,
+        short,
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t = LintFormat::Human,
+        help_heading = Some("OPTIONS"),
+    )]
+	pub format: LintFormat,
+}
+
+#[derive(Debug, Args, PartialEq)]
+pub struct ThemeArgs {
+	#[command(subcommand)]
+	pub command: ThemeCommands,
+}
+
+/// What `smd theme` does with the themes in the configuration directory.
+#[derive(Debug, Subcommand, PartialEq)]
+pub enum ThemeCommands {
+	/// Prints the name and description of every theme.
+	List,
+
+	/// Creates a theme with the default template, to start from.
+	New {
+		/// Name of the theme directory.
+		name: String,
+	},
+
+	/// Prints where a theme is, its settings and files.
+	Show {
+		/// Name of the theme directory.
+		name: String,
+	},
 }
 
 impl Cli {
@@ -0,0 +1,124 @@
+use core::fmt;
+use gfm::{
+	Diagnostic,
+	Lint,
+};
+use std::{
+	ops::Range,
+	path::Path,
+};
+
+/// Formats a diagnostic along with the line it points at, eg.
+///
+/// ```text
+/// warning: link destination is not closed by `)`
+///  --> README.md:3:5
+///   |
+/// 3 | see [the docs](docs.md
+///   |     ^^^^^^^^^^^^^^^^^^
+/// ```
+pub fn code_frame(
+	path: &Path,
+	source: &str,
+	diagnostic: &Diagnostic,
+) -> String {
+	let (line, column) = diagnostic.line_column(source);
+	frame(path, source, diagnostic, &diagnostic.span, line, column)
+}
+
+/// Formats a lint along with the line it points at, eg.
+///
+/// ```text
+/// MD034/no-bare-urls: bare url `https://a.b`, wrap it in `<>`
+///  --> README.md:1:5
+///   |
+/// 1 | see https://a.b
+///   |     ^^^^^^^^^^^
+/// ```
+pub fn lint_frame(path: &Path, source: &str, lint: &Lint) -> String {
+	let (line, column) = lint.line_column(source);
+	frame(path, source, lint, &lint.span, line, column)
+}
+
+fn frame(
+	path: &Path,
+	source: &str,
+	header: &dyn fmt::Display,
+	span: &Range<usize>,
+	line: usize,
+	column: usize,
+) -> String {
+	let text = source.lines().nth(line - 1).unwrap_or("");
+	let number = line.to_string();
+	let gutter = " ".repeat(number.len());
+
+	// The caret covers the span, but not more than the rest of its line
+	let start = text
+		.char_indices()
+		.nth(column - 1)
+		.map_or(text.len(), |(i, _)| i);
+	let span_len = span.end.saturating_sub(span.start);
+	let width = text[start..]
+		.char_indices()
+		.take_while(|(i, _)| *i < span_len)
+		.count()
+		.max(1);
+	let indent: String = text
+		.chars()
+		.take(column - 1)
+		.map(|c| if c == '\t' { '\t' } else { ' ' })
+		.collect();
+
+	format!(
+		"{header}\n{gutter}--> {path}:{line}:{column}\n{gutter} |\n{number} | \
+		 {text}\n{gutter} | {indent}{carets}",
+		path = path.display(),
+		carets = "^".repeat(width),
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use gfm::{
+		LintOptions,
+		Options,
+		Parser,
+	};
+
+	fn frames(source: &str) -> Vec<String> {
+		Parser::render_with_diagnostics(source, &Options::default())
+			.1
+			.iter()
+			.map(|d| code_frame(Path::new("doc.md"), source, d))
+			.collect()
+	}
+
+	#[test]
+	fn frame_points_at_the_span() {
+		assert_eq!(frames("foo\n\nsee [the docs](docs.md\n"), vec![
+			"warning: link destination is not closed by `)`\n --> \
+			 doc.md:3:5\n  |\n3 | see [the docs](docs.md\n  |     \
+			 ^^^^^^^^^^^^^^^^^^"
+		]);
+	}
+
+	#[test]
+	fn lint_frame_names_the_rule() {
+		let source = "see https://a.b\n";
+		let lints = Parser::lint(source, &LintOptions::default());
+		assert_eq!(
+			lint_frame(Path::new("doc.md"), source, &lints[0]),
+			"MD034/no-bare-urls: bare url `https://a.b`, wrap it in `<>`\n --> \
+			 doc.md:1:5\n  |\n1 | see https://a.b\n  |     ^^^^^^^^^^^"
+		);
+	}
+
+	#[test]
+	fn frame_keeps_tabs_in_the_indentation() {
+		assert_eq!(frames("foo\na\tb `c\n"), vec![
+			"warning: code span opened by 1 backtick(s) is never closed\n --> \
+			 doc.md:2:5\n  |\n2 | a\tb `c\n  |  \t  ^"
+		]);
+	}
+}
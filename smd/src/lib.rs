@@ -1,23 +1,68 @@
 use args::{
 	Cli,
 	Commands,
+	LintArgs,
+	LintFormat,
+	ParseArgs,
+	ThemeCommands,
 };
+use gfm::Severity;
 use log::info;
+use serde::Serialize;
 use smd_core::{
-	config::Config,
+	bundle,
+	config::{
+		Config,
+		FilterConfig,
+		HtmlConfig,
+		ImagesConfig,
+		LinkAttributesConfig,
+		LinksConfig,
+		LintConfig,
+		ParseConfig,
+		ParseErrorAction,
+		PdfConfig,
+		UlStyle,
+	},
 	convert::{
 		self,
 		PDFConverter,
 	},
-	error::Result,
+	error::{
+		Error,
+		Result,
+	},
+	filter,
 	fs,
+	page,
+	template::{
+		self,
+		Page,
+		Template,
+	},
+	theme::{
+		self,
+		ThemePackage,
+	},
+};
+use std::{
+	collections::BTreeMap,
+	env,
+	path::{
+		Component,
+		Path,
+		PathBuf,
+	},
 };
 
 pub mod args;
+pub mod diagnostics;
 pub mod logger;
+pub mod toc;
 
 const HTML_FILE_ENDING: &str = "html";
 const PDF_FILE_ENDING: &str = "pdf";
+const JSON_FILE_ENDING: &str = "json";
 
 /// Runs `smd`.
 pub fn run(cli: Cli) -> Result<()> {
@@ -25,37 +70,950 @@ pub fn run(cli: Cli) -> Result<()> {
 		return Config::initialize();
 	}
 
-	// TODO: use config in parser & convert trait
-	let _config = Config::load_config()?;
+	let config = Config::load_config()?;
 
 	match cli.commands {
 		Commands::Parse(args) => {
 			let content = fs::read_to_string(&args.input)?;
+			let options = gfm::Options {
+				url_resolver: url_resolver(&config.links, &args),
+				link_policy: link_policy(&config.links),
+				images: image_options(&config.images, &args.input),
+				..gfm_options(&config.parse)
+			};
+			let json_input = args
+				.input
+				.extension()
+				.is_some_and(|e| e == JSON_FILE_ENDING);
 
-			info!("Transpiling markdown");
-			let result = gfm::Parser::render(&content);
+			let (meta, markdown) = match json_input {
+				true => (BTreeMap::new(), content.as_str()),
+				false => template::front_matter(&content),
+			};
+			let document = match json_input {
+				true => serde_json::from_str(markdown)
+					.map_err(|e| Error::DeserializeError(e.to_string()))?,
+				false => {
+					info!("Parsing markdown");
+					parse(&args, &options, &content, markdown)?
+				}
+			};
+			let document = apply_filters(document, &config.filters)?;
+			let theme = args
+				.theme
+				.as_ref()
+				.or(config.html.theme_package.as_ref())
+				.map(|name| ThemePackage::load(&theme::themes_dir(), name))
+				.transpose()?;
 
 			let mut out_path = args.input.clone();
-
-			if args.output.eq(&args::OutputFileFormat::Html) {
-				out_path.set_extension(HTML_FILE_ENDING);
-				fs::write_to_file(&out_path, &result)?;
-
-				return Ok(());
-			}
-
-			let converted = match args.output {
+			match args.output {
+				args::OutputFileFormat::Json if json_input => {
+					return Err(Error::CustomError(format!(
+						"{} is already a document tree",
+						args.input.display()
+					)));
+				}
+				args::OutputFileFormat::Json => {
+					out_path.set_extension(JSON_FILE_ENDING);
+					let json = serde_json::to_string_pretty(&document)
+						.map_err(|e| Error::SerializeError(e.to_string()))?;
+					fs::write_to_file(&out_path, &json)?;
+				}
+				args::OutputFileFormat::Html => {
+					info!("Rendering html");
+					let mut html = render_page(
+						document,
+						&args.input,
+						&meta,
+						&stylesheet(&config.html, theme.as_ref(), &args.css)?,
+						&page_template(&config.html, theme.as_ref())?,
+						&options,
+					)?;
+					if args.self_contained || config.html.self_contained {
+						html = bundle::embed_resources(
+							&html,
+							input_dir(&args.input),
+							config.html.size_warning_kib * 1024,
+						);
+					}
+					out_path.set_extension(HTML_FILE_ENDING);
+					fs::write_to_file(&out_path, &html)?;
+				}
 				args::OutputFileFormat::Pdf => {
+					info!("Rendering html");
+					let mut pdf =
+						pdf_config(&config.pdf, theme.as_ref(), &args);
+					let title = page_title(&document, &args.input, &meta);
+					for template in [&mut pdf.header, &mut pdf.footer] {
+						*template = template.as_deref().map(|html| {
+							Template::new(html)
+								.render_header_footer(&title, &meta)
+						});
+					}
+					let mut style =
+						stylesheet(&config.html, theme.as_ref(), &args.css)?;
+					if let Some(margins) = &pdf.margins {
+						style.push_str(&margins.page_rule());
+					}
+					if pdf.plain_first_page {
+						style.push_str(page::PLAIN_FIRST_PAGE);
+					}
+					let html = render_page(
+						document,
+						&args.input,
+						&meta,
+						&style,
+						&page_template(&config.html, theme.as_ref())?,
+						&options,
+					)?;
 					out_path.set_extension(PDF_FILE_ENDING);
-					convert::convert_html::<PDFConverter>(&result)?
+					let converted =
+						convert::convert_html::<PDFConverter>(&html, &pdf)?;
+					fs::write_bytes(&out_path, &converted)?;
 				}
-				_ => unreachable!(),
-			};
-
-			fs::write_bytes(&out_path, &converted)?;
+			}
 		}
 
+		Commands::Lint(args) => lint(&args, &config.lint)?,
+
+		Commands::Theme(args) => manage_themes(&args.command)?,
+
 		_ => unreachable!(),
 	}
 	Ok(())
 }
+
+/// Renders a document into its template, with a table of contents and the
+/// front matter of the markdown.
+fn render_page(
+	mut document: gfm::Document,
+	input: &Path,
+	meta: &BTreeMap<String, String>,
+	style: &str,
+	template: &Template,
+	options: &gfm::Options,
+) -> Result<String> {
+	toc::anchor_headings(&mut document);
+	let title = page_title(&document, input, meta);
+	Ok(template.render(&Page {
+		title: &title,
+		content: &gfm::Parser::render_document(&document, options),
+		toc: &toc::render(&document),
+		style,
+		meta,
+	}))
+}
+
+/// Title of a page: the one of the front matter, the first heading or the
+/// name of the file, in that order.
+fn page_title(
+	document: &gfm::Document,
+	input: &Path,
+	meta: &BTreeMap<String, String>,
+) -> String {
+	meta.get("title")
+		.cloned()
+		.or_else(|| toc::title(document))
+		.or_else(|| input.file_stem().map(|s| s.to_string_lossy().into_owned()))
+		.unwrap_or_default()
+}
+
+/// Template of html and pdf output: the configured one, the one of the theme
+/// package or the built-in one, in that order.
+fn page_template(
+	config: &HtmlConfig,
+	theme: Option<&ThemePackage>,
+) -> Result<Template> {
+	match (&config.template, theme) {
+		(Some(path), _) => Template::load(path),
+		(None, Some(theme)) => theme.template(),
+		(None, None) => Ok(Template::default()),
+	}
+}
+
+/// Stylesheet of html and pdf output: the built-in one, the print
+/// stylesheet, those of the theme package and those that are configured or
+/// given as arguments, in that order. The local fonts and images they refer
+/// to are embedded.
+fn stylesheet(
+	config: &HtmlConfig,
+	theme: Option<&ThemePackage>,
+	css: &[PathBuf],
+) -> Result<String> {
+	let colors = theme
+		.and_then(|theme| theme.manifest.colors)
+		.unwrap_or(config.theme);
+	let mut stylesheet = colors.stylesheet();
+	if config.print_stylesheet {
+		stylesheet.push_str(template::PRINT_STYLESHEET);
+	}
+	if let Some(theme) = theme {
+		stylesheet.push_str(&theme.stylesheet()?);
+	}
+	for path in config.stylesheets.iter().chain(css.iter()) {
+		let css = fs::read_to_string(path)?;
+		stylesheet.push_str(&bundle::embed_css_urls(&css, input_dir(path)));
+		if !stylesheet.ends_with('\n') {
+			stylesheet.push('\n');
+		}
+	}
+	Ok(stylesheet)
+}
+
+/// Page setup of pdf output: the configured one, overridden by the
+/// arguments. Unless they are set, the margins are those of the theme
+/// package.
+fn pdf_config(
+	config: &PdfConfig,
+	theme: Option<&ThemePackage>,
+	args: &ParseArgs,
+) -> PdfConfig {
+	PdfConfig {
+		page_size:        args.page_size.unwrap_or(config.page_size),
+		orientation:      args.orientation.unwrap_or(config.orientation),
+		margins:          args
+			.margins
+			.or(config.margins)
+			.or_else(|| theme.and_then(|theme| theme.manifest.pdf.margins)),
+		scale:            args.scale.unwrap_or(config.scale),
+		print_background: config.print_background && !args.no_background,
+		page_ranges:      args
+			.page_ranges
+			.clone()
+			.or_else(|| config.page_ranges.clone()),
+		header:           args.header.clone().or_else(|| config.header.clone()),
+		footer:           args.footer.clone().or_else(|| config.footer.clone()),
+		plain_first_page: config.plain_first_page || args.plain_first_page,
+	}
+}
+
+/// Directory of the markdown file or stylesheet, which relative paths are
+/// resolved against.
+fn input_dir(input: &Path) -> &Path {
+	input
+		.parent()
+		.filter(|p| !p.as_os_str().is_empty())
+		.unwrap_or(Path::new("."))
+}
+
+/// Maps the parse configuration onto the options of the parser.
+fn gfm_options(config: &ParseConfig) -> gfm::Options {
+	gfm::Options {
+		// bare urls are links on GitHub as well
+		autolinks: true,
+		on_parse_error: match config.on_parse_error {
+			ParseErrorAction::Abort => gfm::ParseErrorAction::Abort,
+			ParseErrorAction::Skip => gfm::ParseErrorAction::Skip,
+			ParseErrorAction::Serialize => gfm::ParseErrorAction::Serialize,
+		},
+		..Default::default()
+	}
+}
+
+/// Rewrites urls as configured, with the arguments taking precedence over
+/// the configuration.
+fn url_resolver(config: &LinksConfig, args: &ParseArgs) -> gfm::UrlResolver {
+	let base_url = args.base_url.as_ref().or(config.base_url.as_ref());
+	let markdown_to_html = args.markdown_to_html || config.markdown_to_html;
+	let repositories: Vec<&String> = config
+		.repository_urls
+		.iter()
+		.chain(args.repository_url.iter())
+		.collect();
+	if base_url.is_none() && !markdown_to_html && repositories.is_empty() {
+		return gfm::UrlResolver::default();
+	}
+
+	let mut rewriter =
+		gfm::UrlRewriter::default().markdown_to_html(markdown_to_html);
+	if let Some(base_url) = base_url {
+		rewriter = rewriter.base(base_url);
+	}
+	for repository in repositories.iter() {
+		rewriter = rewriter.repository(repository);
+	}
+	if !repositories.is_empty() {
+		rewriter = rewriter.document(&document_path(&args.input));
+	}
+	gfm::UrlResolver::new(rewriter)
+}
+
+/// Path of the input in the repository, which is taken to be the current
+/// directory, eg. `docs/guide.md`. Inputs outside of it are taken to be at
+/// its root.
+fn document_path(input: &Path) -> String {
+	let relative = match input.is_absolute() {
+		true => env::current_dir()
+			.ok()
+			.and_then(|dir| input.strip_prefix(dir).ok()),
+		false => Some(input),
+	};
+	let components = relative.and_then(|path| {
+		path.components()
+			.filter(|c| *c != Component::CurDir)
+			.map(|c| match c {
+				Component::Normal(name) => name.to_str(),
+				_ => None,
+			})
+			.collect::<Option<Vec<_>>>()
+	});
+	match components {
+		Some(components) => components.join("/"),
+		None => input
+			.file_name()
+			.map(|name| name.to_string_lossy().into_owned())
+			.unwrap_or_default(),
+	}
+}
+
+/// Allowed schemes and link attributes as configured.
+fn link_policy(config: &LinksConfig) -> gfm::LinkPolicy {
+	let attributes = |config: &LinkAttributesConfig| gfm::LinkAttributes {
+		target: config.target.clone(),
+		rel:    config.rel.clone(),
+	};
+	let mut policy = gfm::LinkPolicy::default()
+		.internal(attributes(&config.internal))
+		.external(attributes(&config.external));
+	if !config.allowed_schemes.is_empty() {
+		let schemes: Vec<&str> =
+			config.allowed_schemes.iter().map(String::as_str).collect();
+		policy = policy.schemes(&schemes);
+	}
+	for host in config.internal_hosts.iter() {
+		policy = policy.internal_host(host);
+	}
+	policy
+}
+
+/// Image options as configured. Local images are looked up relative to the
+/// markdown file.
+fn image_options(config: &ImagesConfig, input: &Path) -> gfm::ImageOptions {
+	gfm::ImageOptions {
+		lazy:       config.lazy_loading,
+		figures:    config.figures,
+		local_root: config.detect_size.then(|| input_dir(input).to_path_buf()),
+	}
+}
+
+/// Parses the markdown, which is the end of `content` after its front
+/// matter, and prints what was kept as text with the lines of `content` it
+/// was found on. Fails on errors, on warnings if they are denied and on
+/// anything kept as text if parse errors abort.
+fn parse(
+	args: &ParseArgs,
+	options: &gfm::Options,
+	content: &str,
+	markdown: &str,
+) -> Result<gfm::Document> {
+	if markdown.len() > options.max_input_size {
+		let error = gfm::Error::InputTooLarge {
+			size:  markdown.len(),
+			limit: options.max_input_size,
+		};
+		return Err(Error::ParseError(error.to_string()));
+	}
+
+	let (document, mut diagnostics) =
+		gfm::Parser::parse_with_diagnostics(markdown, options);
+	let front_matter = content.len() - markdown.len();
+	for diagnostic in diagnostics.iter_mut() {
+		let span = &mut diagnostic.span;
+		*span = span.start + front_matter..span.end + front_matter;
+	}
+	for diagnostic in diagnostics.iter() {
+		eprintln!(
+			"{}\n",
+			diagnostics::code_frame(&args.input, content, diagnostic)
+		);
+	}
+
+	if let (gfm::ParseErrorAction::Abort, Some(first)) =
+		(options.on_parse_error, diagnostics.first())
+	{
+		let (line, column) = first.line_column(content);
+		return Err(Error::ParseError(format!(
+			"{}:{}:{}: {}",
+			args.input.display(),
+			line,
+			column,
+			first.message
+		)));
+	}
+
+	let errors = diagnostics
+		.iter()
+		.filter(|d| d.severity == Severity::Error)
+		.count();
+	let warnings = diagnostics.len() - errors;
+	if errors > 0 || (args.deny_warnings && warnings > 0) {
+		return Err(Error::ParseError(format!(
+			"{} error(s) and {} warning(s) in {}",
+			errors,
+			warnings,
+			args.input.display()
+		)));
+	}
+	Ok(document)
+}
+
+/// Pipes the document through every filter, in order, as mdast JSON. What a
+/// filter writes is read back as the input of the next one.
+fn apply_filters(
+	mut document: gfm::Document,
+	filters: &[FilterConfig],
+) -> Result<gfm::Document> {
+	for config in filters.iter() {
+		info!("Running filter {}", config);
+		let json = serde_json::to_string(&document)
+			.map_err(|e| Error::SerializeError(e.to_string()))?;
+		let output = filter::run_filter(config, &json)?;
+		document = serde_json::from_str(&output).map_err(|e| {
+			Error::FilterError(format!(
+				"\"{}\" returned an invalid document: {}",
+				config, e
+			))
+		})?;
+	}
+	Ok(document)
+}
+
+/// A lint as it is printed by `smd lint --format json`.
+#[derive(Debug, Serialize)]
+struct LintReport<'a> {
+	path:    String,
+	line:    usize,
+	column:  usize,
+	code:    &'static str,
+	rule:    &'static str,
+	message: &'a str,
+}
+
+/// Maps the per-rule configuration onto the options of the linter.
+fn lint_options(config: &LintConfig) -> gfm::LintOptions {
+	use gfm::Rule;
+
+	let enabled = [
+		(Rule::HeadingIncrement, config.heading_increment.enabled),
+		(Rule::UlStyle, config.ul_style.enabled),
+		(Rule::NoTrailingSpaces, config.no_trailing_spaces.enabled),
+		(Rule::LineLength, config.line_length.enabled),
+		(
+			Rule::NoDuplicateHeading,
+			config.no_duplicate_heading.enabled,
+		),
+		(Rule::NoBareUrls, config.no_bare_urls.enabled),
+		(Rule::NoEmptyLinks, config.no_empty_links.enabled),
+		(Rule::NoAltText, config.no_alt_text.enabled),
+	];
+	gfm::LintOptions {
+		disabled:    enabled
+			.into_iter()
+			.filter(|(_, enabled)| !enabled)
+			.map(|(rule, _)| rule)
+			.collect(),
+		line_length: config.line_length.line_length,
+		br_spaces:   config.no_trailing_spaces.br_spaces,
+		list_marker: match config.ul_style.style {
+			UlStyle::Consistent => None,
+			UlStyle::Dash => Some('-'),
+			UlStyle::Asterisk => Some('*'),
+			UlStyle::Plus => Some('+'),
+		},
+	}
+}
+
+/// Lints every input and prints the result. Fails if anything was found.
+fn lint(args: &LintArgs, config: &LintConfig) -> Result<()> {
+	let options = lint_options(config);
+	let mut sources = Vec::with_capacity(args.input.len());
+	for path in args.input.iter() {
+		let content = fs::read_to_string(path)?;
+		let lints = gfm::Parser::lint(&content, &options);
+		sources.push((path, content, lints));
+	}
+
+	match args.format {
+		LintFormat::Human => {
+			for (path, content, lints) in sources.iter() {
+				for lint in lints.iter() {
+					eprintln!(
+						"{}\n",
+						diagnostics::lint_frame(path, content, lint)
+					);
+				}
+			}
+		}
+		LintFormat::Json => {
+			let reports: Vec<LintReport> = sources
+				.iter()
+				.flat_map(|(path, content, lints)| {
+					lints.iter().map(move |lint| {
+						let (line, column) = lint.line_column(content);
+						LintReport {
+							path: path.display().to_string(),
+							line,
+							column,
+							code: lint.rule.code(),
+							rule: lint.rule.name(),
+							message: &lint.message,
+						}
+					})
+				})
+				.collect();
+			let json = serde_json::to_string_pretty(&reports)
+				.map_err(|e| Error::SerializeError(e.to_string()))?;
+			println!("{}", json);
+		}
+	}
+
+	let found: usize = sources.iter().map(|(_, _, lints)| lints.len()).sum();
+	if found > 0 {
+		return Err(Error::ParseError(format!(
+			"{} lint(s) in {} file(s)",
+			found,
+			sources.iter().filter(|(_, _, l)| !l.is_empty()).count()
+		)));
+	}
+	Ok(())
+}
+
+/// Lists, creates or shows the themes in the configuration directory.
+fn manage_themes(command: &ThemeCommands) -> Result<()> {
+	let themes = theme::themes_dir();
+	match command {
+		ThemeCommands::List => {
+			let packages = ThemePackage::list(&themes)?;
+			if packages.is_empty() {
+				info!("No themes in {}", themes.display());
+			}
+			let width = packages.iter().map(|p| p.name.len()).max();
+			for package in packages.iter() {
+				println!(
+					"{:width$}  {}",
+					package.name,
+					package.manifest.description,
+					width = width.unwrap_or_default()
+				);
+			}
+		}
+		ThemeCommands::New { name } => {
+			let package = ThemePackage::create(&themes, name)?;
+			info!("Created theme `{}`", package.name);
+			println!("{}", package.dir.display());
+		}
+		ThemeCommands::Show { name } => {
+			let package = ThemePackage::load(&themes, name)?;
+			println!("{}", describe_theme(&package)?);
+		}
+	}
+	Ok(())
+}
+
+/// Where a theme is, its `theme.toml` and its files, as `smd theme show`
+/// prints them.
+fn describe_theme(package: &ThemePackage) -> Result<String> {
+	let manifest = fs::read_to_string(&package.dir.join(theme::MANIFEST))?;
+	let mut description = format!(
+		"{}\n{}\n\n{}",
+		package.name,
+		package.dir.display(),
+		manifest.trim_end()
+	);
+	description.push_str("\n\nFiles:");
+	for file in package.files()?.iter() {
+		description.push_str(&format!("\n  {}", file.display()));
+	}
+	Ok(description)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use smd_core::{
+		page::PageSize,
+		template::Theme,
+	};
+	use std::path::PathBuf;
+
+	fn parse_args() -> ParseArgs {
+		ParseArgs {
+			input:            PathBuf::from("doc.md"),
+			output:           args::OutputFileFormat::Html,
+			deny_warnings:    false,
+			base_url:         None,
+			markdown_to_html: false,
+			repository_url:   Vec::new(),
+			self_contained:   false,
+			theme:            None,
+			css:              Vec::new(),
+			page_size:        None,
+			orientation:      None,
+			margins:          None,
+			scale:            None,
+			no_background:    false,
+			page_ranges:      None,
+			header:           None,
+			footer:           None,
+			plain_first_page: false,
+		}
+	}
+
+	/// Directory of a test in the temporary directory, unique to the process.
+	fn temp_dir(test: &str) -> PathBuf {
+		env::temp_dir().join(format!("smd-{}-{}", test, std::process::id()))
+	}
+
+	fn render_with(action: ParseErrorAction, content: &str) -> Result<String> {
+		let args = parse_args();
+		let options = gfm_options(&ParseConfig {
+			on_parse_error:  action,
+			keep_temp_files: false,
+		});
+		let (_, markdown) = template::front_matter(content);
+		let document = parse(&args, &options, content, markdown)?;
+		Ok(gfm::Parser::render_document(&document, &options))
+	}
+
+	#[test]
+	fn lint_config_disables_and_tunes_rules() {
+		let mut config = LintConfig::default();
+		config.no_bare_urls.enabled = false;
+		config.line_length.line_length = 100;
+		config.ul_style.style = UlStyle::Plus;
+
+		let options = lint_options(&config);
+		assert_eq!(options.disabled, vec![gfm::Rule::NoBareUrls]);
+		assert_eq!(options.line_length, 100);
+		assert_eq!(options.br_spaces, 2);
+		assert_eq!(options.list_marker, Some('+'));
+	}
+
+	#[test]
+	fn links_are_rewritten_as_configured() {
+		let config = LinksConfig {
+			base_url: Some("https://intranet/docs/".to_string()),
+			markdown_to_html: false,
+			repository_urls: vec![
+				"https://github.com/x/y/blob/main".to_string()
+			],
+			..Default::default()
+		};
+		let args = ParseArgs {
+			base_url: Some("https://x.github.io/y/".to_string()),
+			markdown_to_html: true,
+			..parse_args()
+		};
+		let options = gfm::Options {
+			url_resolver: url_resolver(&config, &args),
+			..Default::default()
+		};
+		assert_eq!(
+			gfm::Parser::render_with_options(
+				"[a](a.md) [b](https://github.com/x/y/blob/main/b.md) \
+				 ![c](c.png)",
+				&options
+			),
+			concat!(
+				"<p><a href=\"https://x.github.io/y/a.html\">a</a> ",
+				"<a href=\"https://x.github.io/y/b.html\">b</a> ",
+				"<img src=\"https://x.github.io/y/c.png\" alt=\"c\" ",
+				"referrerpolicy=\"no-referrer\"></p>\n"
+			)
+		);
+		let unset = url_resolver(&LinksConfig::default(), &parse_args());
+		assert_eq!(unset, gfm::UrlResolver::default());
+	}
+
+	#[test]
+	fn repository_urls_are_relative_to_the_input() {
+		let args = ParseArgs {
+			input: PathBuf::from("./docs/guide.md"),
+			repository_url: vec!["https://github.com/o/r/blob/main/".into()],
+			markdown_to_html: true,
+			..parse_args()
+		};
+		let options = gfm::Options {
+			url_resolver: url_resolver(&LinksConfig::default(), &args),
+			..Default::default()
+		};
+		assert_eq!(
+			gfm::Parser::render_with_options(
+				"[a](https://github.com/o/r/blob/main/docs/a.md)",
+				&options
+			),
+			"<p><a href=\"a.html\">a</a></p>\n"
+		);
+		assert_eq!(document_path(Path::new("a.md")), "a.md");
+		assert_eq!(document_path(Path::new("../a/b.md")), "b.md");
+		let absolute = env::current_dir().unwrap().join("docs/c.md");
+		assert_eq!(document_path(&absolute), "docs/c.md");
+	}
+
+	#[test]
+	fn local_images_are_looked_up_next_to_the_input() {
+		let config = ImagesConfig {
+			detect_size: true,
+			..Default::default()
+		};
+		let root = |input: &str| {
+			image_options(&config, Path::new(input)).local_root.unwrap()
+		};
+		assert_eq!(root("docs/a.md"), PathBuf::from("docs"));
+		assert_eq!(root("a.md"), PathBuf::from("."));
+		let unset = image_options(&ImagesConfig::default(), Path::new("a.md"));
+		assert_eq!(unset, gfm::ImageOptions::default());
+	}
+
+	#[test]
+	fn pages_are_rendered_into_the_template() {
+		let dir = temp_dir("template");
+		std::fs::create_dir_all(&dir).unwrap();
+		let template = dir.join("page.html");
+		std::fs::write(
+			&template,
+			"<h1>{{ title }}</h1><p>{{ meta.author }}</p>{{ toc }}{{ content \
+			 }}",
+		)
+		.unwrap();
+		let config = HtmlConfig {
+			template: Some(template),
+			..Default::default()
+		};
+		let (meta, markdown) =
+			template::front_matter("---\nauthor: a\n---\n## B c\n\nd\n");
+		let document = gfm::Parser::parse(markdown, &gfm::Options::default());
+		let page = render_page(
+			document,
+			Path::new("docs/guide.md"),
+			&meta,
+			"",
+			&page_template(&config, None).unwrap(),
+			&gfm::Options::default(),
+		);
+		std::fs::remove_dir_all(&dir).unwrap();
+		assert_eq!(
+			page.unwrap(),
+			concat!(
+				"<h1>B c</h1><p>a</p>",
+				"<nav class=\"toc\">\n<ul>\n<li><a href=\"#b-c\">B c</a>",
+				"</li>\n</ul>\n</nav>\n",
+				"<h2 id=\"b-c\">B c</h2>\n<p>d</p>\n"
+			)
+		);
+	}
+
+	#[test]
+	fn pages_have_a_title_and_stylesheet() {
+		let page = render_page(
+			gfm::Parser::parse("a", &gfm::Options::default()),
+			Path::new("docs/guide.md"),
+			&BTreeMap::new(),
+			&stylesheet(&HtmlConfig::default(), None, &[]).unwrap(),
+			&Template::default(),
+			&gfm::Options::default(),
+		)
+		.unwrap();
+		assert!(page.starts_with("<!DOCTYPE html>\n"));
+		assert!(page.contains("<title>guide</title>"));
+		assert!(page.contains(&Theme::Auto.stylesheet()));
+		assert!(page.contains(template::PRINT_STYLESHEET));
+		assert!(page.contains("<main>\n<p>a</p>\n</main>"));
+	}
+
+	#[test]
+	fn stylesheets_are_added_in_order() {
+		let dir = temp_dir("css");
+		std::fs::create_dir_all(dir.join("fonts")).unwrap();
+		std::fs::write(dir.join("fonts/a.woff2"), "a").unwrap();
+		std::fs::write(
+			dir.join("a.css"),
+			"@font-face { src: url(fonts/a.woff2); }",
+		)
+		.unwrap();
+		std::fs::write(dir.join("b.css"), "p { color: red; }\n").unwrap();
+		let config = HtmlConfig {
+			theme: Theme::None,
+			print_stylesheet: false,
+			stylesheets: vec![dir.join("a.css")],
+			..Default::default()
+		};
+		let css = stylesheet(&config, None, &[dir.join("b.css")]);
+		let missing = stylesheet(&config, None, &[dir.join("c.css")]);
+		std::fs::remove_dir_all(&dir).unwrap();
+		assert_eq!(
+			css.unwrap(),
+			concat!(
+				"@font-face { src: url(\"data:font/woff2;base64,YQ==\"); }\n",
+				"p { color: red; }\n"
+			)
+		);
+		assert!(missing.is_err());
+	}
+
+	#[test]
+	fn theme_packages_replace_the_built_in_look() {
+		let themes = temp_dir("theme");
+		let mut package = ThemePackage::create(&themes, "a").unwrap();
+		std::fs::write(package.dir.join("style.css"), "p {}").unwrap();
+		package.manifest.colors = Some(Theme::None);
+		let config = HtmlConfig {
+			print_stylesheet: false,
+			..Default::default()
+		};
+		let css = stylesheet(&config, Some(&package), &[]);
+		let template = page_template(&config, Some(&package));
+		let description = describe_theme(&package);
+		std::fs::remove_dir_all(&themes).unwrap();
+
+		assert_eq!(css.unwrap(), "p {}\n");
+		assert_eq!(template.unwrap(), Template::default());
+		let description = description.unwrap();
+		assert!(description.starts_with(&format!(
+			"a\n{}\n\n# A theme for smd.",
+			package.dir.display()
+		)));
+		let files = "\n\nFiles:\n  style.css\n  template.html\n  theme.toml";
+		assert!(description.ends_with(files));
+	}
+
+	#[test]
+	fn arguments_override_the_page_setup() {
+		let config = PdfConfig {
+			margins: Some("1in".parse().unwrap()),
+			page_ranges: Some("1".to_string()),
+			footer: Some("{{ page }}".to_string()),
+			..Default::default()
+		};
+		let args = ParseArgs {
+			page_size: Some(PageSize::A4),
+			scale: Some(0.5),
+			no_background: true,
+			header: Some("{{ title }}".to_string()),
+			plain_first_page: true,
+			..parse_args()
+		};
+		assert_eq!(pdf_config(&config, None, &args), PdfConfig {
+			page_size: PageSize::A4,
+			scale: 0.5,
+			print_background: false,
+			header: Some("{{ title }}".to_string()),
+			plain_first_page: true,
+			..config.clone()
+		});
+
+		let themes = temp_dir("pdf-theme");
+		let mut package = ThemePackage::create(&themes, "a").unwrap();
+		std::fs::remove_dir_all(&themes).unwrap();
+		package.manifest.pdf.margins = Some("2cm".parse().unwrap());
+		let margins = |config: &PdfConfig, args: &ParseArgs| {
+			pdf_config(config, Some(&package), args).margins.unwrap()
+		};
+		assert_eq!(margins(&config, &parse_args()).to_string(), "1in");
+		let unset = PdfConfig::default();
+		assert_eq!(margins(&unset, &parse_args()).to_string(), "2cm");
+		let args = ParseArgs {
+			margins: Some("3mm".parse().unwrap()),
+			..parse_args()
+		};
+		assert_eq!(margins(&config, &args).to_string(), "3mm");
+	}
+
+	#[test]
+	fn links_follow_the_configured_policy() {
+		let config = LinksConfig {
+			allowed_schemes: vec!["https".to_string(), "mailto".to_string()],
+			internal_hosts: vec!["docs.x.y".to_string()],
+			external: LinkAttributesConfig {
+				target: Some("_blank".to_string()),
+				rel:    Some("noopener noreferrer nofollow".to_string()),
+			},
+			..Default::default()
+		};
+		let options = gfm::Options {
+			link_policy: link_policy(&config),
+			..Default::default()
+		};
+		assert_eq!(
+			gfm::Parser::render_with_options(
+				"[a](https://docs.x.y/a) [b](https://z/b) [c](ftp://z/c) \
+				 [d](mailto:d@x.y) [e](e.html)",
+				&options
+			),
+			concat!(
+				"<p><a href=\"https://docs.x.y/a\">a</a> ",
+				"<a href=\"https://z/b\" target=\"_blank\" ",
+				"rel=\"noopener noreferrer nofollow\">b</a> c ",
+				"<a href=\"mailto:d@x.y\">d</a> ",
+				"<a href=\"e.html\">e</a></p>\n"
+			)
+		);
+		assert_eq!(
+			link_policy(&LinksConfig::default()),
+			gfm::LinkPolicy::default()
+		);
+	}
+
+	fn shell(script: &str) -> FilterConfig {
+		FilterConfig {
+			command:      "sh".to_string(),
+			args:         vec!["-c".to_string(), script.to_string()],
+			timeout_secs: 5,
+		}
+	}
+
+	#[test]
+	fn filters_transform_the_document_in_order() {
+		let options = gfm::Options::default();
+		let document = gfm::Parser::parse("# foo\n\nbar", &options);
+		let filters = [shell("sed s/foo/baz/g"), shell("sed s/baz/qux/g")];
+		let document = apply_filters(document, &filters).unwrap();
+		assert_eq!(
+			gfm::Parser::render_document(&document, &options),
+			"<h1>qux</h1>\n<p>bar</p>\n"
+		);
+	}
+
+	#[test]
+	fn filters_must_return_a_document() {
+		let document = gfm::Parser::parse("foo", &gfm::Options::default());
+		let error = apply_filters(document, &[shell("echo '[]'")])
+			.unwrap_err()
+			.to_string();
+		assert!(
+			error.contains("\"sh -c echo '[]'\" returned an invalid document"),
+			"{}",
+			error
+		);
+	}
+
+	#[test]
+	fn parse_error_actions() {
+		let content = "foo\n\n`bar";
+		assert_eq!(
+			render_with(ParseErrorAction::Serialize, content).unwrap(),
+			"<p>foo</p>\n<p>`bar</p>\n"
+		);
+		assert_eq!(
+			render_with(ParseErrorAction::Skip, content).unwrap(),
+			"<p>foo</p>\n<p>bar</p>\n"
+		);
+		match render_with(ParseErrorAction::Abort, content) {
+			Err(Error::ParseError(e)) => assert!(e.starts_with("doc.md:3:1: ")),
+			result => panic!("expected a parse error, got {:?}", result),
+		}
+	}
+
+	#[test]
+	fn parse_errors_point_past_the_front_matter() {
+		let content = "---
+title: a
+---
+foo `bar";
+		match render_with(ParseErrorAction::Abort, content) {
+			Err(Error::ParseError(e)) => assert!(e.starts_with("doc.md:4:5: ")),
+			result => panic!("expected a parse error, got {:?}", result),
+		}
+	}
+}
@@ -0,0 +1,172 @@
+use gfm::{
+	Block,
+	Custom,
+	Document,
+	Image,
+	Inline,
+};
+use std::collections::HashSet;
+
+/// Gives every top level heading without an id one made from its text, like
+/// GitHub does, so the table of contents can link to it.
+pub fn anchor_headings(document: &mut Document) {
+	let mut taken: HashSet<String> = document
+		.blocks
+		.iter()
+		.filter_map(|block| match block {
+			Block::Header(_, _, Some(id)) => Some(id.clone()),
+			_ => None,
+		})
+		.collect();
+	for block in document.blocks.iter_mut() {
+		let Block::Header(_, content, id @ None) = block else {
+			continue;
+		};
+		let slug = slug(&plain_text(content));
+		let mut unique = slug.clone();
+		let mut n = 0;
+		while !taken.insert(unique.clone()) {
+			n += 1;
+			unique = format!("{}-{}", slug, n);
+		}
+		*id = Some(unique);
+	}
+}
+
+/// Renders the top level headings that have an id as nested lists of links.
+pub fn render(document: &Document) -> String {
+	let headings: Vec<(usize, String, &str)> = document
+		.blocks
+		.iter()
+		.filter_map(|block| match block {
+			Block::Header(level, content, Some(id)) => {
+				Some((*level, plain_text(content), id.as_str()))
+			}
+			_ => None,
+		})
+		.collect();
+	if headings.is_empty() {
+		return String::new();
+	}
+
+	let mut html = String::from("<nav class=\"toc\">\n");
+	let mut open: Vec<usize> = Vec::new();
+	for (level, text, id) in headings.into_iter() {
+		while open.last().is_some_and(|&l| l > level) {
+			html.push_str("</li>\n</ul>\n");
+			open.pop();
+		}
+		if open.last() == Some(&level) {
+			html.push_str("</li>\n");
+		} else {
+			html.push_str("<ul>\n");
+			open.push(level);
+		}
+		html.push_str(&format!(
+			"<li><a href=\"#{}\">{}</a>",
+			escape(id),
+			escape(&text)
+		));
+	}
+	for _ in open.iter() {
+		html.push_str("</li>\n</ul>\n");
+	}
+	html.push_str("</nav>\n");
+	html
+}
+
+/// Text of the first top level heading, which is the title of the page.
+pub fn title(document: &Document) -> Option<String> {
+	document.blocks.iter().find_map(|block| match block {
+		Block::Header(_, content, _) => Some(plain_text(content)),
+		_ => None,
+	})
+}
+
+/// Concatenates the text of inline content without any markup.
+fn plain_text(inlines: &[Inline]) -> String {
+	let mut text = String::new();
+	for inline in inlines.iter() {
+		match inline {
+			Inline::Text(t) | Inline::Code(t) | Inline::Strikethrough(t) => {
+				text.push_str(t)
+			}
+			Inline::Autolink(_, t) => text.push_str(t),
+			Inline::Emphasis(inner) |
+			Inline::Strong(inner) |
+			Inline::Link(_, inner, _) |
+			Inline::Custom(Custom {
+				children: inner, ..
+			}) => text.push_str(&plain_text(inner)),
+			Inline::Image(Image { alt: Some(alt), .. }) => text.push_str(alt),
+			Inline::LineBreak | Inline::SoftBreak => text.push(' '),
+			_ => {}
+		}
+	}
+	text
+}
+
+/// Id of a heading as GitHub makes it, eg. `foo-bar` of `Foo: Bar`.
+fn slug(text: &str) -> String {
+	text.trim()
+		.to_lowercase()
+		.chars()
+		.filter_map(|c| match c {
+			' ' => Some('-'),
+			'-' | '_' => Some(c),
+			c if c.is_alphanumeric() => Some(c),
+			_ => None,
+		})
+		.collect()
+}
+
+/// Escapes text for html.
+fn escape(text: &str) -> String {
+	text.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse(source: &str) -> Document {
+		gfm::Parser::parse(source, &gfm::Options::default())
+	}
+
+	#[test]
+	fn headings_get_unique_ids() {
+		let mut document = parse("# Foo: *Bar*\n\n## Foo bar\n\n## Foo bar\n");
+		anchor_headings(&mut document);
+		let ids: Vec<_> = document
+			.blocks
+			.iter()
+			.filter_map(|b| match b {
+				Block::Header(_, _, id) => id.clone(),
+				_ => None,
+			})
+			.collect();
+		assert_eq!(ids, ["foo-bar", "foo-bar-1", "foo-bar-2"]);
+	}
+
+	#[test]
+	fn toc_nests_by_level() {
+		let mut document = parse("# A\n\n## B\n\n### C\n\n## D & E\n\n# F");
+		anchor_headings(&mut document);
+		assert_eq!(
+			render(&document),
+			concat!(
+				"<nav class=\"toc\">\n<ul>\n",
+				"<li><a href=\"#a\">A</a><ul>\n",
+				"<li><a href=\"#b\">B</a><ul>\n",
+				"<li><a href=\"#c\">C</a></li>\n</ul>\n</li>\n",
+				"<li><a href=\"#d--e\">D &amp; E</a></li>\n</ul>\n</li>\n",
+				"<li><a href=\"#f\">F</a></li>\n</ul>\n</nav>\n"
+			)
+		);
+		assert_eq!(title(&document).as_deref(), Some("A"));
+		assert_eq!(render(&parse("a")), "");
+	}
+}